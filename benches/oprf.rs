@@ -9,20 +9,29 @@ extern crate criterion;
 use criterion::Criterion;
 use curve25519_dalek::ristretto::RistrettoPoint;
 use generic_array::arr;
+use generic_bytes::SizedBytes;
 use opaque_ke::{
-    group::Group,
-    oprf::{blind_shim, evaluate_shim, finalize_shim},
+    keypair::PrivateKey,
+    oprf::{OprfClient, OprfServer},
 };
 use rand::{prelude::ThreadRng, thread_rng};
 use sha2::Sha512;
 
+fn server() -> OprfServer<RistrettoPoint, Sha512> {
+    let salt_bytes = arr![
+        u8; 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
+        24, 25, 26, 27, 28, 29, 30, 31, 32,
+    ];
+    OprfServer::new_with_key(&PrivateKey::from_arr(&salt_bytes).unwrap()).unwrap()
+}
+
 fn oprf1(c: &mut Criterion) {
     let mut csprng: ThreadRng = thread_rng();
     let input = b"hunter2";
 
     c.bench_function("blind with Ristretto", move |b| {
         b.iter(|| {
-            blind_shim::<_, RistrettoPoint, Sha512>(&input[..], &mut csprng).unwrap();
+            OprfClient::<RistrettoPoint, Sha512>::blind(&input[..], &mut csprng).unwrap();
         })
     });
 }
@@ -31,16 +40,12 @@ fn oprf2(c: &mut Criterion) {
     let mut csprng: ThreadRng = thread_rng();
     let input = b"hunter2";
 
-    let (_, alpha) = blind_shim::<_, RistrettoPoint, Sha512>(&input[..], &mut csprng).unwrap();
-    let salt_bytes = arr![
-        u8; 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-        24, 25, 26, 27, 28, 29, 30, 31, 32,
-    ];
-    let salt = RistrettoPoint::from_scalar_slice(&salt_bytes).unwrap();
+    let (_, alpha) = OprfClient::<RistrettoPoint, Sha512>::blind(&input[..], &mut csprng).unwrap();
+    let server = server();
 
     c.bench_function("evaluate with Ristretto", move |b| {
         b.iter(|| {
-            let _beta = evaluate_shim::<RistrettoPoint>(alpha, &salt);
+            let _beta = server.evaluate(&alpha);
         })
     });
 }
@@ -49,20 +54,50 @@ fn oprf3(c: &mut Criterion) {
     let mut csprng: ThreadRng = thread_rng();
     let input = b"hunter2";
 
-    let (token, alpha) = blind_shim::<_, RistrettoPoint, Sha512>(&input[..], &mut csprng).unwrap();
-    let salt_bytes = arr![
-        u8; 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16, 17, 18, 19, 20, 21, 22, 23,
-        24, 25, 26, 27, 28, 29, 30, 31, 32,
-    ];
-    let salt = RistrettoPoint::from_scalar_slice(&salt_bytes).unwrap();
-    let beta = evaluate_shim::<RistrettoPoint>(alpha, &salt);
+    let (client, alpha) =
+        OprfClient::<RistrettoPoint, Sha512>::blind(&input[..], &mut csprng).unwrap();
+    let beta = server().evaluate(&alpha);
 
     c.bench_function("finalize with Ristretto", move |b| {
         b.iter(|| {
-            let _res = finalize_shim::<RistrettoPoint, Sha512>(&token, beta).unwrap();
+            let _res = client.finalize(&beta).unwrap();
+        })
+    });
+}
+
+fn oprf4(c: &mut Criterion) {
+    let mut csprng: ThreadRng = thread_rng();
+    let input = b"hunter2";
+
+    let (_, alpha) =
+        OprfClient::<RistrettoPoint, Sha512>::blind_verifiable(&input[..], &mut csprng).unwrap();
+    let server = server();
+
+    c.bench_function("verifiable evaluate with Ristretto", move |b| {
+        b.iter(|| {
+            let _beta = server.evaluate_verifiable(&alpha, &mut csprng).unwrap();
+        })
+    });
+}
+
+fn oprf5(c: &mut Criterion) {
+    let mut csprng: ThreadRng = thread_rng();
+    let input = b"hunter2";
+
+    let (client, alpha) =
+        OprfClient::<RistrettoPoint, Sha512>::blind_verifiable(&input[..], &mut csprng).unwrap();
+    let server = server();
+    let public_key = server.public_key();
+    let (beta, proof) = server.evaluate_verifiable(&alpha, &mut csprng).unwrap();
+
+    c.bench_function("verifiable finalize with Ristretto", move |b| {
+        b.iter(|| {
+            let _res = client
+                .finalize_verifiable(&beta, &proof, &public_key)
+                .unwrap();
         })
     });
 }
 
-criterion_group!(oprf_benches, oprf1, oprf2, oprf3);
+criterion_group!(oprf_benches, oprf1, oprf2, oprf3, oprf4, oprf5);
 criterion_main!(oprf_benches);
@@ -35,8 +35,8 @@ use opaque_ke::{
     rand::{rngs::OsRng, RngCore},
     ClientLogin, ClientLoginFinishParameters, ClientLoginStartParameters, ClientRegistration,
     ClientRegistrationFinishParameters, CredentialFinalization, CredentialRequest,
-    CredentialResponse, RegistrationRequest, RegistrationResponse, RegistrationUpload, ServerLogin,
-    ServerLoginStartParameters, ServerRegistration,
+    CredentialResponse, OprfSeed, RegistrationRequest, RegistrationResponse, RegistrationUpload,
+    ServerLogin, ServerLoginStartParameters, ServerRegistration,
 };
 
 // The ciphersuite trait allows to specify the underlying primitives
@@ -82,6 +82,8 @@ fn decrypt(key: &[u8], ciphertext: &[u8]) -> Vec<u8> {
 // Password-based registration and encryption of client secret message between a client and server
 fn register_locker(
     server_kp: &opaque_ke::keypair::KeyPair<curve25519_dalek::ristretto::RistrettoPoint>,
+    oprf_seed: &OprfSeed<Default>,
+    locker_id: usize,
     password: String,
     secret_message: String,
 ) -> Locker {
@@ -92,11 +94,11 @@ fn register_locker(
 
     // Client sends registration_request_bytes to server
 
-    let mut server_rng = OsRng;
     let server_registration_start_result = ServerRegistration::<Default>::start(
-        &mut server_rng,
+        oprf_seed,
         RegistrationRequest::deserialize(&registration_request_bytes[..]).unwrap(),
         server_kp.public(),
+        &locker_id.to_be_bytes(),
     )
     .unwrap();
     let registration_response_bytes = server_registration_start_result.message.serialize();
@@ -135,6 +137,8 @@ fn register_locker(
 // Open the contents of a locker with a password between a client and server
 fn open_locker(
     server_kp: &opaque_ke::keypair::KeyPair<curve25519_dalek::ristretto::RistrettoPoint>,
+    oprf_seed: &OprfSeed<Default>,
+    locker_id: usize,
     password: String,
     locker: &Locker,
 ) -> Result<String, String> {
@@ -154,9 +158,11 @@ fn open_locker(
     let mut server_rng = OsRng;
     let server_login_start_result = ServerLogin::start(
         &mut server_rng,
-        password_file,
-        &server_kp.private(),
+        Some(password_file),
+        server_kp.private(),
+        oprf_seed,
         CredentialRequest::deserialize(&credential_request_bytes[..]).unwrap(),
+        &locker_id.to_be_bytes(),
         ServerLoginStartParameters::default(),
     )
     .unwrap();
@@ -201,6 +207,7 @@ fn open_locker(
 fn main() {
     let mut rng = OsRng;
     let server_kp = Default::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<Default>::generate_random(&mut rng);
 
     let mut rl = Editor::<()>::new();
     let mut registered_lockers: Vec<Locker> = vec![];
@@ -227,6 +234,8 @@ fn main() {
                         );
                         registered_lockers.push(register_locker(
                             &server_kp,
+                            &oprf_seed,
+                            registered_lockers.len(),
                             password,
                             secret_message,
                         ));
@@ -252,7 +261,13 @@ fn main() {
                             continue;
                         }
 
-                        match open_locker(&server_kp, password, &registered_lockers[locker_index]) {
+                        match open_locker(
+                            &server_kp,
+                            &oprf_seed,
+                            locker_index,
+                            password,
+                            &registered_lockers[locker_index],
+                        ) {
                             Ok(contents) => {
                                 println!("\n\nSuccess! Contents: {}\n\n", contents);
                             }
@@ -277,7 +292,7 @@ fn main() {
 
 // Helper functions
 
-fn display_lockers(lockers: &Vec<Locker>) {
+fn display_lockers(lockers: &[Locker]) {
     let mut locker_numbers = vec![];
     for (i, _) in lockers.iter().enumerate() {
         locker_numbers.push(i);
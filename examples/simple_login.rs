@@ -28,7 +28,7 @@ use std::process::exit;
 use opaque_ke::{
     ciphersuite::CipherSuite, rand::rngs::OsRng, ClientLogin, ClientLoginFinishParameters,
     ClientLoginStartParameters, ClientRegistration, ClientRegistrationFinishParameters,
-    CredentialFinalization, CredentialRequest, CredentialResponse, RegistrationRequest,
+    CredentialFinalization, CredentialRequest, CredentialResponse, OprfSeed, RegistrationRequest,
     RegistrationResponse, RegistrationUpload, ServerLogin, ServerLoginStartParameters,
     ServerRegistration,
 };
@@ -47,6 +47,8 @@ impl CipherSuite for Default {
 // Password-based registration between a client and server
 fn account_registration(
     server_kp: &opaque_ke::keypair::KeyPair<curve25519_dalek::ristretto::RistrettoPoint>,
+    oprf_seed: &OprfSeed<Default>,
+    username: &str,
     password: String,
 ) -> Vec<u8> {
     let mut client_rng = OsRng;
@@ -56,11 +58,11 @@ fn account_registration(
 
     // Client sends registration_request_bytes to server
 
-    let mut server_rng = OsRng;
     let server_registration_start_result = ServerRegistration::<Default>::start(
-        &mut server_rng,
+        oprf_seed,
         RegistrationRequest::deserialize(&registration_request_bytes[..]).unwrap(),
         server_kp.public(),
+        username.as_bytes(),
     )
     .unwrap();
     let registration_response_bytes = server_registration_start_result.message.serialize();
@@ -89,6 +91,8 @@ fn account_registration(
 // Password-based login between a client and server
 fn account_login(
     server_kp: &opaque_ke::keypair::KeyPair<curve25519_dalek::ristretto::RistrettoPoint>,
+    oprf_seed: &OprfSeed<Default>,
+    username: &str,
     password: String,
     password_file_bytes: &[u8],
 ) -> bool {
@@ -107,9 +111,11 @@ fn account_login(
     let mut server_rng = OsRng;
     let server_login_start_result = ServerLogin::start(
         &mut server_rng,
-        password_file,
-        &server_kp.private(),
+        Some(password_file),
+        server_kp.private(),
+        oprf_seed,
         CredentialRequest::deserialize(&credential_request_bytes[..]).unwrap(),
+        username.as_bytes(),
         ServerLoginStartParameters::default(),
     )
     .unwrap();
@@ -142,6 +148,7 @@ fn account_login(
 fn main() {
     let mut rng = OsRng;
     let server_kp = Default::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<Default>::generate_random(&mut rng);
 
     let mut rl = Editor::<()>::new();
     let mut registered_users = HashMap::<String, Vec<u8>>::new();
@@ -164,13 +171,20 @@ fn main() {
                 let (username, password) = get_two_strings("Username", "Password", &mut rl, None);
                 match line.as_ref() {
                     "1" => {
-                        registered_users
-                            .insert(username, account_registration(&server_kp, password));
+                        let password_file =
+                            account_registration(&server_kp, &oprf_seed, &username, password);
+                        registered_users.insert(username, password_file);
                         continue;
                     }
                     "2" => match registered_users.get(&username) {
                         Some(password_file_bytes) => {
-                            if account_login(&server_kp, password, password_file_bytes) {
+                            if account_login(
+                                &server_kp,
+                                &oprf_seed,
+                                &username,
+                                password,
+                                password_file_bytes,
+                            ) {
                                 println!("\nLogin success!");
                             } else {
                                 // Note that at this point, the client knows whether or not the login
@@ -6,11 +6,9 @@
 //! Defines the CipherSuite trait to specify the underlying primitives for OPAQUE
 
 use crate::{
-    hash::Hash, key_exchange::traits::KeyExchange, keypair::KeyPair,
+    envelope::InnerEnvelopeMode, hash::Hash, key_exchange::traits::KeyExchange, keypair::KeyPair,
     map_to_curve::GroupWithMapToCurve, slow_hash::SlowHash,
 };
-use digest::Digest;
-
 use rand::{CryptoRng, RngCore};
 
 /// Configures the underlying primitives used in OPAQUE
@@ -21,12 +19,15 @@ use rand::{CryptoRng, RngCore};
 /// * `KeyExchange`: The key exchange protocol to use in the login step
 /// * `Hash`: The main hashing function to use
 /// * `SlowHash`: A slow hashing function, typically used for password hashing
+/// * `INNER_ENVELOPE_MODE`: How the envelope provides the client private key
+///   (`Base`, `Internal` or `External`), which defaults to
+///   `InnerEnvelopeMode::Base`
 pub trait CipherSuite {
     /// A finite cyclic group along with a point representation along with
     /// an extension trait PasswordToCurve that allows some customization on
     /// how to hash a password to a curve point. See `group::Group` and
     /// `map_to_curve::GroupWithMapToCurve`.
-    type Group: GroupWithMapToCurve<UniformBytesLen = <Self::Hash as Digest>::OutputSize>;
+    type Group: GroupWithMapToCurve;
     /// A key exchange protocol
     type KeyExchange: KeyExchange<Self::Hash, Self::Group>;
     /// The main hash function use (for HKDF computations and hashing transcripts)
@@ -34,6 +35,15 @@ pub trait CipherSuite {
     /// A slow hashing function, typically used for password hashing
     type SlowHash: SlowHash<Self::Hash>;
 
+    /// Determines whether the client private key is generated at random and
+    /// encrypted in the envelope (`Base`), derived from the envelope nonce and
+    /// the password (`Internal`), or supplied by the caller at registration
+    /// and encrypted with ChaCha20-Poly1305 (`External`). This is fixed per
+    /// ciphersuite so that the records of a server are all in the same mode,
+    /// which the envelope records in its mode byte along with whether it is
+    /// bound to custom identifiers.
+    const INNER_ENVELOPE_MODE: InnerEnvelopeMode = InnerEnvelopeMode::Base;
+
     /// Generating a random key pair given a cryptographic rng
     fn generate_random_keypair<R: RngCore + CryptoRng>(rng: &mut R) -> KeyPair<Self::Group> {
         KeyPair::<Self::Group>::generate_random(rng)
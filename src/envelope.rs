@@ -4,63 +4,166 @@
 // LICENSE file in the root directory of this source tree.
 
 use crate::{
+    ciphersuite::CipherSuite,
     errors::{utils::check_slice_size_atleast, InternalPakeError, PakeError, ProtocolError},
+    group::Group,
     hash::Hash,
-    keypair::Key,
-    serialization::serialize,
+    keypair::{KeyPair, PrivateKey, PublicKey, SizedBytesExt},
+    oprf,
+    serialization::{serialize, tokenize},
+};
+use chacha20poly1305::{
+    aead::{Aead, NewAead, Payload},
+    ChaCha20Poly1305, Key, Nonce,
 };
 use digest::Digest;
 use generic_array::{typenum::Unsigned, GenericArray};
-use generic_bytes::SizedBytes;
 use hkdf::Hkdf;
 use hmac::{Hmac, Mac, NewMac};
 use rand::{CryptoRng, RngCore};
-use std::convert::TryFrom;
+use zeroize::Zeroize;
 
 // Constant string used as salt for HKDF computation
 const STR_PAD: &[u8] = b"Pad";
 const STR_AUTH_KEY: &[u8] = b"AuthKey";
 const STR_EXPORT_KEY: &[u8] = b"ExportKey";
+const STR_PRIVATE_KEY: &[u8] = b"PrivateKey";
+const STR_ENCRYPTION_KEY: &[u8] = b"EncryptionKey";
+const STR_OPAQUE_DERIVE_DH_KEY_PAIR: &[u8] = b"OPAQUE-DeriveDiffieHellmanKeyPair";
+
+// The length of the seed from which the client private key is derived in
+// internal mode
+const SEED_LEN: usize = 32;
+
+// The lengths of the key, nonce and tag of the AEAD used in external mode
+const AEAD_KEY_LEN: usize = 32;
+const AEAD_NONCE_LEN: usize = 12;
+const AEAD_TAG_LEN: usize = 16;
 
 const NONCE_LEN: usize = 32;
 
-#[derive(Clone, Copy, PartialEq)]
-pub(crate) enum InnerEnvelopeMode {
-    Base = 1,
-    CustomIdentifier = 2,
+// Set in the mode byte when the envelope carries slow hashing parameters
+const KSF_PARAMETERS_FLAG: u8 = 0x80;
+
+// Set in the mode byte when the envelope carries a secret
+const SECRET_FLAG: u8 = 0x40;
+
+/// The maximum length of the secret which a client can store in its
+/// envelope, along with its private key
+pub const MAX_SECRET_LEN: usize = 1024;
+
+/// Determines how the envelope provides the client's static private key
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InnerEnvelopeMode {
+    /// The client private key is generated at random, and encrypted in the
+    /// envelope
+    Base,
+    /// The client private key is derived from the envelope nonce and the
+    /// password-derived key, so that the envelope does not encrypt anything
+    Internal,
+    /// The client private key is supplied by the caller at registration,
+    /// and encrypted in the envelope with ChaCha20-Poly1305
+    External,
 }
 
-impl TryFrom<u8> for InnerEnvelopeMode {
-    type Error = PakeError;
-    fn try_from(x: u8) -> Result<Self, Self::Error> {
-        match x {
-            1 => Ok(InnerEnvelopeMode::Base),
-            2 => Ok(InnerEnvelopeMode::CustomIdentifier),
-            _ => Err(PakeError::SerializationError),
-        }
+/// Determines whether the envelope is bound to custom identifiers
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum IdentifierMode {
+    Base,
+    CustomIdentifier,
+}
+
+// The mode byte of an envelope combines the two modes above, where the
+// values for the base inner envelope mode predate the other ones
+fn mode_to_byte(mode: InnerEnvelopeMode, identifier_mode: IdentifierMode) -> u8 {
+    match (mode, identifier_mode) {
+        (InnerEnvelopeMode::Base, IdentifierMode::Base) => 1,
+        (InnerEnvelopeMode::Base, IdentifierMode::CustomIdentifier) => 2,
+        (InnerEnvelopeMode::Internal, IdentifierMode::Base) => 3,
+        (InnerEnvelopeMode::Internal, IdentifierMode::CustomIdentifier) => 4,
+        (InnerEnvelopeMode::External, IdentifierMode::Base) => 5,
+        (InnerEnvelopeMode::External, IdentifierMode::CustomIdentifier) => 6,
+    }
+}
+
+fn mode_from_byte(x: u8) -> Result<(InnerEnvelopeMode, IdentifierMode), PakeError> {
+    match x {
+        1 => Ok((InnerEnvelopeMode::Base, IdentifierMode::Base)),
+        2 => Ok((InnerEnvelopeMode::Base, IdentifierMode::CustomIdentifier)),
+        3 => Ok((InnerEnvelopeMode::Internal, IdentifierMode::Base)),
+        4 => Ok((
+            InnerEnvelopeMode::Internal,
+            IdentifierMode::CustomIdentifier,
+        )),
+        5 => Ok((InnerEnvelopeMode::External, IdentifierMode::Base)),
+        6 => Ok((
+            InnerEnvelopeMode::External,
+            IdentifierMode::CustomIdentifier,
+        )),
+        _ => Err(PakeError::SerializationError),
     }
 }
 
 pub(crate) struct InnerEnvelope {
     mode: InnerEnvelopeMode,
+    identifier_mode: IdentifierMode,
     nonce: Vec<u8>,
     ciphertext: Vec<u8>,
+    has_secret: bool,
+    ksf_parameters: Option<Vec<u8>>,
 }
 
 impl InnerEnvelope {
+    /// The format of the output is:
+    /// mode | nonce             | ciphertext       | ksf_parameters (optional)
+    /// u8   | nonce_size bytes  | variable length  | u16 length-prefixed
+    ///
+    /// where the high bit of the mode byte is set if the slow hashing
+    /// parameters are present. The next bit is set if the ciphertext also
+    /// holds a secret, in which case it is u16 length-prefixed.
     pub(crate) fn serialize(&self) -> Vec<u8> {
-        [&[self.mode as u8], &self.nonce[..], &self.ciphertext[..]].concat()
+        let mut mode = mode_to_byte(self.mode, self.identifier_mode);
+        let ciphertext = if self.has_secret {
+            mode |= SECRET_FLAG;
+            serialize(&self.ciphertext, 2)
+        } else {
+            self.ciphertext.clone()
+        };
+        let ksf_parameters = match &self.ksf_parameters {
+            Some(ksf_parameters) => {
+                mode |= KSF_PARAMETERS_FLAG;
+                serialize(ksf_parameters, 2)
+            }
+            None => Vec::new(),
+        };
+        [
+            &[mode],
+            &self.nonce[..],
+            &ciphertext[..],
+            &ksf_parameters[..],
+        ]
+        .concat()
     }
 
-    pub(crate) fn deserialize(input: &[u8]) -> Result<(Self, Vec<u8>), ProtocolError> {
+    /// Here, `key_len` is the length of a private key, which is encrypted in
+    /// base and external modes (with a tag in the latter), but not in
+    /// internal mode
+    pub(crate) fn deserialize(
+        input: &[u8],
+        key_len: usize,
+    ) -> Result<(Self, Vec<u8>), ProtocolError> {
         if input.is_empty() {
             return Err(ProtocolError::VerificationError(
                 PakeError::SerializationError,
             ));
         }
-        let mode = InnerEnvelopeMode::try_from(input[0])?;
-
-        let key_len = <Key as SizedBytes>::Len::to_usize();
+        let (mode, identifier_mode) =
+            mode_from_byte(input[0] & !(KSF_PARAMETERS_FLAG | SECRET_FLAG))?;
+        let key_len = match mode {
+            InnerEnvelopeMode::Base => key_len,
+            InnerEnvelopeMode::Internal => 0,
+            InnerEnvelopeMode::External => key_len + AEAD_TAG_LEN,
+        };
 
         let bytes = &input[1..];
         if bytes.len() < NONCE_LEN + key_len {
@@ -69,13 +172,39 @@ impl InnerEnvelope {
             ));
         }
 
+        let has_secret = input[0] & SECRET_FLAG != 0;
+        let (ciphertext, remainder) = if has_secret {
+            let (ciphertext, remainder) = tokenize(&bytes[NONCE_LEN..], 2)?;
+            if ciphertext.len() < key_len || ciphertext.len() > key_len + MAX_SECRET_LEN {
+                return Err(ProtocolError::VerificationError(
+                    PakeError::SerializationError,
+                ));
+            }
+            (ciphertext, remainder)
+        } else {
+            (
+                bytes[NONCE_LEN..NONCE_LEN + key_len].to_vec(),
+                bytes[NONCE_LEN + key_len..].to_vec(),
+            )
+        };
+
+        let (ksf_parameters, remainder) = if input[0] & KSF_PARAMETERS_FLAG != 0 {
+            let (ksf_parameters, remainder) = tokenize(&remainder, 2)?;
+            (Some(ksf_parameters), remainder)
+        } else {
+            (None, remainder)
+        };
+
         Ok((
             Self {
                 mode,
+                identifier_mode,
                 nonce: bytes[..NONCE_LEN].to_vec(),
-                ciphertext: bytes[NONCE_LEN..NONCE_LEN + key_len].to_vec(),
+                ciphertext,
+                has_secret,
+                ksf_parameters,
             },
-            bytes[NONCE_LEN + key_len..].to_vec(),
+            remainder,
         ))
     }
 }
@@ -90,40 +219,120 @@ impl InnerEnvelope {
 /// The specification update has simplified this assumption by taking
 /// an XOR-based approach without compromising on security, and to avoid
 /// the confusion around the implementation of an RKR-secure encryption.
-pub(crate) struct Envelope<D: Hash> {
+///
+/// The envelope provides the client private key in one of three modes. In
+/// base mode, the key is generated at random and encrypted with the
+/// XOR-based pad. In internal mode, it is derived from the envelope nonce
+/// and the password-derived key, and nothing is encrypted. In external mode,
+/// it is supplied by the caller and encrypted with ChaCha20-Poly1305.
+///
+/// The low bits of the mode byte combine the inner envelope mode with
+/// whether the envelope is bound to custom identifiers: 1 and 2 for base
+/// mode, 3 and 4 for internal mode, and 5 and 6 for external mode, where
+/// the even values denote custom identifiers. On top of these, 0x80 is set
+/// if slow hashing parameters follow the ciphertext, and 0x40 is set if
+/// the ciphertext also holds a secret. The mode byte is covered by the
+/// HMAC. A request to re-seal the envelope is not part of it, and is
+/// instead sent as the unauthenticated trailing byte of the credential
+/// response.
+pub(crate) struct Envelope<CS: CipherSuite> {
     inner_envelope: InnerEnvelope,
-    hmac: GenericArray<u8, <D as Digest>::OutputSize>,
+    hmac: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
 }
 
+// Consists of the sealed envelope, followed by the export key
+type SealRawResult<CS> = (
+    Envelope<CS>,
+    GenericArray<u8, <<CS as CipherSuite>::Hash as Digest>::OutputSize>,
+);
+
+// Consists of the sealed envelope, the client public key, and the export key
+type SealResult<CS> = (
+    Envelope<CS>,
+    PublicKey<<CS as CipherSuite>::Group>,
+    GenericArray<u8, <<CS as CipherSuite>::Hash as Digest>::OutputSize>,
+);
+
 // Note that this struct represents an envelope that has been "opened" with the asssociated
 // key. This key is also used to derive the export_key parameter, which is technically
 // unrelated to the envelope's encrypted and authenticated contents.
-pub(crate) struct OpenedEnvelope<D: Hash> {
-    pub(crate) client_s_sk: Vec<u8>,
-    pub(crate) export_key: GenericArray<u8, <D as Digest>::OutputSize>,
+pub(crate) struct OpenedEnvelope<CS: CipherSuite> {
+    pub(crate) client_s_sk: PrivateKey<CS::Group>,
+    pub(crate) secret: Option<Vec<u8>>,
+    pub(crate) export_key: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
 }
 
-pub(crate) struct OpenedInnerEnvelope<D: Hash> {
+pub(crate) struct OpenedInnerEnvelope<CS: CipherSuite> {
     pub(crate) plaintext: Vec<u8>,
-    pub(crate) export_key: GenericArray<u8, <D as Digest>::OutputSize>,
+    pub(crate) export_key: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
 }
 
-impl<D: Hash> Envelope<D> {
+impl<CS: CipherSuite> Envelope<CS> {
     fn hmac_key_size() -> usize {
-        <D as Digest>::OutputSize::to_usize()
+        <CS::Hash as Digest>::OutputSize::to_usize()
     }
 
     fn export_key_size() -> usize {
-        <D as Digest>::OutputSize::to_usize()
+        <CS::Hash as Digest>::OutputSize::to_usize()
+    }
+
+    /// An envelope in the given modes which no password opens, where
+    /// `fill_random` supplies the bytes of the nonce, the ciphertext and the
+    /// hmac, and which carries the slow hashing parameters and a secret of
+    /// the given length if specified
+    pub(crate) fn fake(
+        mode: InnerEnvelopeMode,
+        identifier_mode: IdentifierMode,
+        ksf_parameters: Option<Vec<u8>>,
+        secret_len: Option<usize>,
+        fill_random: impl FnOnce(&mut [u8]) -> Result<(), InternalPakeError>,
+    ) -> Result<Self, InternalPakeError> {
+        if matches!(secret_len, Some(secret_len) if secret_len > MAX_SECRET_LEN) {
+            return Err(InternalPakeError::SecretTooLongError);
+        }
+        let key_len = match mode {
+            InnerEnvelopeMode::Base => <CS::Group as Group>::ScalarLen::to_usize(),
+            InnerEnvelopeMode::Internal => 0,
+            InnerEnvelopeMode::External => {
+                <CS::Group as Group>::ScalarLen::to_usize() + AEAD_TAG_LEN
+            }
+        };
+        let key_len = key_len + secret_len.unwrap_or(0);
+        let mut bytes = vec![0u8; NONCE_LEN + key_len + Self::hmac_key_size()];
+        fill_random(&mut bytes)?;
+        let (nonce, remainder) = bytes.split_at(NONCE_LEN);
+        let (ciphertext, hmac) = remainder.split_at(key_len);
+        Ok(Self {
+            inner_envelope: InnerEnvelope {
+                mode,
+                identifier_mode,
+                nonce: nonce.to_vec(),
+                ciphertext: ciphertext.to_vec(),
+                has_secret: secret_len.is_some(),
+                ksf_parameters,
+            },
+            hmac: GenericArray::clone_from_slice(hmac),
+        })
     }
 
     pub(crate) fn get_mode(&self) -> InnerEnvelopeMode {
         self.inner_envelope.mode
     }
 
+    pub(crate) fn get_identifier_mode(&self) -> IdentifierMode {
+        self.inner_envelope.identifier_mode
+    }
+
+    /// The serialized parameters of the slow hashing function used to seal
+    /// this envelope, if they were stored with it
+    pub(crate) fn get_ksf_parameters(&self) -> Option<&[u8]> {
+        self.inner_envelope.ksf_parameters.as_deref()
+    }
+
     /// The format of the output is:
-    /// mode | nonce             | ciphertext       | hmac
-    /// u8   | nonce_size bytes  | variable length  | hmac_size bytes
+    /// inner envelope   | hmac
+    /// variable length  | hmac_size bytes
+    #[cfg(test)]
     pub(crate) fn from_bytes(bytes: &[u8]) -> Result<Self, InternalPakeError> {
         let (result, remainder) = Self::deserialize(bytes)
             .map_err(|_| InternalPakeError::InvalidEnvelopeStructureError)?;
@@ -142,7 +351,8 @@ impl<D: Hash> Envelope<D> {
     }
 
     pub(crate) fn deserialize(input: &[u8]) -> Result<(Self, Vec<u8>), ProtocolError> {
-        let (inner_envelope, remainder) = InnerEnvelope::deserialize(input)?;
+        let (inner_envelope, remainder) =
+            InnerEnvelope::deserialize(input, <CS::Group as Group>::ScalarLen::to_usize())?;
 
         let hmac_key_size = Self::hmac_key_size();
         let hmac_and_remainder =
@@ -157,57 +367,133 @@ impl<D: Hash> Envelope<D> {
         ))
     }
 
+    /// Seals the client's static private key with the password-derived key.
+    /// In base mode, a new private key is generated at random and encrypted,
+    /// while in internal mode it is derived from the key and the nonce. In
+    /// external mode, the private key of `client_s_kp` is encrypted, and
+    /// this key pair must be supplied in that mode only. The secret, if
+    /// specified, is encrypted along with the private key.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn seal<R: RngCore + CryptoRng>(
         rng: &mut R,
         key: &[u8],
-        client_s_sk: &[u8],
-        server_s_pk: &[u8],
+        mode: InnerEnvelopeMode,
+        client_s_kp: Option<&KeyPair<CS::Group>>,
+        secret: Option<&[u8]>,
+        server_s_pk: &PublicKey<CS::Group>,
         optional_ids: Option<(Vec<u8>, Vec<u8>)>,
-    ) -> Result<(Self, GenericArray<u8, <D as Digest>::OutputSize>), InternalPakeError> {
+        ksf_parameters: Option<Vec<u8>>,
+    ) -> Result<SealResult<CS>, InternalPakeError> {
         let aad = construct_aad(server_s_pk, &optional_ids);
-        Self::seal_raw(rng, key, &client_s_sk, &aad, mode_from_ids(&optional_ids))
+        let identifier_mode = mode_from_ids(&optional_ids);
+        match (mode, client_s_kp) {
+            (InnerEnvelopeMode::Base, None) => {
+                let client_static_keypair = CS::generate_random_keypair(rng);
+                let (envelope, export_key) = Self::seal_raw(
+                    rng,
+                    key,
+                    client_static_keypair.private(),
+                    secret,
+                    &aad,
+                    mode,
+                    identifier_mode,
+                    ksf_parameters,
+                )?;
+                Ok((envelope, client_static_keypair.public().clone(), export_key))
+            }
+            (InnerEnvelopeMode::Internal, None) => {
+                let (envelope, export_key) = Self::seal_raw(
+                    rng,
+                    key,
+                    &[],
+                    secret,
+                    &aad,
+                    mode,
+                    identifier_mode,
+                    ksf_parameters,
+                )?;
+                let client_s_sk = derive_private_key::<CS>(key, &envelope.inner_envelope.nonce)?;
+                Ok((
+                    envelope,
+                    KeyPair::<CS::Group>::public_from_private(&client_s_sk),
+                    export_key,
+                ))
+            }
+            (InnerEnvelopeMode::External, Some(client_s_kp)) => {
+                let (envelope, export_key) = Self::seal_raw(
+                    rng,
+                    key,
+                    client_s_kp.private(),
+                    secret,
+                    &aad,
+                    mode,
+                    identifier_mode,
+                    ksf_parameters,
+                )?;
+                Ok((envelope, client_s_kp.public().clone(), export_key))
+            }
+            _ => Err(InternalPakeError::IncompatibleEnvelopeModeError),
+        }
     }
 
-    /// Uses a key to convert the plaintext into an envelope, authenticated by the aad field.
-    /// Note that a new nonce is sampled for each call to seal.
+    /// Uses a key to convert the plaintext, followed by the secret if specified, into an
+    /// envelope, authenticated by the aad field along with the slow hashing parameters, if
+    /// specified. Note that a new nonce is sampled for each call to seal. In external mode, the
+    /// plaintext is encrypted with an AEAD rather than with a one-time pad.
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn seal_raw<R: RngCore + CryptoRng>(
         rng: &mut R,
         key: &[u8],
         plaintext: &[u8],
+        secret: Option<&[u8]>,
         aad: &[u8],
         mode: InnerEnvelopeMode,
-    ) -> Result<(Self, GenericArray<u8, <D as Digest>::OutputSize>), InternalPakeError> {
+        identifier_mode: IdentifierMode,
+        ksf_parameters: Option<Vec<u8>>,
+    ) -> Result<SealRawResult<CS>, InternalPakeError> {
+        if matches!(secret, Some(secret) if secret.len() > MAX_SECRET_LEN) {
+            return Err(InternalPakeError::SecretTooLongError);
+        }
+        let plaintext = [plaintext, secret.unwrap_or_default()].concat();
+
         let mut nonce = vec![0u8; NONCE_LEN];
         rng.fill_bytes(&mut nonce);
 
-        let h = Hkdf::<D>::new(Some(&nonce), &key);
-        let mut xor_key = vec![0u8; plaintext.len()];
+        let h = Hkdf::<CS::Hash>::new(Some(&nonce), key);
         let mut hmac_key = vec![0u8; Self::hmac_key_size()];
         let mut export_key = vec![0u8; Self::export_key_size()];
 
-        h.expand(STR_PAD, &mut xor_key)
-            .map_err(|_| InternalPakeError::HkdfError)?;
         h.expand(STR_AUTH_KEY, &mut hmac_key)
             .map_err(|_| InternalPakeError::HkdfError)?;
         h.expand(STR_EXPORT_KEY, &mut export_key)
             .map_err(|_| InternalPakeError::HkdfError)?;
 
-        let ciphertext: Vec<u8> = xor_key
-            .iter()
-            .zip(plaintext.iter())
-            .map(|(&x1, &x2)| x1 ^ x2)
-            .collect();
+        let ciphertext = match mode {
+            InnerEnvelopeMode::Base | InnerEnvelopeMode::Internal => xor_with_pad(&h, &plaintext)?,
+            InnerEnvelopeMode::External => aead_cipher(&h)?
+                .encrypt(
+                    Nonce::from_slice(&[0u8; AEAD_NONCE_LEN]),
+                    Payload {
+                        msg: &plaintext,
+                        aad,
+                    },
+                )
+                .map_err(|_| InternalPakeError::AeadError)?,
+        };
 
         let inner_envelope = InnerEnvelope {
             mode,
+            identifier_mode,
             nonce,
             ciphertext,
+            has_secret: secret.is_some(),
+            ksf_parameters,
         };
 
         let mut hmac =
-            Hmac::<D>::new_varkey(&hmac_key).map_err(|_| InternalPakeError::HmacError)?;
+            Hmac::<CS::Hash>::new_varkey(&hmac_key).map_err(|_| InternalPakeError::HmacError)?;
         hmac.update(&inner_envelope.serialize());
-        hmac.update(&aad);
+        hmac.update(aad);
 
         let hmac_bytes = hmac.finalize().into_bytes();
 
@@ -223,24 +509,48 @@ impl<D: Hash> Envelope<D> {
     pub(crate) fn open(
         &self,
         key: &[u8],
-        server_s_pk: &[u8],
+        server_s_pk: &PublicKey<CS::Group>,
         optional_ids: &Option<(Vec<u8>, Vec<u8>)>,
-    ) -> Result<OpenedEnvelope<D>, InternalPakeError> {
+    ) -> Result<OpenedEnvelope<CS>, InternalPakeError> {
         // First, check that mode matches
-        if self.inner_envelope.mode != mode_from_ids(optional_ids) {
+        if self.inner_envelope.identifier_mode != mode_from_ids(optional_ids) {
             return Err(InternalPakeError::IncompatibleEnvelopeModeError);
         }
 
         let aad = construct_aad(server_s_pk, optional_ids);
         let opened = self.open_raw(key, &aad)?;
 
-        if opened.plaintext.len() != <Key as SizedBytes>::Len::to_usize() {
-            // Plaintext should consist of a single key
+        // The plaintext consists of the private key, unless it is derived,
+        // followed by the secret if there is one
+        let key_len = match self.inner_envelope.mode {
+            InnerEnvelopeMode::Base | InnerEnvelopeMode::External => {
+                <CS::Group as Group>::ScalarLen::to_usize()
+            }
+            InnerEnvelopeMode::Internal => 0,
+        };
+        if opened.plaintext.len() < key_len
+            || (!self.inner_envelope.has_secret && opened.plaintext.len() != key_len)
+        {
             return Err(InternalPakeError::UnexpectedEnvelopeContentsError);
         }
+        let (key_bytes, secret) = opened.plaintext.split_at(key_len);
+
+        let client_s_sk = match self.inner_envelope.mode {
+            InnerEnvelopeMode::Base | InnerEnvelopeMode::External => {
+                PrivateKey::from_bytes(key_bytes)?
+            }
+            InnerEnvelopeMode::Internal => {
+                derive_private_key::<CS>(key, &self.inner_envelope.nonce)?
+            }
+        };
 
         Ok(OpenedEnvelope {
-            client_s_sk: opened.plaintext,
+            client_s_sk,
+            secret: if self.inner_envelope.has_secret {
+                Some(secret.to_vec())
+            } else {
+                None
+            },
             export_key: opened.export_key,
         })
     }
@@ -251,35 +561,40 @@ impl<D: Hash> Envelope<D> {
         &self,
         key: &[u8],
         aad: &[u8],
-    ) -> Result<OpenedInnerEnvelope<D>, InternalPakeError> {
-        let h = Hkdf::<D>::new(Some(&self.inner_envelope.nonce), &key);
-        let mut xor_key = vec![0u8; self.inner_envelope.ciphertext.len()];
+    ) -> Result<OpenedInnerEnvelope<CS>, InternalPakeError> {
+        let h = Hkdf::<CS::Hash>::new(Some(&self.inner_envelope.nonce), key);
         let mut hmac_key = vec![0u8; Self::hmac_key_size()];
         let mut export_key = vec![0u8; Self::export_key_size()];
 
-        h.expand(STR_PAD, &mut xor_key)
-            .map_err(|_| InternalPakeError::HkdfError)?;
         h.expand(STR_AUTH_KEY, &mut hmac_key)
             .map_err(|_| InternalPakeError::HkdfError)?;
         h.expand(STR_EXPORT_KEY, &mut export_key)
             .map_err(|_| InternalPakeError::HkdfError)?;
 
         let mut hmac =
-            Hmac::<D>::new_varkey(&hmac_key).map_err(|_| InternalPakeError::HmacError)?;
+            Hmac::<CS::Hash>::new_varkey(&hmac_key).map_err(|_| InternalPakeError::HmacError)?;
         hmac.update(&self.inner_envelope.serialize());
         hmac.update(aad);
         if hmac.verify(&self.hmac).is_err() {
             return Err(InternalPakeError::SealOpenHmacError);
         }
 
-        let plaintext: Vec<u8> = xor_key
-            .iter()
-            .zip(self.inner_envelope.ciphertext.iter())
-            .map(|(&x1, &x2)| x1 ^ x2)
-            .collect();
+        let ciphertext = &self.inner_envelope.ciphertext;
+        let plaintext = match self.inner_envelope.mode {
+            InnerEnvelopeMode::Base | InnerEnvelopeMode::Internal => xor_with_pad(&h, ciphertext)?,
+            InnerEnvelopeMode::External => aead_cipher(&h)?
+                .decrypt(
+                    Nonce::from_slice(&[0u8; AEAD_NONCE_LEN]),
+                    Payload {
+                        msg: ciphertext,
+                        aad,
+                    },
+                )
+                .map_err(|_| InternalPakeError::AeadError)?,
+        };
         Ok(OpenedInnerEnvelope {
             plaintext,
-            export_key: GenericArray::<u8, <D as Digest>::OutputSize>::clone_from_slice(
+            export_key: GenericArray::<u8, <CS::Hash as Digest>::OutputSize>::clone_from_slice(
                 &export_key,
             ),
         })
@@ -288,7 +603,51 @@ impl<D: Hash> Envelope<D> {
 
 // Helper functions
 
-fn construct_aad(server_s_pk: &[u8], optional_ids: &Option<(Vec<u8>, Vec<u8>)>) -> Vec<u8> {
+// Encrypts or decrypts the input by xor-ing it with a pad derived from the
+// envelope key
+fn xor_with_pad<D: Hash>(h: &Hkdf<D>, input: &[u8]) -> Result<Vec<u8>, InternalPakeError> {
+    let mut xor_key = vec![0u8; input.len()];
+    h.expand(STR_PAD, &mut xor_key)
+        .map_err(|_| InternalPakeError::HkdfError)?;
+    Ok(xor_key
+        .iter()
+        .zip(input.iter())
+        .map(|(&x1, &x2)| x1 ^ x2)
+        .collect())
+}
+
+// The AEAD used in external mode. Since its key is derived from the envelope
+// key and a fresh envelope nonce, each key encrypts a single plaintext, so a
+// fixed AEAD nonce can be used.
+fn aead_cipher<D: Hash>(h: &Hkdf<D>) -> Result<ChaCha20Poly1305, InternalPakeError> {
+    let mut aead_key = [0u8; AEAD_KEY_LEN];
+    h.expand(STR_ENCRYPTION_KEY, &mut aead_key)
+        .map_err(|_| InternalPakeError::HkdfError)?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&aead_key));
+    aead_key.zeroize();
+    Ok(cipher)
+}
+
+// Derives the client private key of an envelope in internal mode
+fn derive_private_key<CS: CipherSuite>(
+    key: &[u8],
+    nonce: &[u8],
+) -> Result<PrivateKey<CS::Group>, InternalPakeError> {
+    let mut seed = [0u8; SEED_LEN];
+    Hkdf::<CS::Hash>::new(Some(nonce), key)
+        .expand(STR_PRIVATE_KEY, &mut seed)
+        .map_err(|_| InternalPakeError::HkdfError)?;
+    let scalar = oprf::derive_key::<CS::Group, CS::Hash>(&seed, STR_OPAQUE_DERIVE_DH_KEY_PAIR);
+    seed.zeroize();
+    Ok(PrivateKey::from_bytes(&CS::Group::scalar_as_bytes(
+        &scalar?,
+    ))?)
+}
+
+fn construct_aad<G: Group>(
+    server_s_pk: &PublicKey<G>,
+    optional_ids: &Option<(Vec<u8>, Vec<u8>)>,
+) -> Vec<u8> {
     let ids = optional_ids
         .iter()
         .flat_map(|(l, r)| [serialize(l, 2), serialize(r, 2)].concat())
@@ -296,18 +655,28 @@ fn construct_aad(server_s_pk: &[u8], optional_ids: &Option<(Vec<u8>, Vec<u8>)>)
     [server_s_pk.to_vec(), ids].concat()
 }
 
-pub(crate) fn mode_from_ids(optional_ids: &Option<(Vec<u8>, Vec<u8>)>) -> InnerEnvelopeMode {
+pub(crate) fn mode_from_ids(optional_ids: &Option<(Vec<u8>, Vec<u8>)>) -> IdentifierMode {
     match optional_ids {
-        Some(_) => InnerEnvelopeMode::CustomIdentifier,
-        None => InnerEnvelopeMode::Base,
+        Some(_) => IdentifierMode::CustomIdentifier,
+        None => IdentifierMode::Base,
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{key_exchange::tripledh::TripleDH, slow_hash::NoOpHash};
+    use curve25519_dalek::ristretto::RistrettoPoint;
     use rand::rngs::OsRng;
 
+    struct Default;
+    impl CipherSuite for Default {
+        type Group = RistrettoPoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha256;
+        type SlowHash = NoOpHash;
+    }
+
     #[test]
     fn seal_and_open() {
         let mut rng = OsRng;
@@ -317,16 +686,243 @@ mod tests {
         let mut msg = [0u8; 100];
         rng.fill_bytes(&mut msg);
 
-        let (envelope, export_key_1) = Envelope::<sha2::Sha256>::seal_raw(
+        let (envelope, export_key_1) = Envelope::<Default>::seal_raw(
             &mut rng,
             &key,
             &msg,
+            None,
             b"aad",
             InnerEnvelopeMode::Base,
+            IdentifierMode::Base,
+            None,
         )
         .unwrap();
         let opened_envelope = envelope.open_raw(&key, b"aad").unwrap();
         assert_eq!(&msg.to_vec(), &opened_envelope.plaintext);
         assert_eq!(&export_key_1.to_vec(), &opened_envelope.export_key.to_vec());
     }
+
+    #[test]
+    fn seal_and_open_with_ksf_parameters() {
+        let mut rng = OsRng;
+        let mut key = [0u8; 32];
+        rng.fill_bytes(&mut key);
+
+        let mut msg = [0u8; 32];
+        rng.fill_bytes(&mut msg);
+
+        let (envelope, _) = Envelope::<Default>::seal_raw(
+            &mut rng,
+            &key,
+            &msg,
+            None,
+            b"aad",
+            InnerEnvelopeMode::Base,
+            IdentifierMode::Base,
+            Some(b"parameters".to_vec()),
+        )
+        .unwrap();
+        let mut bytes = envelope.serialize();
+        let envelope = Envelope::<Default>::from_bytes(&bytes).unwrap();
+        assert_eq!(Some(&b"parameters"[..]), envelope.get_ksf_parameters());
+        assert_eq!(
+            &msg.to_vec(),
+            &envelope.open_raw(&key, b"aad").unwrap().plaintext
+        );
+
+        // The parameters are authenticated along with the rest of the envelope
+        let index = 1 + NONCE_LEN + msg.len() + 2;
+        bytes[index] ^= 1;
+        let tampered = Envelope::<Default>::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            tampered.open_raw(&key, b"aad"),
+            Err(InternalPakeError::SealOpenHmacError)
+        ));
+    }
+
+    #[test]
+    fn seal_and_open_internal() {
+        let mut rng = OsRng;
+        let mut key = [0u8; 32];
+        rng.fill_bytes(&mut key);
+        let server_s_pk = Default::generate_random_keypair(&mut rng).public().clone();
+
+        let (envelope, client_s_pk, export_key) = Envelope::<Default>::seal(
+            &mut rng,
+            &key,
+            InnerEnvelopeMode::Internal,
+            None,
+            None,
+            &server_s_pk,
+            None,
+            None,
+        )
+        .unwrap();
+        let bytes = envelope.serialize();
+        // Fake envelopes have the same length as real ones
+        let fake = Envelope::<Default>::fake(
+            InnerEnvelopeMode::Internal,
+            IdentifierMode::Base,
+            None,
+            None,
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(fake.serialize().len(), bytes.len());
+
+        // The private key is derived again on opening, rather than decrypted
+        let envelope = Envelope::<Default>::from_bytes(&bytes).unwrap();
+        assert_eq!(InnerEnvelopeMode::Internal, envelope.get_mode());
+        let opened = envelope.open(&key, &server_s_pk, &None).unwrap();
+        assert_eq!(
+            client_s_pk,
+            KeyPair::<RistrettoPoint>::public_from_private(&opened.client_s_sk)
+        );
+        assert_eq!(export_key.to_vec(), opened.export_key.to_vec());
+
+        let mut wrong_key = key;
+        wrong_key[0] ^= 1;
+        assert!(matches!(
+            envelope.open(&wrong_key, &server_s_pk, &None),
+            Err(InternalPakeError::SealOpenHmacError)
+        ));
+    }
+
+    #[test]
+    fn seal_and_open_external() {
+        let mut rng = OsRng;
+        let mut key = [0u8; 32];
+        rng.fill_bytes(&mut key);
+        let server_s_pk = Default::generate_random_keypair(&mut rng).public().clone();
+        let client_s_kp = Default::generate_random_keypair(&mut rng);
+
+        let (envelope, client_s_pk, export_key) = Envelope::<Default>::seal(
+            &mut rng,
+            &key,
+            InnerEnvelopeMode::External,
+            Some(&client_s_kp),
+            None,
+            &server_s_pk,
+            None,
+            None,
+        )
+        .unwrap();
+        assert_eq!(client_s_kp.public(), &client_s_pk);
+        let mut bytes = envelope.serialize();
+        // Fake envelopes have the same length as real ones
+        let fake = Envelope::<Default>::fake(
+            InnerEnvelopeMode::External,
+            IdentifierMode::Base,
+            None,
+            None,
+            |_| Ok(()),
+        )
+        .unwrap();
+        assert_eq!(fake.serialize().len(), bytes.len());
+
+        let envelope = Envelope::<Default>::from_bytes(&bytes).unwrap();
+        assert_eq!(InnerEnvelopeMode::External, envelope.get_mode());
+        let opened = envelope.open(&key, &server_s_pk, &None).unwrap();
+        assert_eq!(client_s_kp.private(), &opened.client_s_sk);
+        assert_eq!(export_key.to_vec(), opened.export_key.to_vec());
+
+        // The encrypted key is authenticated
+        bytes[1 + NONCE_LEN] ^= 1;
+        let tampered = Envelope::<Default>::from_bytes(&bytes).unwrap();
+        assert!(matches!(
+            tampered.open(&key, &server_s_pk, &None),
+            Err(InternalPakeError::SealOpenHmacError)
+        ));
+
+        // A key pair is taken in external mode only
+        for (mode, client_s_kp) in &[
+            (InnerEnvelopeMode::External, None),
+            (InnerEnvelopeMode::Base, Some(&client_s_kp)),
+            (InnerEnvelopeMode::Internal, Some(&client_s_kp)),
+        ] {
+            assert!(matches!(
+                Envelope::<Default>::seal(
+                    &mut rng,
+                    &key,
+                    *mode,
+                    *client_s_kp,
+                    None,
+                    &server_s_pk,
+                    None,
+                    None
+                ),
+                Err(InternalPakeError::IncompatibleEnvelopeModeError)
+            ));
+        }
+    }
+
+    #[test]
+    fn seal_and_open_with_secret() {
+        let mut rng = OsRng;
+        let mut key = [0u8; 32];
+        rng.fill_bytes(&mut key);
+        let server_s_pk = Default::generate_random_keypair(&mut rng).public().clone();
+        let client_s_kp = Default::generate_random_keypair(&mut rng);
+        let secret = b"recovery key";
+
+        for (mode, client_s_kp) in &[
+            (InnerEnvelopeMode::Base, None),
+            (InnerEnvelopeMode::Internal, None),
+            (InnerEnvelopeMode::External, Some(&client_s_kp)),
+        ] {
+            let (envelope, client_s_pk, _) = Envelope::<Default>::seal(
+                &mut rng,
+                &key,
+                *mode,
+                *client_s_kp,
+                Some(&secret[..]),
+                &server_s_pk,
+                None,
+                Some(b"parameters".to_vec()),
+            )
+            .unwrap();
+            let envelope = Envelope::<Default>::from_bytes(&envelope.serialize()).unwrap();
+            assert_eq!(Some(&b"parameters"[..]), envelope.get_ksf_parameters());
+
+            let opened = envelope.open(&key, &server_s_pk, &None).unwrap();
+            assert_eq!(Some(secret.to_vec()), opened.secret);
+            assert_eq!(
+                client_s_pk,
+                KeyPair::<RistrettoPoint>::public_from_private(&opened.client_s_sk)
+            );
+
+            // The secret is bound to the server public key
+            let other_s_pk = Default::generate_random_keypair(&mut rng).public().clone();
+            assert!(matches!(
+                envelope.open(&key, &other_s_pk, &None),
+                Err(InternalPakeError::SealOpenHmacError)
+            ));
+        }
+
+        let secret = [0u8; MAX_SECRET_LEN + 1];
+        assert!(Envelope::<Default>::seal(
+            &mut rng,
+            &key,
+            InnerEnvelopeMode::Base,
+            None,
+            Some(&secret[..MAX_SECRET_LEN]),
+            &server_s_pk,
+            None,
+            None,
+        )
+        .is_ok());
+        assert!(matches!(
+            Envelope::<Default>::seal(
+                &mut rng,
+                &key,
+                InnerEnvelopeMode::Base,
+                None,
+                Some(&secret[..]),
+                &server_s_pk,
+                None,
+                None,
+            ),
+            Err(InternalPakeError::SecretTooLongError)
+        ));
+    }
 }
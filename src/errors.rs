@@ -33,8 +33,24 @@ pub enum InternalPakeError {
     HkdfError,
     /// Computing HMAC failed while supplying a secret key
     HmacError,
+    /// Encrypting or decrypting with the AEAD failed
+    AeadError,
+    /// Encapsulating or decapsulating with the KEM failed
+    KemError,
     /// Computing the slow hashing function failed
     SlowHashError,
+    /// This error occurs when the slow hashing parameters stored with an
+    /// envelope are malformed or belong to a different algorithm
+    /// Invalid slow hashing parameters.
+    InvalidSlowHashParametersError,
+    /// This error occurs when the slow hashing parameters stored with an
+    /// envelope are weaker than the minimum the client accepts
+    /// Slow hashing parameters below the configured minimum.
+    WeakSlowHashParametersError,
+    /// This error occurs when the slow hashing parameters stored with an
+    /// envelope are more expensive than the maximum the client computes
+    /// Slow hashing parameters above the configured maximum.
+    ExpensiveSlowHashParametersError,
     /// This error occurs when the envelope seal fails
     /// Constructing the envelope seal failed.
     SealError,
@@ -48,29 +64,59 @@ pub enum InternalPakeError {
     /// based on the credentials that were specified to be required.
     InvalidEnvelopeStructureError,
     /// This error occurs when attempting to open an envelope of the wrong
-    /// type (base mode, custom identifier)
+    /// type (base mode, custom identifier), or when a client key pair is
+    /// supplied (or missing) for an envelope mode which does not take one
     IncompatibleEnvelopeModeError,
     /// This error occurs when the envelope is opened and deserialization
     /// fails
     UnexpectedEnvelopeContentsError,
+    /// This error occurs when the public key of a key pair supplied by the
+    /// caller is not the one of its private key
+    /// The public key does not match the private key.
+    MismatchedKeyPairError,
+    /// This error occurs when the secret to store in the envelope is longer
+    /// than `MAX_SECRET_LEN`
+    /// The secret is too long to be stored in the envelope.
+    SecretTooLongError,
+    /// This error occurs when the application context of a login is longer
+    /// than `MAX_CONTEXT_LEN`
+    /// The application context is too long.
+    ContextTooLongError,
+    /// This error occurs when the DLEQ proof attached to a verifiable OPRF
+    /// evaluation does not verify against the server's OPRF public key
+    /// Verifying the OPRF evaluation proof failed.
+    InvalidProofError,
+    /// This error occurs when finalizing an OPRF evaluation in a different
+    /// mode (base, verifiable) than the one used for blinding
+    IncompatibleOprfModeError,
+    /// This error occurs when the public info of a POPRF evaluation cancels
+    /// out the OPRF key, or differs between the elements of a batch
+    InvalidPoprfInfoError,
+    /// This error occurs when no valid OPRF key could be derived from a seed
+    /// Deriving an OPRF key from the seed failed.
+    DeriveKeyPairError,
 }
 
 /// Represents an error in password checking
 #[derive(Debug, Display, Error)]
+#[ignore_extra_doc_attributes]
 pub enum PakeError {
     /// This error results from an internal error during PRF construction
     ///
     /// Internal error during PRF verification: {0}
+    #[displaydoc("Internal error during PRF verification: {0}")]
     CryptoError(InternalPakeError),
     /// This error occurs when the server object that is being called finish() on is malformed
     /// Incomplete set of keys passed into finish() function
     IncompleteKeysError,
     /// The provided server public key doesn't match the sealed one
     IncompatibleServerStaticPublicKeyError,
-    /// Error in key exchange protocol when attempting to validate MACs
+    /// Error in key exchange protocol when attempting to validate MACs or signatures
     KeyExchangeMacValidationError,
     /// Error in validating credentials
     InvalidLoginError,
+    /// The password file was sealed under a server key which is not in the key set
+    UnknownServerKeyError,
     /// Error with serializing / deserializing protocol messages
     SerializationError,
 }
@@ -85,10 +131,12 @@ impl From<InternalPakeError> for PakeError {
 
 /// Represents an error in protocol handling
 #[derive(Debug, Display, Error)]
+#[ignore_extra_doc_attributes]
 pub enum ProtocolError {
     /// This error results from an error during password verification
     ///
     /// Internal error during password verification: {0}
+    #[displaydoc("Internal error during password verification: {0}")]
     VerificationError(PakeError),
     /// This error occurs when the server answer cannot be handled
     /// Server response cannot be handled.
@@ -0,0 +1,219 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! An implementation of the Group and GroupWithMapToCurve traits for the
+//! decaf448 prime-order group built on top of Ed448-Goldilocks
+//!
+//! This is not one of the ciphersuites of RFC 9497: the standard decaf448
+//! suite, decaf448-SHAKE256, hashes to the group with expand_message_xof,
+//! whereas this implementation uses expand_message_xmd with the ciphersuite's
+//! hash function, as the other groups of this crate do. Its OPRF outputs
+//! therefore match no published test vectors and do not interoperate with
+//! other decaf448 implementations, and the partially-oblivious mode, which
+//! is only defined for the RFC 9497 suites, is not available.
+
+use crate::{
+    errors::InternalPakeError,
+    group::Group,
+    hash::Hash,
+    map_to_curve::{expand_message_xmd, GroupWithMapToCurve},
+};
+use ed448_goldilocks_plus::{
+    elliptic_curve::PrimeField, CompressedDecaf, DecafPoint, Scalar, ScalarBytes, WideScalarBytes,
+};
+use generic_array::{
+    typenum::{Unsigned, U112, U56},
+    GenericArray,
+};
+use rand::{CryptoRng, RngCore};
+use std::convert::TryInto;
+
+impl Group for DecafPoint {
+    type Scalar = Scalar;
+    type ScalarLen = U56;
+    fn from_scalar_slice(
+        scalar_bits: &GenericArray<u8, Self::ScalarLen>,
+    ) -> Result<Self::Scalar, InternalPakeError> {
+        // The underlying representation carries an extra trailing byte, as per
+        // RFC 8032, which is always zero for canonical scalars
+        let mut repr = ScalarBytes::default();
+        repr[..56].copy_from_slice(scalar_bits);
+        Option::from(Scalar::from_repr(repr)).ok_or(InternalPakeError::InvalidByteSequence)
+    }
+    fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+        // Rejection sampling over 446-bit strings, so that tests can supply
+        // the exact scalar bytes through the rng
+        loop {
+            let mut scalar_bytes = GenericArray::<u8, Self::ScalarLen>::default();
+            rng.fill_bytes(&mut scalar_bytes);
+            scalar_bytes[55] &= 0x3f;
+            if let Ok(scalar) = Self::from_scalar_slice(&scalar_bytes) {
+                if !bool::from(scalar.is_zero()) {
+                    return scalar;
+                }
+            }
+        }
+    }
+    fn scalar_as_bytes(scalar: &Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        scalar.to_bytes().into()
+    }
+    fn scalar_invert(scalar: &Self::Scalar) -> Self::Scalar {
+        scalar.invert()
+    }
+
+    // The decaf448 encoding of a group element
+    type ElemLen = U56;
+    fn from_element_slice(
+        element_bits: &GenericArray<u8, Self::ElemLen>,
+    ) -> Result<Self, InternalPakeError> {
+        // Decompression rejects non-canonical encodings, and every valid
+        // encoding lies in the prime-order group. We additionally reject the
+        // identity element.
+        let compressed = CompressedDecaf((*element_bits).into());
+        let point: Option<Self> = compressed.decompress().into();
+        match point {
+            Some(p) if !bool::from(p.is_identity()) => Ok(p),
+            _ => Err(InternalPakeError::PointError),
+        }
+    }
+    fn to_arr(&self) -> GenericArray<u8, Self::ElemLen> {
+        self.compress().0.into()
+    }
+
+    // Two 56-byte halves, each of which is fed to the decaf448 map
+    type UniformBytesLen = U112;
+    fn hash_to_curve(uniform_bytes: &GenericArray<u8, Self::UniformBytesLen>) -> Self {
+        let bytes: &[u8; 112] = uniform_bytes
+            .as_slice()
+            .try_into()
+            .expect("UniformBytesLen is 112 bytes");
+        DecafPoint::from_uniform_bytes(bytes)
+    }
+
+    fn base_point() -> Self {
+        DecafPoint::GENERATOR
+    }
+
+    fn identity() -> Self {
+        DecafPoint::IDENTITY
+    }
+
+    fn mult_by_slice(&self, scalar: &GenericArray<u8, Self::ScalarLen>) -> Self {
+        let mut wide = WideScalarBytes::default();
+        wide[..56].copy_from_slice(scalar);
+        self * Scalar::from_bytes_mod_order_wide(&wide)
+    }
+}
+
+impl GroupWithMapToCurve for DecafPoint {
+    const SUITE_ID: usize = 0x0002;
+    const IDENTIFIER: &'static str = "decaf448-SHAKE256";
+
+    // Implements hash_to_decaf448 from
+    // https://www.ietf.org/archive/id/draft-irtf-cfrg-ristretto255-decaf448-00.txt,
+    // using expand_message_xmd to produce the uniform bytes
+    fn map_to_curve<H: Hash>(msg: &[u8], dst: &[u8]) -> Result<Self, InternalPakeError> {
+        let uniform_bytes = expand_message_xmd::<H>(msg, dst, U112::to_usize())?;
+        Ok(<Self as Group>::hash_to_curve(GenericArray::from_slice(
+            &uniform_bytes[..],
+        )))
+    }
+
+    // Reduces 84 uniform bytes, interpreted in little-endian order, modulo
+    // the group order
+    fn hash_to_scalar<H: Hash>(msg: &[u8], dst: &[u8]) -> Result<Scalar, InternalPakeError> {
+        let uniform_bytes = expand_message_xmd::<H>(msg, dst, 84)?;
+        let mut wide = WideScalarBytes::default();
+        wide[..84].copy_from_slice(&uniform_bytes);
+        Ok(Scalar::from_bytes_mod_order_wide(&wide))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::oprf;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    #[test]
+    fn test_context_string() {
+        assert_eq!(vec![0x00, 0x00, 0x02], DecafPoint::get_context_string(0x00));
+    }
+
+    #[test]
+    fn test_base_point_multiples() {
+        // Encodings of the generator and its double, taken from libdecaf
+        let g = DecafPoint::base_point();
+        assert_eq!(
+            "6666666666666666666666666666666666666666666666666666666633333333333333333333333333333333333333333333333333333333",
+            hex::encode(g.to_arr())
+        );
+
+        let mut two = GenericArray::<u8, U56>::default();
+        two[0] = 2;
+        assert_eq!(
+            "c898eb4f87f97c564c6fd61fc7e49689314a1f818ec85eeb3bd5514ac816d38778f69ef347a89fca817e66defdedce178c7cc709b2116e75",
+            hex::encode(g.mult_by_slice(&two).to_arr())
+        );
+    }
+
+    #[test]
+    fn test_element_roundtrip() {
+        let mut rng = OsRng;
+        let scalar = DecafPoint::random_scalar(&mut rng);
+        let point = DecafPoint::base_point() * scalar;
+        let bytes = point.to_arr();
+        assert_eq!(DecafPoint::from_element_slice(&bytes).unwrap(), point);
+
+        let scalar_bytes = DecafPoint::scalar_as_bytes(&scalar);
+        assert_eq!(
+            DecafPoint::from_scalar_slice(&scalar_bytes).unwrap(),
+            scalar
+        );
+        assert_eq!(DecafPoint::base_point().mult_by_slice(&scalar_bytes), point);
+    }
+
+    #[test]
+    fn test_reject_invalid_elements() {
+        // The identity element is not a valid group element for the protocol
+        let identity = GenericArray::<u8, U56>::default();
+        assert!(DecafPoint::from_element_slice(&identity).is_err());
+
+        // Neither are encodings which fail to decompress
+        let invalid = GenericArray::<u8, U56>::clone_from_slice(&[1u8; 56]);
+        assert!(DecafPoint::from_element_slice(&invalid).is_err());
+
+        // Nor scalars which are not reduced modulo the group order
+        let non_canonical = GenericArray::<u8, U56>::clone_from_slice(&[0xffu8; 56]);
+        assert!(DecafPoint::from_scalar_slice(&non_canonical).is_err());
+    }
+
+    #[test]
+    fn test_map_to_curve_is_valid() {
+        let point = DecafPoint::map_to_curve::<Sha512>(b"hunter2", b"decaf448-test").unwrap();
+        assert_eq!(
+            DecafPoint::from_element_slice(&point.to_arr()).unwrap(),
+            point
+        );
+    }
+
+    #[test]
+    fn oprf_retrieval() -> Result<(), InternalPakeError> {
+        let input = b"hunter2";
+        let mut rng = OsRng;
+        let (token, alpha) = oprf::blind::<_, DecafPoint, Sha512>(&input[..], &mut rng)?;
+        let oprf_key = DecafPoint::random_scalar(&mut rng);
+        let beta = oprf::evaluate::<DecafPoint>(alpha, &oprf_key);
+        let res = oprf::finalize::<DecafPoint, Sha512>(&token.data, &token.blind, beta);
+
+        let (token2, alpha2) = oprf::blind::<_, DecafPoint, Sha512>(&input[..], &mut rng)?;
+        let beta2 = oprf::evaluate::<DecafPoint>(alpha2, &oprf_key);
+        let res2 = oprf::finalize::<DecafPoint, Sha512>(&token2.data, &token2.blind, beta2);
+
+        assert_eq!(res, res2);
+        Ok(())
+    }
+}
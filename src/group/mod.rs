@@ -12,6 +12,7 @@ use curve25519_dalek::{
     constants::RISTRETTO_BASEPOINT_POINT,
     ristretto::{CompressedRistretto, RistrettoPoint},
     scalar::Scalar,
+    traits::Identity,
 };
 use generic_array::{
     typenum::{U32, U64},
@@ -20,16 +21,27 @@ use generic_array::{
 use std::convert::TryInto;
 
 use rand::{CryptoRng, RngCore};
-use std::ops::Mul;
+use std::ops::{Add, Mul, Sub};
 use zeroize::Zeroize;
 
+#[cfg(feature = "decaf448")]
+mod decaf448;
+#[cfg(any(feature = "p256", feature = "p384", feature = "p521"))]
+mod nist;
+
 /// A prime-order subgroup of a base field (EC, prime-order field ...). This
 /// subgroup is noted additively — as in the draft RFC — in this trait.
-pub trait Group: Copy + Sized + for<'a> Mul<&'a <Self as Group>::Scalar, Output = Self> {
+pub trait Group:
+    Copy + Sized + Add<Output = Self> + for<'a> Mul<&'a <Self as Group>::Scalar, Output = Self>
+{
     /// The type of base field scalars
-    type Scalar: Zeroize + Clone;
+    type Scalar: Zeroize
+        + Clone
+        + Add<Output = Self::Scalar>
+        + Sub<Output = Self::Scalar>
+        + for<'a> Mul<&'a Self::Scalar, Output = Self::Scalar>;
     /// The byte length necessary to represent scalars
-    type ScalarLen: ArrayLength<u8>;
+    type ScalarLen: ArrayLength<u8> + 'static;
     /// Return a scalar from its fixed-length bytes representation
     fn from_scalar_slice(
         scalar_bits: &GenericArray<u8, Self::ScalarLen>,
@@ -37,12 +49,12 @@ pub trait Group: Copy + Sized + for<'a> Mul<&'a <Self as Group>::Scalar, Output
     /// picks a scalar at random
     fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar;
     /// Serializes a scalar to bytes
-    fn scalar_as_bytes(scalar: &Self::Scalar) -> &GenericArray<u8, Self::ScalarLen>;
+    fn scalar_as_bytes(scalar: &Self::Scalar) -> GenericArray<u8, Self::ScalarLen>;
     /// The multiplicative inverse of this scalar
     fn scalar_invert(scalar: &Self::Scalar) -> Self::Scalar;
 
     /// The byte length necessary to represent group elements
-    type ElemLen: ArrayLength<u8>;
+    type ElemLen: ArrayLength<u8> + 'static;
     /// Return an element from its fixed-length bytes representation
     fn from_element_slice(
         element_bits: &GenericArray<u8, Self::ElemLen>,
@@ -62,6 +74,9 @@ pub trait Group: Copy + Sized + for<'a> Mul<&'a <Self as Group>::Scalar, Output
     /// Get the base point for the group
     fn base_point() -> Self;
 
+    /// Get the identity element of the group
+    fn identity() -> Self;
+
     /// Multiply the point by a scalar, represented as a slice
     fn mult_by_slice(&self, scalar: &GenericArray<u8, Self::ScalarLen>) -> Self;
 }
@@ -93,8 +108,8 @@ impl Group for RistrettoPoint {
             Scalar::from_bytes_mod_order(scalar_bytes)
         }
     }
-    fn scalar_as_bytes(scalar: &Self::Scalar) -> &GenericArray<u8, Self::ScalarLen> {
-        GenericArray::from_slice(scalar.as_bytes())
+    fn scalar_as_bytes(scalar: &Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+        *GenericArray::from_slice(scalar.as_bytes())
     }
     fn scalar_invert(scalar: &Self::Scalar) -> Self::Scalar {
         scalar.invert()
@@ -130,6 +145,10 @@ impl Group for RistrettoPoint {
         RISTRETTO_BASEPOINT_POINT
     }
 
+    fn identity() -> Self {
+        <Self as Identity>::identity()
+    }
+
     fn mult_by_slice(&self, scalar: &GenericArray<u8, Self::ScalarLen>) -> Self {
         let arr: [u8; 32] = scalar.as_slice().try_into().expect("Wrong length");
         self * Scalar::from_bits(arr)
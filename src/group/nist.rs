@@ -0,0 +1,296 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! Implementations of the Group and GroupWithMapToCurve traits for the NIST
+//! P-256, P-384 and P-521 curves, using SEC1 compressed points as the element
+//! representation
+
+/// Implements Group and GroupWithMapToCurve for the `ProjectivePoint` type of
+/// one of the RustCrypto NIST curve crates. The lengths are, in order: the
+/// scalar length, the compressed element length, the length L of a single
+/// field element as per hash_to_field, and the total number of uniform bytes
+/// (2 * L) consumed by hash_to_curve. These are followed by the draft-05
+/// suite ID, and by the RFC 9497 suite identifier along with the hash
+/// function it is defined for.
+macro_rules! impl_nist_group {
+    (
+        $module:ident,
+        $krate:ident,
+        $curve:ident,
+        $uint:ident,
+        $scalar_len:ident,
+        $elem_len:ident,
+        $field_len:ident,
+        $uniform_len:ident,
+        $suite_id:expr,
+        $identifier:expr,
+        $hash:ty
+    ) => {
+        mod $module {
+            use crate::{
+                errors::InternalPakeError,
+                group::Group,
+                hash::Hash,
+                map_to_curve::{expand_message_xmd, GroupWithMapToCurve, PoprfSuite},
+            };
+            use generic_array::{
+                typenum::{$elem_len, $field_len, $scalar_len, $uniform_len, Unsigned},
+                GenericArray,
+            };
+            use rand::{CryptoRng, RngCore};
+            use $krate::{
+                elliptic_curve::{
+                    group::{Group as _, GroupEncoding},
+                    hash2curve::{FromOkm, GroupDigest, MapToCurve},
+                    ops::Reduce,
+                    Field, PrimeField,
+                },
+                $curve, $uint, ProjectivePoint, Scalar,
+            };
+
+            // The field element type used by the simplified SWU map
+            type FieldElement = <$curve as GroupDigest>::FieldElement;
+
+            impl Group for ProjectivePoint {
+                type Scalar = Scalar;
+                type ScalarLen = $scalar_len;
+                fn from_scalar_slice(
+                    scalar_bits: &GenericArray<u8, Self::ScalarLen>,
+                ) -> Result<Self::Scalar, InternalPakeError> {
+                    Option::from(Scalar::from_repr(*scalar_bits))
+                        .ok_or(InternalPakeError::InvalidByteSequence)
+                }
+                fn random_scalar<R: RngCore + CryptoRng>(rng: &mut R) -> Self::Scalar {
+                    // Wide reduction of L uniform bytes, as in hash_to_field
+                    #[cfg(not(test))]
+                    {
+                        let mut scalar_bytes = GenericArray::<u8, $field_len>::default();
+                        rng.fill_bytes(&mut scalar_bytes);
+                        Scalar::from_okm(&scalar_bytes)
+                    }
+
+                    // Tests need an exact conversion from bytes to scalar, sampling only
+                    // ScalarLen bytes from rng
+                    #[cfg(test)]
+                    loop {
+                        let mut scalar_bytes = GenericArray::<u8, Self::ScalarLen>::default();
+                        rng.fill_bytes(&mut scalar_bytes);
+                        if let Some(scalar) =
+                            Option::<Scalar>::from(Scalar::from_repr(scalar_bytes))
+                        {
+                            if !bool::from(Field::is_zero(&scalar)) {
+                                return scalar;
+                            }
+                        }
+                    }
+                }
+                fn scalar_as_bytes(scalar: &Self::Scalar) -> GenericArray<u8, Self::ScalarLen> {
+                    scalar.to_repr()
+                }
+                fn scalar_invert(scalar: &Self::Scalar) -> Self::Scalar {
+                    Option::from(Field::invert(scalar)).unwrap_or(Scalar::ZERO)
+                }
+
+                // SEC1 compressed encoding: one tag byte followed by the
+                // x-coordinate
+                type ElemLen = $elem_len;
+                fn from_element_slice(
+                    element_bits: &GenericArray<u8, Self::ElemLen>,
+                ) -> Result<Self, InternalPakeError> {
+                    // Decoding checks that the point lies on the curve, and
+                    // since the NIST curves have a cofactor of 1 there is no
+                    // small subgroup to check against. We additionally reject
+                    // the identity element.
+                    let point: Option<Self> = Self::from_bytes(element_bits).into();
+                    match point {
+                        Some(p) if !bool::from(p.is_identity()) => Ok(p),
+                        _ => Err(InternalPakeError::PointError),
+                    }
+                }
+                fn to_arr(&self) -> GenericArray<u8, Self::ElemLen> {
+                    self.to_bytes()
+                }
+
+                // Two field elements of L bytes each, as per hash_to_field
+                type UniformBytesLen = $uniform_len;
+                fn hash_to_curve(uniform_bytes: &GenericArray<u8, Self::UniformBytesLen>) -> Self {
+                    let (u0_bytes, u1_bytes) = uniform_bytes.split_at($field_len::to_usize());
+                    let u0 = FieldElement::from_okm(GenericArray::from_slice(u0_bytes));
+                    let u1 = FieldElement::from_okm(GenericArray::from_slice(u1_bytes));
+                    // The cofactor is 1, so there is no cofactor to clear
+                    u0.map_to_curve() + u1.map_to_curve()
+                }
+
+                fn base_point() -> Self {
+                    Self::GENERATOR
+                }
+
+                fn identity() -> Self {
+                    Self::IDENTITY
+                }
+
+                fn mult_by_slice(&self, scalar: &GenericArray<u8, Self::ScalarLen>) -> Self {
+                    self * &<Scalar as Reduce<$uint>>::reduce_bytes(scalar)
+                }
+            }
+
+            impl GroupWithMapToCurve for ProjectivePoint {
+                const SUITE_ID: usize = $suite_id;
+                const IDENTIFIER: &'static str = $identifier;
+
+                // Implements the corresponding _XMD:SHA-2_SSWU_RO_ suite from
+                // https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-10.txt
+                fn map_to_curve<H: Hash>(
+                    msg: &[u8],
+                    dst: &[u8],
+                ) -> Result<Self, InternalPakeError> {
+                    let uniform_bytes =
+                        expand_message_xmd::<H>(msg, dst, $uniform_len::to_usize())?;
+                    Ok(<Self as Group>::hash_to_curve(GenericArray::from_slice(
+                        &uniform_bytes[..],
+                    )))
+                }
+
+                // Implements hash_to_field for a single element of the
+                // scalar field, with the same L as the base field
+                fn hash_to_scalar<H: Hash>(
+                    msg: &[u8],
+                    dst: &[u8],
+                ) -> Result<Scalar, InternalPakeError> {
+                    let uniform_bytes = expand_message_xmd::<H>(msg, dst, $field_len::to_usize())?;
+                    Ok(Scalar::from_okm(GenericArray::from_slice(
+                        &uniform_bytes[..],
+                    )))
+                }
+            }
+
+            impl PoprfSuite<$hash> for ProjectivePoint {}
+
+            #[cfg(test)]
+            mod tests {
+                use super::*;
+                use crate::oprf;
+                use rand::rngs::OsRng;
+
+                #[test]
+                fn test_context_string() {
+                    assert_eq!(
+                        vec![0x00, 0x00, $suite_id as u8],
+                        ProjectivePoint::get_context_string(0x00)
+                    );
+                }
+
+                #[test]
+                fn test_element_roundtrip() {
+                    let mut rng = OsRng;
+                    let scalar = ProjectivePoint::random_scalar(&mut rng);
+                    let point = ProjectivePoint::base_point() * scalar;
+                    let bytes = point.to_arr();
+                    assert_eq!(ProjectivePoint::from_element_slice(&bytes).unwrap(), point);
+
+                    let scalar_bytes = ProjectivePoint::scalar_as_bytes(&scalar);
+                    assert_eq!(
+                        ProjectivePoint::from_scalar_slice(&scalar_bytes).unwrap(),
+                        scalar
+                    );
+                    assert_eq!(
+                        ProjectivePoint::base_point().mult_by_slice(&scalar_bytes),
+                        point
+                    );
+                }
+
+                #[test]
+                fn test_reject_invalid_elements() {
+                    // The identity element is not a valid group element for
+                    // the protocol
+                    let identity = GenericArray::<u8, $elem_len>::default();
+                    assert!(ProjectivePoint::from_element_slice(&identity).is_err());
+
+                    // An x-coordinate which is not a canonical field element
+                    let mut non_canonical = GenericArray::<u8, $elem_len>::default();
+                    non_canonical[0] = 0x02;
+                    for byte in non_canonical[1..].iter_mut() {
+                        *byte = 0xff;
+                    }
+                    assert!(ProjectivePoint::from_element_slice(&non_canonical).is_err());
+
+                    // An invalid SEC1 tag
+                    let mut bad_tag = ProjectivePoint::base_point().to_arr();
+                    bad_tag[0] = 0x04;
+                    assert!(ProjectivePoint::from_element_slice(&bad_tag).is_err());
+                }
+
+                #[test]
+                fn oprf_retrieval() -> Result<(), InternalPakeError> {
+                    let input = b"hunter2";
+                    let mut rng = OsRng;
+                    let (token, alpha) =
+                        oprf::blind::<_, ProjectivePoint, $hash>(&input[..], &mut rng)?;
+                    let oprf_key = ProjectivePoint::random_scalar(&mut rng);
+                    let beta = oprf::evaluate::<ProjectivePoint>(alpha, &oprf_key);
+                    let res =
+                        oprf::finalize::<ProjectivePoint, $hash>(&token.data, &token.blind, beta);
+
+                    let (token2, alpha2) =
+                        oprf::blind::<_, ProjectivePoint, $hash>(&input[..], &mut rng)?;
+                    let beta2 = oprf::evaluate::<ProjectivePoint>(alpha2, &oprf_key);
+                    let res2 = oprf::finalize::<ProjectivePoint, $hash>(
+                        &token2.data,
+                        &token2.blind,
+                        beta2,
+                    );
+
+                    assert_eq!(res, res2);
+                    Ok(())
+                }
+            }
+        }
+    };
+}
+
+#[cfg(feature = "p256")]
+impl_nist_group!(
+    p256,
+    p256_,
+    NistP256,
+    U256,
+    U32,
+    U33,
+    U48,
+    U96,
+    0x0003,
+    "P256-SHA256",
+    sha2::Sha256
+);
+
+#[cfg(feature = "p384")]
+impl_nist_group!(
+    p384,
+    p384_,
+    NistP384,
+    U384,
+    U48,
+    U49,
+    U72,
+    U144,
+    0x0004,
+    "P384-SHA384",
+    sha2::Sha384
+);
+
+#[cfg(feature = "p521")]
+impl_nist_group!(
+    p521,
+    p521_,
+    NistP521,
+    U576,
+    U66,
+    U67,
+    U98,
+    U196,
+    0x0005,
+    "P521-SHA512",
+    sha2::Sha512
+);
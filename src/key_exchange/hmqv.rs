@@ -0,0 +1,408 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! An implementation of the HMQV key exchange protocol
+//!
+//! Like TripleDH, HMQV authenticates both parties implicitly through their
+//! static Diffie-Hellman keys, but it combines the static and ephemeral keys
+//! of each party into a single exponent, weighted by a hash of the ephemeral
+//! key and the peer's identity. Each party then performs a single
+//! Diffie-Hellman operation plus one scalar multiplication, instead of the
+//! three Diffie-Hellman operations of TripleDH.
+//!
+//! The key derivation, the transcript hashing and the MACs are the same as in
+//! TripleDH. The second message omits the server nonce: the freshness of the
+//! server's contribution is carried by its ephemeral key, which is bound into
+//! both the hashed exponents and the transcript.
+//!
+//! Unlike the original HMQV, whose hashed exponents are |q|/2 bits long, the
+//! exponents here are full-length scalars, in the style of FHMQV. HMQV picks
+//! half-length exponents to save half of a scalar multiplication, which the
+//! constant-time scalar multiplications of the groups here do not exploit,
+//! and the length of the exponents bounds the probability that an adversary
+//! predicts one, which is 2^-(|q|/2) in HMQV and close to 2^-|q| here.
+use crate::{
+    errors::{
+        utils::{check_slice_size, check_slice_size_atleast},
+        InternalPakeError, PakeError, ProtocolError,
+    },
+    group::Group,
+    hash::Hash,
+    key_exchange::{
+        traits::{KeyExchange, ToBytes},
+        tripledh::{
+            derive_keys, generate_nonce, serialize_context, xor_with_encryption_pad, Ke1Message,
+            Ke2State, Ke3Message, NonceLen,
+        },
+    },
+    keypair::{KeyPair, PrivateKey, PublicKey, SizedBytesExt},
+    map_to_curve::GroupWithMapToCurve,
+    serialization::{serialize, tokenize},
+};
+use digest::{Digest, FixedOutput};
+use generic_array::{typenum::Unsigned, ArrayLength, GenericArray};
+use generic_bytes::SizedBytes;
+use hmac::{Hmac, Mac, NewMac};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use std::convert::TryFrom;
+
+static STR_HMQV: &[u8] = b"HMQV";
+static STR_HMQV_EXPONENT: &[u8] = b"OPAQUE-HMQV-Exponent";
+
+/// The HMQV key exchange implementation
+#[allow(clippy::upper_case_acronyms)]
+pub struct HMQV;
+
+impl<D: Hash, G: GroupWithMapToCurve> KeyExchange<D, G> for HMQV {
+    type KE1State = Ke1State<G>;
+    type KE2State = Ke2State<<D as FixedOutput>::OutputSize>;
+    type KE1Message = Ke1Message<G>;
+    type KE2Message = Ke2Message<G, <D as FixedOutput>::OutputSize>;
+    type KE3Message = Ke3Message<<D as FixedOutput>::OutputSize>;
+
+    fn generate_ke1<R: RngCore + CryptoRng>(
+        info: Vec<u8>,
+        rng: &mut R,
+    ) -> Result<(Self::KE1State, Self::KE1Message), ProtocolError> {
+        let client_e_kp = KeyPair::<G>::generate_random(rng);
+        let client_nonce = generate_nonce::<R>(rng);
+
+        let ke1_message = Ke1Message {
+            client_nonce,
+            info,
+            client_e_pk: client_e_kp.public().clone(),
+        };
+
+        Ok((
+            Ke1State {
+                client_e_sk: client_e_kp.private().clone(),
+                client_e_pk: client_e_kp.public().clone(),
+                client_nonce,
+            },
+            ke1_message,
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn generate_ke2<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        serialized_credential_request: Vec<u8>,
+        l2_bytes: Vec<u8>,
+        ke1_message: Self::KE1Message,
+        client_s_pk: PublicKey<G>,
+        server_s_sk: PrivateKey<G>,
+        id_u: Vec<u8>,
+        id_s: Vec<u8>,
+        e_info: Vec<u8>,
+        context: Vec<u8>,
+    ) -> Result<(Vec<u8>, Self::KE2State, Self::KE2Message), ProtocolError> {
+        let server_e_kp = KeyPair::<G>::generate_random(rng);
+
+        let mut transcript_hasher = D::new()
+            .chain(STR_HMQV)
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
+            .chain(&serialized_credential_request[..])
+            .chain(serialize(&id_s, 2))
+            .chain(&l2_bytes[..])
+            .chain(server_e_kp.public().to_arr());
+
+        let ikm = derive_shared_secret::<D, G>(
+            &ke1_message.client_e_pk,
+            &client_s_pk,
+            server_e_kp.public(),
+            server_e_kp.private(),
+            &server_s_sk,
+            &id_u,
+            &id_s,
+            false,
+        )?;
+        let (session_key, km2, ke2, km3) =
+            derive_keys::<D>(&ikm, &transcript_hasher.clone().finalize())?;
+
+        // Compute encryption of e_info
+        let ciphertext = xor_with_encryption_pad::<D>(&ke2, &e_info)?;
+        transcript_hasher.update(serialize(&ciphertext, 2));
+
+        let mut mac_hasher =
+            Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
+        mac_hasher.update(&transcript_hasher.clone().finalize());
+        let mac = mac_hasher.finalize().into_bytes();
+
+        transcript_hasher.update(&mac);
+
+        Ok((
+            ke1_message.info,
+            Ke2State {
+                km3,
+                hashed_transcript: transcript_hasher.finalize(),
+                session_key,
+            },
+            Ke2Message {
+                server_e_pk: server_e_kp.public().clone(),
+                e_info: ciphertext,
+                mac,
+            },
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn generate_ke3(
+        l2_component: Vec<u8>,
+        ke2_message: Self::KE2Message,
+        ke1_state: &Self::KE1State,
+        serialized_credential_request: &[u8],
+        server_s_pk: PublicKey<G>,
+        client_s_sk: PrivateKey<G>,
+        id_u: Vec<u8>,
+        id_s: Vec<u8>,
+        context: Vec<u8>,
+    ) -> Result<(Vec<u8>, Vec<u8>, Self::KE3Message), ProtocolError> {
+        let mut transcript_hasher = D::new()
+            .chain(STR_HMQV)
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
+            .chain(serialized_credential_request)
+            .chain(serialize(&id_s, 2))
+            .chain(&l2_component[..])
+            .chain(ke2_message.server_e_pk.to_arr());
+
+        let ikm = derive_shared_secret::<D, G>(
+            &ke2_message.server_e_pk,
+            &server_s_pk,
+            &ke1_state.client_e_pk,
+            &ke1_state.client_e_sk,
+            &client_s_sk,
+            &id_u,
+            &id_s,
+            true,
+        )?;
+        let (session_key, km2, ke2, km3) =
+            derive_keys::<D>(&ikm, &transcript_hasher.clone().finalize())?;
+
+        transcript_hasher.update(serialize(&ke2_message.e_info[..], 2));
+
+        let mut server_mac =
+            Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
+        server_mac.update(&transcript_hasher.clone().finalize());
+
+        // Mac::verify compares the tags in constant time
+        server_mac
+            .verify(&ke2_message.mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
+
+        transcript_hasher.update(&ke2_message.mac);
+
+        let mut client_mac =
+            Hmac::<D>::new_varkey(&km3).map_err(|_| InternalPakeError::HmacError)?;
+        client_mac.update(&transcript_hasher.finalize());
+
+        // Compute decryption of e_info
+        let plaintext = xor_with_encryption_pad::<D>(&ke2, &ke2_message.e_info)?;
+
+        Ok((
+            plaintext,
+            session_key.to_vec(),
+            Ke3Message {
+                mac: client_mac.finalize().into_bytes(),
+            },
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn finish_ke(
+        ke3_message: Self::KE3Message,
+        ke2_state: &Self::KE2State,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let mut client_mac =
+            Hmac::<D>::new_varkey(&ke2_state.km3).map_err(|_| InternalPakeError::HmacError)?;
+        client_mac.update(&ke2_state.hashed_transcript);
+
+        client_mac
+            .verify(&ke3_message.mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
+
+        Ok(ke2_state.session_key.to_vec())
+    }
+
+    fn ke2_message_size() -> usize {
+        <G as Group>::ElemLen::to_usize() + <<D as FixedOutput>::OutputSize as Unsigned>::to_usize()
+    }
+}
+
+/// Computes the HMQV shared secret from the point of view of one party.
+///
+/// With the client's keys (x, X) and (a, A), the server's keys (y, Y) and
+/// (b, B), d = H(X || id_s) and e = H(Y || id_u), the client computes
+/// (Y + B * e) * (x + d * a) and the server computes (X + A * d) * (y + e * b),
+/// which are the same point.
+#[allow(clippy::too_many_arguments)]
+fn derive_shared_secret<D: Hash, G: GroupWithMapToCurve>(
+    peer_e_pk: &PublicKey<G>,
+    peer_s_pk: &PublicKey<G>,
+    e_pk: &PublicKey<G>,
+    e_sk: &PrivateKey<G>,
+    s_sk: &PrivateKey<G>,
+    id_u: &[u8],
+    id_s: &[u8],
+    is_client: bool,
+) -> Result<Vec<u8>, InternalPakeError> {
+    let (client_e_pk, server_e_pk) = if is_client {
+        (e_pk, peer_e_pk)
+    } else {
+        (peer_e_pk, e_pk)
+    };
+    let d = hashed_exponent::<D, G>(client_e_pk, id_s)?;
+    let e = hashed_exponent::<D, G>(server_e_pk, id_u)?;
+    let (own_exponent, peer_exponent) = if is_client { (d, e) } else { (e, d) };
+
+    let peer_point =
+        G::from_element_slice(peer_e_pk)? + G::from_element_slice(peer_s_pk)? * &peer_exponent;
+    let mut e_scalar = G::from_scalar_slice(e_sk)?;
+    let mut s_scalar = G::from_scalar_slice(s_sk)?;
+    let mut exponent = e_scalar.clone() + own_exponent * &s_scalar;
+    let shared_secret = peer_point * &exponent;
+
+    e_scalar.zeroize();
+    s_scalar.zeroize();
+    exponent.zeroize();
+    Ok(shared_secret.to_arr().to_vec())
+}
+
+// Hashes an ephemeral key and the peer's identity to a full-length exponent
+fn hashed_exponent<D: Hash, G: GroupWithMapToCurve>(
+    e_pk: &PublicKey<G>,
+    peer_id: &[u8],
+) -> Result<G::Scalar, InternalPakeError> {
+    let input = [&e_pk.to_arr()[..], &serialize(peer_id, 2)].concat();
+    G::hash_to_scalar::<D>(&input, STR_HMQV_EXPONENT)
+}
+
+/// The client state produced after the first key exchange message, which
+/// keeps the ephemeral public key so that it need not be recomputed
+pub struct Ke1State<G: Group> {
+    client_e_sk: PrivateKey<G>,
+    client_e_pk: PublicKey<G>,
+    client_nonce: GenericArray<u8, NonceLen>,
+}
+
+impl<G: Group> TryFrom<&[u8]> for Ke1State<G> {
+    type Error = PakeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let sk_len = <G as Group>::ScalarLen::to_usize();
+        let pk_len = <G as Group>::ElemLen::to_usize();
+        let nonce_len = NonceLen::to_usize();
+        let checked_bytes = check_slice_size(bytes, sk_len + pk_len + nonce_len, "ke1_state")?;
+
+        Ok(Self {
+            client_e_sk: PrivateKey::from_bytes(&checked_bytes[..sk_len])?,
+            client_e_pk: PublicKey::from_bytes(&checked_bytes[sk_len..sk_len + pk_len])?,
+            client_nonce: GenericArray::clone_from_slice(&checked_bytes[sk_len + pk_len..]),
+        })
+    }
+}
+
+impl<G: Group> ToBytes for Ke1State<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.client_e_sk.to_arr()[..],
+            &self.client_e_pk.to_arr()[..],
+            &self.client_nonce[..],
+        ]
+        .concat()
+    }
+}
+
+/// The second key exchange message
+pub struct Ke2Message<G: Group, HashLen: ArrayLength<u8>> {
+    server_e_pk: PublicKey<G>,
+    e_info: Vec<u8>,
+    mac: GenericArray<u8, HashLen>,
+}
+
+impl<G: Group, HashLen: ArrayLength<u8>> ToBytes for Ke2Message<G, HashLen> {
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.server_e_pk.to_arr()[..],
+            &serialize(&self.e_info, 2),
+            &self.mac[..],
+        ]
+        .concat()
+    }
+}
+
+impl<G: Group, HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke2Message<G, HashLen> {
+    type Error = PakeError;
+
+    fn try_from(input: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ElemLen::to_usize();
+        let checked_server_e_pk =
+            check_slice_size_atleast(input, key_len, "ke2_message server_e_pk")?;
+        let (e_info, remainder) = tokenize(&checked_server_e_pk[key_len..], 2)?;
+        let checked_mac = check_slice_size(&remainder, HashLen::to_usize(), "ke2_message mac")?;
+
+        Ok(Self {
+            server_e_pk: PublicKey::from_bytes(&checked_server_e_pk[..key_len])?,
+            e_info,
+            mac: GenericArray::clone_from_slice(checked_mac),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use rand::rngs::OsRng;
+    use sha2::Sha512;
+
+    #[test]
+    fn shared_secrets_agree() -> Result<(), InternalPakeError> {
+        let mut rng = OsRng;
+        let client_e_kp = KeyPair::<RistrettoPoint>::generate_random(&mut rng);
+        let client_s_kp = KeyPair::<RistrettoPoint>::generate_random(&mut rng);
+        let server_e_kp = KeyPair::<RistrettoPoint>::generate_random(&mut rng);
+        let server_s_kp = KeyPair::<RistrettoPoint>::generate_random(&mut rng);
+
+        let client_secret = derive_shared_secret::<Sha512, RistrettoPoint>(
+            server_e_kp.public(),
+            server_s_kp.public(),
+            client_e_kp.public(),
+            client_e_kp.private(),
+            client_s_kp.private(),
+            b"user",
+            b"server",
+            true,
+        )?;
+        let server_secret = derive_shared_secret::<Sha512, RistrettoPoint>(
+            client_e_kp.public(),
+            client_s_kp.public(),
+            server_e_kp.public(),
+            server_e_kp.private(),
+            server_s_kp.private(),
+            b"user",
+            b"server",
+            false,
+        )?;
+        assert_eq!(client_secret, server_secret);
+
+        // The secret depends on the identities bound into the exponents
+        let other_secret = derive_shared_secret::<Sha512, RistrettoPoint>(
+            client_e_kp.public(),
+            client_s_kp.public(),
+            server_e_kp.public(),
+            server_e_kp.private(),
+            server_s_kp.private(),
+            b"other user",
+            b"server",
+            false,
+        )?;
+        assert_ne!(client_secret, other_secret);
+        Ok(())
+    }
+}
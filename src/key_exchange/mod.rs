@@ -6,5 +6,9 @@
 //! Includes instantiations of key exchange protocols used in the
 //! login step for OPAQUE
 
+pub mod hmqv;
+pub mod sigma;
 pub(crate) mod traits;
 pub mod tripledh;
+#[cfg(feature = "ml-kem")]
+pub mod tripledh_mlkem;
@@ -0,0 +1,657 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! An implementation of the SIGMA-I key exchange protocol
+//!
+//! In SIGMA-I, the shared secret comes from the ephemeral keys alone, and
+//! each party authenticates by signing the transcript with its long-term
+//! signing key, and by MACing the transcript, which includes its identity,
+//! with a key derived from the shared secret. This suits deployments in which
+//! the server's identity is a certificate-backed signing key rather than a
+//! Diffie-Hellman key.
+//!
+//! As in SIGMA-I, the client only reveals its signature and MAC once it has
+//! authenticated the server, and sends them encrypted under a key derived
+//! from the shared secret, so that they cannot be matched against candidate
+//! client public keys by an observer. The server's signature and MAC are sent
+//! in the clear, as the server's identity is assumed to be public.
+//!
+//! The signatures are computed with a [SignatureScheme], whose signing and
+//! verifying keys are the static key pairs of the ciphersuite. These key
+//! pairs are only ever used to sign, and never in a Diffie-Hellman
+//! computation, so they must not be shared with a ciphersuite which uses
+//! another key exchange. Two schemes are provided:
+//! - [Schnorr] signs with Schnorr signatures over Ristretto255 and SHA-512,
+//!   with deterministic nonces as in Ed25519, and can be used with the
+//!   default Ristretto255 ciphersuite.
+//! - [Ecdsa] signs with deterministic ECDSA (RFC 6979) over P-256 or P-384,
+//!   with the curve's hash function, so that a static key pair is an ordinary
+//!   ECDSA key pair of the curve, such as the one of a certificate. P-521 is
+//!   not supported, as its ECDSA implementation only signs with random
+//!   nonces.
+use crate::{
+    errors::{
+        utils::{check_slice_size, check_slice_size_atleast},
+        InternalPakeError, PakeError, ProtocolError,
+    },
+    group::Group,
+    hash::Hash,
+    key_exchange::{
+        traits::{KeyExchange, ToBytes},
+        tripledh::{
+            derive_keys, generate_nonce, serialize_context, xor_with_encryption_pad, xor_with_pad,
+            Ke1Message, Ke1State, NonceLen,
+        },
+    },
+    keypair::{KeyPair, PrivateKey, PublicKey, SizedBytesExt},
+    serialization::{serialize, tokenize},
+};
+use curve25519_dalek::{
+    constants::RISTRETTO_BASEPOINT_TABLE,
+    ristretto::{CompressedRistretto, RistrettoPoint},
+    scalar::Scalar,
+};
+use digest::{Digest, FixedOutput};
+use generic_array::{
+    typenum::{Unsigned, U64},
+    ArrayLength, GenericArray,
+};
+use generic_bytes::SizedBytes;
+use hmac::{Hmac, Mac, NewMac};
+use rand::{CryptoRng, RngCore};
+use sha2::Sha512;
+use zeroize::Zeroize;
+
+use std::{convert::TryFrom, marker::PhantomData};
+
+static STR_SIGMA_I: &[u8] = b"SIGMA-I";
+static STR_SERVER_SIGNATURE: &[u8] = b"server signature";
+static STR_CLIENT_SIGNATURE: &[u8] = b"client signature";
+static STR_CLIENT_ENCRYPTION_PAD: &[u8] = b"client encryption pad";
+static STR_SCHNORR_NONCE: &[u8] = b"Schnorr nonce";
+
+/// A signature scheme whose signing and verifying keys are the static key
+/// pairs of the group `G`
+pub trait SignatureScheme<G: Group> {
+    /// The length of a signature
+    type SignatureLen: ArrayLength<u8>;
+
+    /// Signs the message with the signing key
+    fn sign(
+        signing_key: &PrivateKey<G>,
+        message: &[u8],
+    ) -> Result<GenericArray<u8, Self::SignatureLen>, InternalPakeError>;
+
+    /// Verifies the signature of the message under the verifying key
+    fn verify(
+        verifying_key: &PublicKey<G>,
+        message: &[u8],
+        signature: &GenericArray<u8, Self::SignatureLen>,
+    ) -> Result<(), PakeError>;
+}
+
+/// Schnorr signatures over Ristretto255 with SHA-512, in the style of
+/// Ed25519: the nonce is derived from the signing key and the message, and a
+/// signature is the nonce commitment followed by the response
+pub struct Schnorr;
+
+impl SignatureScheme<RistrettoPoint> for Schnorr {
+    type SignatureLen = U64;
+
+    fn sign(
+        signing_key: &PrivateKey<RistrettoPoint>,
+        message: &[u8],
+    ) -> Result<GenericArray<u8, Self::SignatureLen>, InternalPakeError> {
+        let mut x = RistrettoPoint::from_scalar_slice(GenericArray::from_slice(&signing_key[..]))?;
+        let verifying_key = KeyPair::<RistrettoPoint>::public_from_private(signing_key);
+
+        let mut r = Scalar::from_hash(
+            Sha512::new()
+                .chain(STR_SCHNORR_NONCE)
+                .chain(&signing_key[..])
+                .chain(message),
+        );
+        let commitment = (&r * &RISTRETTO_BASEPOINT_TABLE).compress();
+        let challenge = schnorr_challenge(&commitment, &verifying_key, message);
+        let response = r + challenge * x;
+        r.zeroize();
+        x.zeroize();
+
+        Ok(GenericArray::clone_from_slice(
+            &[&commitment.as_bytes()[..], &response.as_bytes()[..]].concat(),
+        ))
+    }
+
+    fn verify(
+        verifying_key: &PublicKey<RistrettoPoint>,
+        message: &[u8],
+        signature: &GenericArray<u8, Self::SignatureLen>,
+    ) -> Result<(), PakeError> {
+        let point = RistrettoPoint::from_element_slice(&verifying_key.to_arr())?;
+        let commitment = CompressedRistretto::from_slice(&signature[..32]);
+        let mut response_bytes = [0u8; 32];
+        response_bytes.copy_from_slice(&signature[32..]);
+        let response = Scalar::from_canonical_bytes(response_bytes)
+            .ok_or(PakeError::KeyExchangeMacValidationError)?;
+
+        let challenge = schnorr_challenge(&commitment, verifying_key, message);
+        let expected_commitment =
+            RistrettoPoint::vartime_double_scalar_mul_basepoint(&-challenge, &point, &response)
+                .compress();
+        if expected_commitment != commitment {
+            return Err(PakeError::KeyExchangeMacValidationError);
+        }
+        Ok(())
+    }
+}
+
+// The challenge binds the nonce commitment, the verifying key and the message
+fn schnorr_challenge(
+    commitment: &CompressedRistretto,
+    verifying_key: &PublicKey<RistrettoPoint>,
+    message: &[u8],
+) -> Scalar {
+    Scalar::from_hash(
+        Sha512::new()
+            .chain(commitment.as_bytes())
+            .chain(verifying_key.to_arr())
+            .chain(message),
+    )
+}
+
+/// Deterministic ECDSA (RFC 6979) over the group of the ciphersuite, with
+/// fixed-size signatures
+pub struct Ecdsa;
+
+// The signing key is the scalar of the static private key, and the verifying
+// key is the SEC1 compressed point of the static public key
+#[cfg(any(feature = "p256", feature = "p384"))]
+macro_rules! impl_ecdsa {
+    ($group:ty, $ecdsa:path, $signature_len:ty) => {
+        impl SignatureScheme<$group> for Ecdsa {
+            type SignatureLen = $signature_len;
+
+            fn sign(
+                signing_key: &PrivateKey<$group>,
+                message: &[u8],
+            ) -> Result<GenericArray<u8, Self::SignatureLen>, InternalPakeError> {
+                use $ecdsa::{signature::Signer, Signature, SigningKey};
+
+                let signing_key = SigningKey::from_slice(&signing_key[..])
+                    .map_err(|_| InternalPakeError::InvalidByteSequence)?;
+                let signature: Signature = signing_key.sign(message);
+                Ok(GenericArray::clone_from_slice(&signature.to_bytes()))
+            }
+
+            fn verify(
+                verifying_key: &PublicKey<$group>,
+                message: &[u8],
+                signature: &GenericArray<u8, Self::SignatureLen>,
+            ) -> Result<(), PakeError> {
+                use $ecdsa::{signature::Verifier, Signature, VerifyingKey};
+
+                let verifying_key = VerifyingKey::from_sec1_bytes(&verifying_key.to_arr())
+                    .map_err(|_| InternalPakeError::PointError)?;
+                Signature::from_slice(signature)
+                    .and_then(|signature| verifying_key.verify(message, &signature))
+                    .map_err(|_| PakeError::KeyExchangeMacValidationError)
+            }
+        }
+    };
+}
+
+#[cfg(feature = "p256")]
+impl_ecdsa!(
+    p256_::ProjectivePoint,
+    p256_::ecdsa,
+    generic_array::typenum::U64
+);
+
+#[cfg(feature = "p384")]
+impl_ecdsa!(
+    p384_::ProjectivePoint,
+    p384_::ecdsa,
+    generic_array::typenum::U96
+);
+
+/// The SIGMA-I key exchange implementation, signing with the signature
+/// scheme `S`
+pub struct SigmaI<S>(PhantomData<S>);
+
+impl<D: Hash, G: Group, S: SignatureScheme<G>> KeyExchange<D, G> for SigmaI<S> {
+    type KE1State = Ke1State<G>;
+    type KE2State = Ke2State<G, <D as FixedOutput>::OutputSize>;
+    type KE1Message = Ke1Message<G>;
+    type KE2Message = Ke2Message<G, S::SignatureLen, <D as FixedOutput>::OutputSize>;
+    type KE3Message = Ke3Message<S::SignatureLen, <D as FixedOutput>::OutputSize>;
+
+    fn generate_ke1<R: RngCore + CryptoRng>(
+        info: Vec<u8>,
+        rng: &mut R,
+    ) -> Result<(Self::KE1State, Self::KE1Message), ProtocolError> {
+        let client_e_kp = KeyPair::<G>::generate_random(rng);
+        let client_nonce = generate_nonce::<R>(rng);
+
+        let ke1_message = Ke1Message {
+            client_nonce,
+            info,
+            client_e_pk: client_e_kp.public().clone(),
+        };
+
+        Ok((
+            Ke1State {
+                client_e_sk: client_e_kp.private().clone(),
+                client_nonce,
+            },
+            ke1_message,
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn generate_ke2<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        serialized_credential_request: Vec<u8>,
+        l2_bytes: Vec<u8>,
+        ke1_message: Self::KE1Message,
+        client_s_pk: PublicKey<G>,
+        server_s_sk: PrivateKey<G>,
+        id_u: Vec<u8>,
+        id_s: Vec<u8>,
+        e_info: Vec<u8>,
+        context: Vec<u8>,
+    ) -> Result<(Vec<u8>, Self::KE2State, Self::KE2Message), ProtocolError> {
+        let server_e_kp = KeyPair::<G>::generate_random(rng);
+        let server_nonce = generate_nonce::<R>(rng);
+
+        let mut transcript_hasher = D::new()
+            .chain(STR_SIGMA_I)
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
+            .chain(&serialized_credential_request[..])
+            .chain(serialize(&id_s, 2))
+            .chain(&l2_bytes[..])
+            .chain(&server_nonce[..])
+            .chain(server_e_kp.public().to_arr());
+
+        let ikm = KeyPair::<G>::diffie_hellman(
+            ke1_message.client_e_pk.clone(),
+            server_e_kp.private().clone(),
+        )?;
+        let (session_key, km2, ke2, km3) =
+            derive_keys::<D>(&ikm, &transcript_hasher.clone().finalize())?;
+
+        // Compute encryption of e_info
+        let ciphertext = xor_with_encryption_pad::<D>(&ke2, &e_info)?;
+        transcript_hasher.update(serialize(&ciphertext, 2));
+
+        let signature = S::sign(
+            &server_s_sk,
+            &signature_input::<D>(STR_SERVER_SIGNATURE, &transcript_hasher),
+        )?;
+        transcript_hasher.update(&signature);
+
+        let mut mac_hasher =
+            Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
+        mac_hasher.update(&transcript_hasher.clone().finalize());
+        let mac = mac_hasher.finalize().into_bytes();
+
+        transcript_hasher.update(&mac);
+
+        Ok((
+            ke1_message.info,
+            Ke2State {
+                client_s_pk,
+                ke2,
+                km3,
+                hashed_transcript: transcript_hasher.finalize(),
+                session_key,
+            },
+            Ke2Message {
+                server_nonce,
+                server_e_pk: server_e_kp.public().clone(),
+                e_info: ciphertext,
+                signature,
+                mac,
+            },
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn generate_ke3(
+        l2_component: Vec<u8>,
+        ke2_message: Self::KE2Message,
+        ke1_state: &Self::KE1State,
+        serialized_credential_request: &[u8],
+        server_s_pk: PublicKey<G>,
+        client_s_sk: PrivateKey<G>,
+        id_u: Vec<u8>,
+        id_s: Vec<u8>,
+        context: Vec<u8>,
+    ) -> Result<(Vec<u8>, Vec<u8>, Self::KE3Message), ProtocolError> {
+        let mut transcript_hasher = D::new()
+            .chain(STR_SIGMA_I)
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
+            .chain(serialized_credential_request)
+            .chain(serialize(&id_s, 2))
+            .chain(&l2_component[..])
+            .chain(ke2_message.to_bytes_without_info_or_authentication());
+
+        let ikm = KeyPair::<G>::diffie_hellman(
+            ke2_message.server_e_pk.clone(),
+            ke1_state.client_e_sk.clone(),
+        )?;
+        let (session_key, km2, ke2, km3) =
+            derive_keys::<D>(&ikm, &transcript_hasher.clone().finalize())?;
+
+        transcript_hasher.update(serialize(&ke2_message.e_info[..], 2));
+
+        S::verify(
+            &server_s_pk,
+            &signature_input::<D>(STR_SERVER_SIGNATURE, &transcript_hasher),
+            &ke2_message.signature,
+        )?;
+        transcript_hasher.update(&ke2_message.signature);
+
+        let mut server_mac =
+            Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
+        server_mac.update(&transcript_hasher.clone().finalize());
+
+        // Mac::verify compares the tags in constant time
+        server_mac
+            .verify(&ke2_message.mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
+
+        transcript_hasher.update(&ke2_message.mac);
+        let signature = S::sign(
+            &client_s_sk,
+            &signature_input::<D>(STR_CLIENT_SIGNATURE, &transcript_hasher),
+        )?;
+        let hashed_transcript = transcript_hasher.finalize();
+
+        let mut client_mac =
+            Hmac::<D>::new_varkey(&km3).map_err(|_| InternalPakeError::HmacError)?;
+        client_mac.update(&hashed_transcript);
+        client_mac.update(&signature);
+
+        // Compute decryption of e_info
+        let plaintext = xor_with_encryption_pad::<D>(&ke2, &ke2_message.e_info)?;
+
+        Ok((
+            plaintext,
+            session_key.to_vec(),
+            Ke3Message::encrypt::<D>(&ke2, &signature, &client_mac.finalize().into_bytes())?,
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn finish_ke(
+        ke3_message: Self::KE3Message,
+        ke2_state: &Self::KE2State,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let (signature, mac) = ke3_message.decrypt::<D>(&ke2_state.ke2)?;
+        S::verify(
+            &ke2_state.client_s_pk,
+            &[STR_CLIENT_SIGNATURE, &ke2_state.hashed_transcript[..]].concat(),
+            &signature,
+        )?;
+
+        let mut client_mac =
+            Hmac::<D>::new_varkey(&ke2_state.km3).map_err(|_| InternalPakeError::HmacError)?;
+        client_mac.update(&ke2_state.hashed_transcript);
+        client_mac.update(&signature);
+
+        client_mac
+            .verify(&mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
+
+        Ok(ke2_state.session_key.to_vec())
+    }
+
+    fn ke2_message_size() -> usize {
+        NonceLen::to_usize()
+            + <G as Group>::ElemLen::to_usize()
+            + S::SignatureLen::to_usize()
+            + <<D as FixedOutput>::OutputSize as Unsigned>::to_usize()
+    }
+}
+
+// Each party signs its label followed by the hash of the transcript so far
+fn signature_input<D: Hash>(label: &[u8], transcript_hasher: &D) -> Vec<u8> {
+    [label, &transcript_hasher.clone().finalize()[..]].concat()
+}
+
+/// The server state produced after the second key exchange message
+pub struct Ke2State<G: Group, HashLen: ArrayLength<u8>> {
+    client_s_pk: PublicKey<G>,
+    ke2: GenericArray<u8, HashLen>,
+    km3: GenericArray<u8, HashLen>,
+    hashed_transcript: GenericArray<u8, HashLen>,
+    session_key: GenericArray<u8, HashLen>,
+}
+
+/// The second key exchange message
+pub struct Ke2Message<G: Group, SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>> {
+    server_nonce: GenericArray<u8, NonceLen>,
+    server_e_pk: PublicKey<G>,
+    e_info: Vec<u8>,
+    signature: GenericArray<u8, SignatureLen>,
+    mac: GenericArray<u8, HashLen>,
+}
+
+/// The third key exchange message, which carries the client's signature and
+/// MAC encrypted under the handshake encryption key
+pub struct Ke3Message<SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>> {
+    encrypted_signature: GenericArray<u8, SignatureLen>,
+    encrypted_mac: GenericArray<u8, HashLen>,
+}
+
+impl<SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>> Ke3Message<SignatureLen, HashLen> {
+    fn encrypt<D: Hash>(
+        ke2: &[u8],
+        signature: &GenericArray<u8, SignatureLen>,
+        mac: &GenericArray<u8, HashLen>,
+    ) -> Result<Self, ProtocolError> {
+        let ciphertext = xor_with_pad::<D>(
+            ke2,
+            STR_CLIENT_ENCRYPTION_PAD,
+            &[&signature[..], &mac[..]].concat(),
+        )?;
+        let signature_len = SignatureLen::to_usize();
+        Ok(Self {
+            encrypted_signature: GenericArray::clone_from_slice(&ciphertext[..signature_len]),
+            encrypted_mac: GenericArray::clone_from_slice(&ciphertext[signature_len..]),
+        })
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn decrypt<D: Hash>(
+        &self,
+        ke2: &[u8],
+    ) -> Result<(GenericArray<u8, SignatureLen>, GenericArray<u8, HashLen>), ProtocolError> {
+        let plaintext = xor_with_pad::<D>(ke2, STR_CLIENT_ENCRYPTION_PAD, &self.to_bytes())?;
+        let signature_len = SignatureLen::to_usize();
+        Ok((
+            GenericArray::clone_from_slice(&plaintext[..signature_len]),
+            GenericArray::clone_from_slice(&plaintext[signature_len..]),
+        ))
+    }
+}
+
+impl<G: Group, HashLen: ArrayLength<u8>> ToBytes for Ke2State<G, HashLen> {
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.client_s_pk.to_arr()[..],
+            &self.ke2[..],
+            &self.km3[..],
+            &self.hashed_transcript[..],
+            &self.session_key[..],
+        ]
+        .concat()
+    }
+}
+
+impl<G: Group, HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke2State<G, HashLen> {
+    type Error = PakeError;
+
+    fn try_from(input: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ElemLen::to_usize();
+        let hash_len = HashLen::to_usize();
+        let checked_bytes = check_slice_size(input, key_len + 4 * hash_len, "ke2_state")?;
+        let hashes = &checked_bytes[key_len..];
+
+        Ok(Self {
+            client_s_pk: PublicKey::from_bytes(&checked_bytes[..key_len])?,
+            ke2: GenericArray::clone_from_slice(&hashes[..hash_len]),
+            km3: GenericArray::clone_from_slice(&hashes[hash_len..2 * hash_len]),
+            hashed_transcript: GenericArray::clone_from_slice(&hashes[2 * hash_len..3 * hash_len]),
+            session_key: GenericArray::clone_from_slice(&hashes[3 * hash_len..4 * hash_len]),
+        })
+    }
+}
+
+impl<G: Group, SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>> ToBytes
+    for Ke2Message<G, SignatureLen, HashLen>
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.to_bytes_without_info_or_authentication(),
+            &serialize(&self.e_info, 2),
+            &self.signature[..],
+            &self.mac[..],
+        ]
+        .concat()
+    }
+}
+
+impl<G: Group, SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>>
+    Ke2Message<G, SignatureLen, HashLen>
+{
+    fn to_bytes_without_info_or_authentication(&self) -> Vec<u8> {
+        [&self.server_nonce[..], &self.server_e_pk.to_arr()].concat()
+    }
+}
+
+impl<G: Group, SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>> TryFrom<&[u8]>
+    for Ke2Message<G, SignatureLen, HashLen>
+{
+    type Error = PakeError;
+
+    fn try_from(input: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ElemLen::to_usize();
+        let nonce_len = NonceLen::to_usize();
+        let signature_len = SignatureLen::to_usize();
+        let checked_nonce = check_slice_size_atleast(input, nonce_len, "ke2_message nonce")?;
+        let checked_server_e_pk = check_slice_size_atleast(
+            &checked_nonce[nonce_len..],
+            key_len,
+            "ke2_message server_e_pk",
+        )?;
+        let (e_info, remainder) = tokenize(&checked_server_e_pk[key_len..], 2)?;
+        let checked_authentication = check_slice_size(
+            &remainder,
+            signature_len + HashLen::to_usize(),
+            "ke2_message signature and mac",
+        )?;
+
+        Ok(Self {
+            server_nonce: GenericArray::clone_from_slice(&checked_nonce[..nonce_len]),
+            server_e_pk: PublicKey::from_bytes(&checked_server_e_pk[..key_len])?,
+            e_info,
+            signature: GenericArray::clone_from_slice(&checked_authentication[..signature_len]),
+            mac: GenericArray::clone_from_slice(&checked_authentication[signature_len..]),
+        })
+    }
+}
+
+impl<SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>> ToBytes
+    for Ke3Message<SignatureLen, HashLen>
+{
+    fn to_bytes(&self) -> Vec<u8> {
+        [&self.encrypted_signature[..], &self.encrypted_mac[..]].concat()
+    }
+}
+
+impl<SignatureLen: ArrayLength<u8>, HashLen: ArrayLength<u8>> TryFrom<&[u8]>
+    for Ke3Message<SignatureLen, HashLen>
+{
+    type Error = PakeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let signature_len = SignatureLen::to_usize();
+        let checked_bytes =
+            check_slice_size(bytes, signature_len + HashLen::to_usize(), "ke3_message")?;
+
+        Ok(Self {
+            encrypted_signature: GenericArray::clone_from_slice(&checked_bytes[..signature_len]),
+            encrypted_mac: GenericArray::clone_from_slice(&checked_bytes[signature_len..]),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::OsRng;
+
+    // Checks that signatures verify under the signing key's public key only,
+    // and only for the signed message
+    fn sign_and_verify<G: Group, S: SignatureScheme<G>>() -> Result<(), ProtocolError> {
+        let mut rng = OsRng;
+        let kp = KeyPair::<G>::generate_random(&mut rng);
+        let other_kp = KeyPair::<G>::generate_random(&mut rng);
+
+        let signature = S::sign(kp.private(), b"message")?;
+        S::verify(kp.public(), b"message", &signature)?;
+
+        // Signing is deterministic
+        assert_eq!(signature, S::sign(kp.private(), b"message")?);
+
+        let mut tampered_signature = signature.clone();
+        tampered_signature[0] ^= 1;
+        let mut tampered_response = signature.clone();
+        tampered_response[S::SignatureLen::to_usize() - 1] ^= 1;
+        for (public_key, message, signature) in &[
+            (other_kp.public(), &b"message"[..], &signature),
+            (kp.public(), &b"other message"[..], &signature),
+            (kp.public(), &b"message"[..], &tampered_signature),
+            (kp.public(), &b"message"[..], &tampered_response),
+        ] {
+            assert!(matches!(
+                S::verify(public_key, message, signature),
+                Err(PakeError::KeyExchangeMacValidationError)
+            ));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn sign_and_verify_schnorr() -> Result<(), ProtocolError> {
+        sign_and_verify::<RistrettoPoint, Schnorr>()
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn sign_and_verify_ecdsa_p256() -> Result<(), ProtocolError> {
+        use p256_::ProjectivePoint;
+
+        sign_and_verify::<ProjectivePoint, Ecdsa>()?;
+
+        // The static key pair is an ordinary ECDSA key pair of the curve
+        let kp = KeyPair::<ProjectivePoint>::generate_random(&mut OsRng);
+        let verifying_key = p256_::ecdsa::SigningKey::from_slice(&kp.private()[..])
+            .unwrap()
+            .verifying_key()
+            .to_encoded_point(true);
+        assert_eq!(verifying_key.as_bytes(), &kp.public().to_arr()[..]);
+        Ok(())
+    }
+
+    #[cfg(feature = "p384")]
+    #[test]
+    fn sign_and_verify_ecdsa_p384() -> Result<(), ProtocolError> {
+        sign_and_verify::<p384_::ProjectivePoint, Ecdsa>()
+    }
+}
@@ -7,7 +7,7 @@ use crate::{
     errors::{PakeError, ProtocolError},
     group::Group,
     hash::Hash,
-    keypair::Key,
+    keypair::{PrivateKey, PublicKey},
 };
 use rand::{CryptoRng, RngCore};
 
@@ -31,11 +31,12 @@ pub trait KeyExchange<D: Hash, G: Group> {
         l1_bytes: Vec<u8>,
         l2_bytes: Vec<u8>,
         ke1_message: Self::KE1Message,
-        client_s_pk: Key,
-        server_s_sk: Key,
+        client_s_pk: PublicKey<G>,
+        server_s_sk: PrivateKey<G>,
         id_u: Vec<u8>,
         id_s: Vec<u8>,
         e_info: Vec<u8>,
+        context: Vec<u8>,
     ) -> Result<(Vec<u8>, Self::KE2State, Self::KE2Message), ProtocolError>;
 
     #[allow(clippy::too_many_arguments, clippy::type_complexity)]
@@ -44,10 +45,11 @@ pub trait KeyExchange<D: Hash, G: Group> {
         ke2_message: Self::KE2Message,
         ke1_state: &Self::KE1State,
         serialized_credential_request: &[u8],
-        server_s_pk: Key,
-        client_s_sk: Key,
+        server_s_pk: PublicKey<G>,
+        client_s_sk: PrivateKey<G>,
         id_u: Vec<u8>,
         id_s: Vec<u8>,
+        context: Vec<u8>,
     ) -> Result<(Vec<u8>, Vec<u8>, Self::KE3Message), ProtocolError>;
 
     #[allow(clippy::type_complexity)]
@@ -12,7 +12,7 @@ use crate::{
     group::Group,
     hash::Hash,
     key_exchange::traits::{KeyExchange, ToBytes},
-    keypair::{Key, KeyPair, SizedBytesExt},
+    keypair::{KeyPair, PrivateKey, PublicKey, SizedBytesExt},
     serialization::{serialize, tokenize},
 };
 use digest::{Digest, FixedOutput};
@@ -24,10 +24,10 @@ use generic_bytes::SizedBytes;
 use hkdf::Hkdf;
 use hmac::{Hmac, Mac, NewMac};
 use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
 
 use std::convert::TryFrom;
 
-const KEY_LEN: usize = 32;
 pub(crate) type NonceLen = U32;
 
 static STR_3DH: &[u8] = b"3DH";
@@ -38,16 +38,21 @@ static STR_HANDSHAKE_ENC: &[u8] = b"handshake enc";
 static STR_ENCRYPTION_PAD: &[u8] = b"encryption pad";
 static STR_SESSION_SECRET: &[u8] = b"session secret";
 static STR_OPAQUE: &[u8] = b"OPAQUE ";
+static STR_CONTEXT: &[u8] = b"context";
+
+/// The maximum length of the application context of a login, which is
+/// encoded with a two-byte length prefix
+pub const MAX_CONTEXT_LEN: usize = 65535;
 
 #[allow(clippy::upper_case_acronyms)]
 /// The Triple Diffie-Hellman key exchange implementation
 pub struct TripleDH;
 
 impl<D: Hash, G: Group> KeyExchange<D, G> for TripleDH {
-    type KE1State = Ke1State;
+    type KE1State = Ke1State<G>;
     type KE2State = Ke2State<<D as FixedOutput>::OutputSize>;
-    type KE1Message = Ke1Message;
-    type KE2Message = Ke2Message<<D as FixedOutput>::OutputSize>;
+    type KE1Message = Ke1Message<G>;
+    type KE2Message = Ke2Message<G, <D as FixedOutput>::OutputSize>;
     type KE3Message = Ke3Message<<D as FixedOutput>::OutputSize>;
 
     fn generate_ke1<R: RngCore + CryptoRng>(
@@ -78,23 +83,25 @@ impl<D: Hash, G: Group> KeyExchange<D, G> for TripleDH {
         serialized_credential_request: Vec<u8>,
         l2_bytes: Vec<u8>,
         ke1_message: Self::KE1Message,
-        client_s_pk: Key,
-        server_s_sk: Key,
+        client_s_pk: PublicKey<G>,
+        server_s_sk: PrivateKey<G>,
         id_u: Vec<u8>,
         id_s: Vec<u8>,
         e_info: Vec<u8>,
+        context: Vec<u8>,
     ) -> Result<(Vec<u8>, Self::KE2State, Self::KE2Message), ProtocolError> {
         let server_e_kp = KeyPair::<G>::generate_random(rng);
         let server_nonce = generate_nonce::<R>(rng);
 
         let mut transcript_hasher = D::new()
             .chain(STR_3DH)
-            .chain(&serialize(&id_u, 2))
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
             .chain(&serialized_credential_request[..])
-            .chain(&serialize(&id_s, 2))
+            .chain(serialize(&id_s, 2))
             .chain(&l2_bytes[..])
             .chain(&server_nonce[..])
-            .chain(&server_e_kp.public().to_arr());
+            .chain(server_e_kp.public().to_arr());
 
         let (session_key, km2, ke2, km3) = derive_3dh_keys::<D, G>(
             TripleDHComponents {
@@ -105,21 +112,14 @@ impl<D: Hash, G: Group> KeyExchange<D, G> for TripleDH {
                 pk3: client_s_pk,
                 sk3: server_e_kp.private().clone(),
             },
+            &[],
             &transcript_hasher.clone().finalize(),
         )?;
 
         // Compute encryption of e_info
-        let h = Hkdf::<D>::from_prk(&ke2).map_err(|_| InternalPakeError::HkdfError)?;
-        let mut encryption_pad = vec![0u8; e_info.len()];
-        h.expand(STR_ENCRYPTION_PAD, &mut encryption_pad)
-            .map_err(|_| InternalPakeError::HkdfError)?;
-        let ciphertext: Vec<u8> = encryption_pad
-            .iter()
-            .zip(e_info.iter())
-            .map(|(&x1, &x2)| x1 ^ x2)
-            .collect();
-
-        transcript_hasher.update(&serialize(&ciphertext, 2));
+        let ciphertext = xor_with_encryption_pad::<D>(&ke2, &e_info)?;
+
+        transcript_hasher.update(serialize(&ciphertext, 2));
 
         let mut mac_hasher =
             Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
@@ -150,18 +150,20 @@ impl<D: Hash, G: Group> KeyExchange<D, G> for TripleDH {
         ke2_message: Self::KE2Message,
         ke1_state: &Self::KE1State,
         serialized_credential_request: &[u8],
-        server_s_pk: Key,
-        client_s_sk: Key,
+        server_s_pk: PublicKey<G>,
+        client_s_sk: PrivateKey<G>,
         id_u: Vec<u8>,
         id_s: Vec<u8>,
+        context: Vec<u8>,
     ) -> Result<(Vec<u8>, Vec<u8>, Self::KE3Message), ProtocolError> {
         let mut transcript_hasher = D::new()
             .chain(STR_3DH)
-            .chain(&serialize(&id_u, 2))
-            .chain(&serialized_credential_request)
-            .chain(&serialize(&id_s, 2))
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
+            .chain(serialized_credential_request)
+            .chain(serialize(&id_s, 2))
             .chain(&l2_component[..])
-            .chain(&ke2_message.to_bytes_without_info_or_mac());
+            .chain(ke2_message.to_bytes_without_info_or_mac());
 
         let (session_key, km2, ke2, km3) = derive_3dh_keys::<D, G>(
             TripleDHComponents {
@@ -172,37 +174,29 @@ impl<D: Hash, G: Group> KeyExchange<D, G> for TripleDH {
                 pk3: ke2_message.server_e_pk.clone(),
                 sk3: client_s_sk,
             },
+            &[],
             &transcript_hasher.clone().finalize(),
         )?;
 
-        transcript_hasher.update(&serialize(&ke2_message.e_info[..], 2));
+        transcript_hasher.update(serialize(&ke2_message.e_info[..], 2));
 
         let mut server_mac =
             Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
         server_mac.update(&transcript_hasher.clone().finalize());
 
-        if ke2_message.mac != server_mac.finalize().into_bytes() {
-            return Err(ProtocolError::VerificationError(
-                PakeError::KeyExchangeMacValidationError,
-            ));
-        }
+        // Mac::verify compares the tags in constant time
+        server_mac
+            .verify(&ke2_message.mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
 
-        transcript_hasher.update(ke2_message.mac.to_vec());
+        transcript_hasher.update(&ke2_message.mac);
 
         let mut client_mac =
             Hmac::<D>::new_varkey(&km3).map_err(|_| InternalPakeError::HmacError)?;
         client_mac.update(&transcript_hasher.finalize());
 
         // Compute decryption of e_info
-        let h = Hkdf::<D>::from_prk(&ke2).map_err(|_| InternalPakeError::HkdfError)?;
-        let mut encryption_pad = vec![0u8; ke2_message.e_info.len()];
-        h.expand(STR_ENCRYPTION_PAD, &mut encryption_pad)
-            .map_err(|_| InternalPakeError::HkdfError)?;
-        let plaintext: Vec<u8> = encryption_pad
-            .iter()
-            .zip(ke2_message.e_info.iter())
-            .map(|(&x1, &x2)| x1 ^ x2)
-            .collect();
+        let plaintext = xor_with_encryption_pad::<D>(&ke2, &ke2_message.e_info)?;
 
         Ok((
             plaintext,
@@ -222,59 +216,60 @@ impl<D: Hash, G: Group> KeyExchange<D, G> for TripleDH {
             Hmac::<D>::new_varkey(&ke2_state.km3).map_err(|_| InternalPakeError::HmacError)?;
         client_mac.update(&ke2_state.hashed_transcript);
 
-        if ke3_message.mac != client_mac.finalize().into_bytes() {
-            return Err(ProtocolError::VerificationError(
-                PakeError::KeyExchangeMacValidationError,
-            ));
-        }
+        client_mac
+            .verify(&ke3_message.mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
 
         Ok(ke2_state.session_key.to_vec())
     }
 
     fn ke2_message_size() -> usize {
-        NonceLen::to_usize() + KEY_LEN + <<D as FixedOutput>::OutputSize as Unsigned>::to_usize()
+        NonceLen::to_usize()
+            + <G as Group>::ElemLen::to_usize()
+            + <<D as FixedOutput>::OutputSize as Unsigned>::to_usize()
     }
 }
 
 /// The client state produced after the first key exchange message
 #[derive(PartialEq, Eq)]
-pub struct Ke1State {
-    client_e_sk: Key,
-    client_nonce: GenericArray<u8, NonceLen>,
+pub struct Ke1State<G: Group> {
+    pub(super) client_e_sk: PrivateKey<G>,
+    pub(super) client_nonce: GenericArray<u8, NonceLen>,
 }
 
 /// The first key exchange message
 #[derive(PartialEq, Eq)]
-pub struct Ke1Message {
+pub struct Ke1Message<G: Group> {
     pub(crate) client_nonce: GenericArray<u8, NonceLen>,
     pub(crate) info: Vec<u8>,
-    pub(crate) client_e_pk: Key,
+    pub(crate) client_e_pk: PublicKey<G>,
 }
 
-impl TryFrom<&[u8]> for Ke1State {
+impl<G: Group> TryFrom<&[u8]> for Ke1State<G> {
     type Error = PakeError;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ScalarLen::to_usize();
         let nonce_len = NonceLen::to_usize();
-        let checked_bytes = check_slice_size_atleast(bytes, KEY_LEN + nonce_len, "ke1_state")?;
+        let checked_bytes = check_slice_size_atleast(bytes, key_len + nonce_len, "ke1_state")?;
 
         Ok(Self {
-            client_e_sk: Key::from_bytes(&checked_bytes[..KEY_LEN])?,
+            client_e_sk: PrivateKey::from_bytes(&checked_bytes[..key_len])?,
             client_nonce: GenericArray::clone_from_slice(
-                &checked_bytes[KEY_LEN..KEY_LEN + nonce_len],
+                &checked_bytes[key_len..key_len + nonce_len],
             ),
         })
     }
 }
 
-impl ToBytes for Ke1State {
+impl<G: Group> ToBytes for Ke1State<G> {
     fn to_bytes(&self) -> Vec<u8> {
         let output: Vec<u8> = [&self.client_e_sk.to_arr(), &self.client_nonce[..]].concat();
         output
     }
 }
 
-impl ToBytes for Ke1Message {
+impl<G: Group> ToBytes for Ke1Message<G> {
     fn to_bytes(&self) -> Vec<u8> {
         [
             &self.client_nonce[..],
@@ -285,36 +280,37 @@ impl ToBytes for Ke1Message {
     }
 }
 
-impl TryFrom<&[u8]> for Ke1Message {
+impl<G: Group> TryFrom<&[u8]> for Ke1Message<G> {
     type Error = PakeError;
 
     fn try_from(ke1_message_bytes: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ElemLen::to_usize();
         let nonce_len = NonceLen::to_usize();
         let checked_nonce =
             check_slice_size_atleast(ke1_message_bytes, nonce_len, "ke1_message nonce")?;
 
         let (info, remainder) = tokenize(&checked_nonce[nonce_len..], 2)?;
 
-        let checked_client_e_pk = check_slice_size(&remainder, KEY_LEN, "ke1_message client_e_pk")?;
+        let checked_client_e_pk = check_slice_size(&remainder, key_len, "ke1_message client_e_pk")?;
 
         Ok(Self {
             client_nonce: GenericArray::clone_from_slice(&checked_nonce[..nonce_len]),
             info,
-            client_e_pk: Key::from_bytes(&checked_client_e_pk)?,
+            client_e_pk: PublicKey::from_bytes(checked_client_e_pk)?,
         })
     }
 }
 /// The server state produced after the second key exchange message
 pub struct Ke2State<HashLen: ArrayLength<u8>> {
-    km3: GenericArray<u8, HashLen>,
-    hashed_transcript: GenericArray<u8, HashLen>,
-    session_key: GenericArray<u8, HashLen>,
+    pub(super) km3: GenericArray<u8, HashLen>,
+    pub(super) hashed_transcript: GenericArray<u8, HashLen>,
+    pub(super) session_key: GenericArray<u8, HashLen>,
 }
 
 /// The second key exchange message
-pub struct Ke2Message<HashLen: ArrayLength<u8>> {
+pub struct Ke2Message<G: Group, HashLen: ArrayLength<u8>> {
     server_nonce: GenericArray<u8, NonceLen>,
-    server_e_pk: Key,
+    server_e_pk: PublicKey<G>,
     e_info: Vec<u8>,
     mac: GenericArray<u8, HashLen>,
 }
@@ -347,7 +343,7 @@ impl<HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke2State<HashLen> {
     }
 }
 
-impl<HashLen: ArrayLength<u8>> ToBytes for Ke2Message<HashLen> {
+impl<G: Group, HashLen: ArrayLength<u8>> ToBytes for Ke2Message<G, HashLen> {
     fn to_bytes(&self) -> Vec<u8> {
         [
             &self.to_bytes_without_info_or_mac(),
@@ -358,49 +354,50 @@ impl<HashLen: ArrayLength<u8>> ToBytes for Ke2Message<HashLen> {
     }
 }
 
-impl<HashLen: ArrayLength<u8>> Ke2Message<HashLen> {
+impl<G: Group, HashLen: ArrayLength<u8>> Ke2Message<G, HashLen> {
     fn to_bytes_without_info_or_mac(&self) -> Vec<u8> {
         [&self.server_nonce[..], &self.server_e_pk.to_arr()].concat()
     }
 }
 
-impl<HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke2Message<HashLen> {
+impl<G: Group, HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke2Message<G, HashLen> {
     type Error = PakeError;
 
     fn try_from(input: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ElemLen::to_usize();
         let nonce_len = NonceLen::to_usize();
         let checked_nonce = check_slice_size_atleast(input, nonce_len, "ke2_message nonce")?;
         let checked_server_e_pk = check_slice_size_atleast(
             &checked_nonce[nonce_len..],
-            KEY_LEN,
+            key_len,
             "ke2_message server_e_pk",
         )?;
-        let (e_info, remainder) = tokenize(&checked_server_e_pk[KEY_LEN..], 2)?;
+        let (e_info, remainder) = tokenize(&checked_server_e_pk[key_len..], 2)?;
         let checked_mac = check_slice_size(&remainder, HashLen::to_usize(), "ke1_message mac")?;
 
         Ok(Self {
             server_nonce: GenericArray::clone_from_slice(&checked_nonce[..nonce_len]),
-            server_e_pk: Key::from_bytes(&checked_server_e_pk[..KEY_LEN])?,
+            server_e_pk: PublicKey::from_bytes(&checked_server_e_pk[..key_len])?,
             e_info,
-            mac: GenericArray::clone_from_slice(&checked_mac),
+            mac: GenericArray::clone_from_slice(checked_mac),
         })
     }
 }
 
 #[allow(clippy::upper_case_acronyms)]
 // The triple of public and private components used in the 3DH computation
-struct TripleDHComponents {
-    pk1: Key,
-    sk1: Key,
-    pk2: Key,
-    sk2: Key,
-    pk3: Key,
-    sk3: Key,
+pub(super) struct TripleDHComponents<G: Group> {
+    pub(super) pk1: PublicKey<G>,
+    pub(super) sk1: PrivateKey<G>,
+    pub(super) pk2: PublicKey<G>,
+    pub(super) sk2: PrivateKey<G>,
+    pub(super) pk3: PublicKey<G>,
+    pub(super) sk3: PrivateKey<G>,
 }
 
 #[allow(clippy::upper_case_acronyms)]
 // Consists of a session key, followed by two mac keys and an encryption key: (session_key, km2, ke2, km3)
-type TripleDHDerivationResult<D> = (
+pub(super) type TripleDHDerivationResult<D> = (
     GenericArray<u8, <D as FixedOutput>::OutputSize>,
     GenericArray<u8, <D as FixedOutput>::OutputSize>,
     GenericArray<u8, <D as FixedOutput>::OutputSize>,
@@ -409,7 +406,7 @@ type TripleDHDerivationResult<D> = (
 
 /// The third key exchange message
 pub struct Ke3Message<HashLen: ArrayLength<u8>> {
-    mac: GenericArray<u8, HashLen>,
+    pub(super) mac: GenericArray<u8, HashLen>,
 }
 
 impl<HashLen: ArrayLength<u8>> ToBytes for Ke3Message<HashLen> {
@@ -422,10 +419,10 @@ impl<HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke3Message<HashLen> {
     type Error = PakeError;
 
     fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
-        let checked_bytes = check_slice_size(&bytes, HashLen::to_usize(), "ke3_message")?;
+        let checked_bytes = check_slice_size(bytes, HashLen::to_usize(), "ke3_message")?;
 
         Ok(Self {
-            mac: GenericArray::clone_from_slice(&checked_bytes),
+            mac: GenericArray::clone_from_slice(checked_bytes),
         })
     }
 }
@@ -433,45 +430,75 @@ impl<HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke3Message<HashLen> {
 // Helper functions
 
 // Internal function which takes the public and private components of the client and server keypairs, along
-// with some auxiliary metadata, to produce the session key and two MAC keys
-fn derive_3dh_keys<D: Hash, G: Group>(
-    dh: TripleDHComponents,
+// with some auxiliary metadata, to produce the session key and two MAC keys. An additional shared secret, such
+// as that of a KEM, is appended to the Diffie-Hellman secrets when it is not empty.
+pub(super) fn derive_3dh_keys<D: Hash, G: Group>(
+    dh: TripleDHComponents<G>,
+    additional_secret: &[u8],
     hashed_derivation_transcript: &[u8],
 ) -> Result<TripleDHDerivationResult<D>, ProtocolError> {
-    let ikm: Vec<u8> = [
+    let mut ikm: Vec<u8> = [
         &KeyPair::<G>::diffie_hellman(dh.pk1, dh.sk1)?[..],
         &KeyPair::<G>::diffie_hellman(dh.pk2, dh.sk2)?[..],
         &KeyPair::<G>::diffie_hellman(dh.pk3, dh.sk3)?[..],
+        additional_secret,
     ]
     .concat();
 
-    let extracted_ikm = Hkdf::<D>::new(None, &ikm);
+    let derived_keys = derive_keys::<D>(&ikm, hashed_derivation_transcript);
+    ikm.zeroize();
+    derived_keys
+}
+
+// The encoding of the application context in the transcript preamble, right
+// after the protocol label. The length prefix is always present, so that an
+// empty context is encoded the same way as any other.
+pub(super) fn serialize_context(context: &[u8]) -> Result<Vec<u8>, InternalPakeError> {
+    check_context_len(context)?;
+    Ok([STR_CONTEXT, &serialize(context, 2)].concat())
+}
+
+// Rejects contexts whose length does not fit in their length prefix
+pub(crate) fn check_context_len(context: &[u8]) -> Result<(), InternalPakeError> {
+    if context.len() > MAX_CONTEXT_LEN {
+        return Err(InternalPakeError::ContextTooLongError);
+    }
+    Ok(())
+}
+
+// The key schedule from the shared Diffie-Hellman secrets, which is common
+// to the key exchange protocols
+pub(super) fn derive_keys<D: Hash>(
+    ikm: &[u8],
+    hashed_derivation_transcript: &[u8],
+) -> Result<TripleDHDerivationResult<D>, ProtocolError> {
+    let extracted_ikm = Hkdf::<D>::new(None, ikm);
     let handshake_secret = derive_secrets::<D>(
         &extracted_ikm,
-        &STR_HANDSHAKE_SECRET,
-        &hashed_derivation_transcript,
+        STR_HANDSHAKE_SECRET,
+        hashed_derivation_transcript,
     )?;
     let session_key = derive_secrets::<D>(
         &extracted_ikm,
-        &STR_SESSION_SECRET,
-        &hashed_derivation_transcript,
+        STR_SESSION_SECRET,
+        hashed_derivation_transcript,
     )?;
 
     let km2 = hkdf_expand_label::<D>(
         &handshake_secret,
-        &STR_SERVER_MAC,
+        STR_SERVER_MAC,
         b"",
         <D as Digest>::OutputSize::to_usize(),
     )?;
     let ke2 = hkdf_expand_label::<D>(
         &handshake_secret,
-        &STR_HANDSHAKE_ENC,
+        STR_HANDSHAKE_ENC,
         b"",
         <D as Digest>::OutputSize::to_usize(),
     )?;
     let km3 = hkdf_expand_label::<D>(
         &handshake_secret,
-        &STR_CLIENT_MAC,
+        STR_CLIENT_MAC,
         b"",
         <D as Digest>::OutputSize::to_usize(),
     )?;
@@ -506,11 +533,11 @@ fn hkdf_expand_label_extracted<D: Hash>(
     hkdf_label.extend_from_slice(&length.to_be_bytes()[std::mem::size_of::<usize>() - 2..]);
 
     let mut opaque_label: Vec<u8> = Vec::new();
-    opaque_label.extend_from_slice(&STR_OPAQUE);
-    opaque_label.extend_from_slice(&label);
+    opaque_label.extend_from_slice(STR_OPAQUE);
+    opaque_label.extend_from_slice(label);
     hkdf_label.extend_from_slice(&serialize(&opaque_label, 1));
 
-    hkdf_label.extend_from_slice(&serialize(&context, 1));
+    hkdf_label.extend_from_slice(&serialize(context, 1));
 
     hkdf.expand(&hkdf_label, &mut okm)
         .map_err(|_| InternalPakeError::HkdfError)?;
@@ -525,13 +552,40 @@ fn derive_secrets<D: Hash>(
     hkdf_expand_label_extracted::<D>(
         hkdf,
         label,
-        &hashed_derivation_transcript,
+        hashed_derivation_transcript,
         <D as Digest>::OutputSize::to_usize(),
     )
 }
 
+// Encrypts or decrypts the info field of the second message with a pad
+// expanded from the handshake encryption key
+pub(super) fn xor_with_encryption_pad<D: Hash>(
+    ke2: &[u8],
+    input: &[u8],
+) -> Result<Vec<u8>, ProtocolError> {
+    xor_with_pad::<D>(ke2, STR_ENCRYPTION_PAD, input)
+}
+
+// Encrypts or decrypts the input with a pad expanded from the handshake
+// encryption key under the label, so that each label yields its own pad
+pub(super) fn xor_with_pad<D: Hash>(
+    ke2: &[u8],
+    label: &[u8],
+    input: &[u8],
+) -> Result<Vec<u8>, ProtocolError> {
+    let h = Hkdf::<D>::from_prk(ke2).map_err(|_| InternalPakeError::HkdfError)?;
+    let mut encryption_pad = vec![0u8; input.len()];
+    h.expand(label, &mut encryption_pad)
+        .map_err(|_| InternalPakeError::HkdfError)?;
+    Ok(encryption_pad
+        .iter()
+        .zip(input.iter())
+        .map(|(&x1, &x2)| x1 ^ x2)
+        .collect())
+}
+
 // Generate a random nonce up to NonceLen::to_usize() bytes.
-fn generate_nonce<R: RngCore + CryptoRng>(rng: &mut R) -> GenericArray<u8, NonceLen> {
+pub(super) fn generate_nonce<R: RngCore + CryptoRng>(rng: &mut R) -> GenericArray<u8, NonceLen> {
     let mut nonce_bytes = vec![0u8; NonceLen::to_usize()];
     rng.fill_bytes(&mut nonce_bytes);
     GenericArray::clone_from_slice(&nonce_bytes)
@@ -0,0 +1,505 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+//! An implementation of a hybrid key exchange protocol, which runs Triple
+//! Diffie-Hellman together with an ML-KEM-768 key encapsulation
+//!
+//! The client sends an ephemeral ML-KEM encapsulation key in the first
+//! message, and the server encapsulates a shared secret to it and returns the
+//! ciphertext in the second message. The KEM shared secret is appended to the
+//! three Diffie-Hellman secrets in the 3DH key schedule, so that the session
+//! key remains secret as long as either the Diffie-Hellman problem in the
+//! group or ML-KEM is hard. This protects recorded sessions against a future
+//! quantum adversary, while authentication still relies on the static
+//! Diffie-Hellman keys.
+//!
+//! This module requires the `ml-kem` feature.
+use crate::{
+    errors::{
+        utils::{check_slice_size, check_slice_size_atleast},
+        InternalPakeError, PakeError, ProtocolError,
+    },
+    group::Group,
+    hash::Hash,
+    key_exchange::{
+        traits::{KeyExchange, ToBytes},
+        tripledh::{
+            derive_3dh_keys, generate_nonce, serialize_context, xor_with_encryption_pad, Ke2State,
+            Ke3Message, NonceLen, TripleDHComponents,
+        },
+    },
+    keypair::{KeyPair, PrivateKey, PublicKey, SizedBytesExt},
+    serialization::{serialize, tokenize},
+};
+use digest::{Digest, FixedOutput};
+use generic_array::{typenum::Unsigned, ArrayLength, GenericArray};
+use generic_bytes::SizedBytes;
+use hmac::{Hmac, Mac, NewMac};
+use ml_kem_::{
+    array::Array,
+    kem::{Decapsulate, Encapsulate},
+    Ciphertext, EncodedSizeUser, KemCore, MlKem768,
+};
+use rand::{CryptoRng, RngCore};
+use zeroize::Zeroize;
+
+use std::convert::TryFrom;
+
+static STR_3DH_ML_KEM_768: &[u8] = b"3DH-ML-KEM-768";
+
+type DecapsulationKey = <MlKem768 as KemCore>::DecapsulationKey;
+type EncapsulationKey = <MlKem768 as KemCore>::EncapsulationKey;
+type DecapsulationKeyLen = <DecapsulationKey as EncodedSizeUser>::EncodedSize;
+type EncapsulationKeyLen = <EncapsulationKey as EncodedSizeUser>::EncodedSize;
+type CiphertextLen = <MlKem768 as KemCore>::CiphertextSize;
+
+/// The hybrid Triple Diffie-Hellman and ML-KEM-768 key exchange
+/// implementation
+#[allow(clippy::upper_case_acronyms)]
+pub struct TripleDHMlKem768;
+
+impl<D: Hash, G: Group> KeyExchange<D, G> for TripleDHMlKem768 {
+    type KE1State = Ke1State<G>;
+    type KE2State = Ke2State<<D as FixedOutput>::OutputSize>;
+    type KE1Message = Ke1Message<G>;
+    type KE2Message = Ke2Message<G, <D as FixedOutput>::OutputSize>;
+    type KE3Message = Ke3Message<<D as FixedOutput>::OutputSize>;
+
+    fn generate_ke1<R: RngCore + CryptoRng>(
+        info: Vec<u8>,
+        rng: &mut R,
+    ) -> Result<(Self::KE1State, Self::KE1Message), ProtocolError> {
+        let client_e_kp = KeyPair::<G>::generate_random(rng);
+        let client_nonce = generate_nonce::<R>(rng);
+        let (client_dk, client_ek) = MlKem768::generate(rng);
+
+        let ke1_message = Ke1Message {
+            client_nonce,
+            info,
+            client_e_pk: client_e_kp.public().clone(),
+            client_ek,
+        };
+
+        Ok((
+            Ke1State {
+                client_e_sk: client_e_kp.private().clone(),
+                client_nonce,
+                client_dk,
+            },
+            ke1_message,
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn generate_ke2<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        serialized_credential_request: Vec<u8>,
+        l2_bytes: Vec<u8>,
+        ke1_message: Self::KE1Message,
+        client_s_pk: PublicKey<G>,
+        server_s_sk: PrivateKey<G>,
+        id_u: Vec<u8>,
+        id_s: Vec<u8>,
+        e_info: Vec<u8>,
+        context: Vec<u8>,
+    ) -> Result<(Vec<u8>, Self::KE2State, Self::KE2Message), ProtocolError> {
+        let server_e_kp = KeyPair::<G>::generate_random(rng);
+        let server_nonce = generate_nonce::<R>(rng);
+        let (kem_ciphertext, mut kem_secret) = ke1_message
+            .client_ek
+            .encapsulate(rng)
+            .map_err(|_| InternalPakeError::KemError)?;
+
+        let mut transcript_hasher = D::new()
+            .chain(STR_3DH_ML_KEM_768)
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
+            .chain(&serialized_credential_request[..])
+            .chain(serialize(&id_s, 2))
+            .chain(&l2_bytes[..])
+            .chain(&server_nonce[..])
+            .chain(server_e_kp.public().to_arr())
+            .chain(&kem_ciphertext[..]);
+
+        let derived_keys = derive_3dh_keys::<D, G>(
+            TripleDHComponents {
+                pk1: ke1_message.client_e_pk.clone(),
+                sk1: server_e_kp.private().clone(),
+                pk2: ke1_message.client_e_pk,
+                sk2: server_s_sk,
+                pk3: client_s_pk,
+                sk3: server_e_kp.private().clone(),
+            },
+            &kem_secret,
+            &transcript_hasher.clone().finalize(),
+        );
+        kem_secret.as_mut_slice().zeroize();
+        let (session_key, km2, ke2, km3) = derived_keys?;
+
+        // Compute encryption of e_info
+        let ciphertext = xor_with_encryption_pad::<D>(&ke2, &e_info)?;
+
+        transcript_hasher.update(serialize(&ciphertext, 2));
+
+        let mut mac_hasher =
+            Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
+        mac_hasher.update(&transcript_hasher.clone().finalize());
+        let mac = mac_hasher.finalize().into_bytes();
+
+        transcript_hasher.update(&mac);
+
+        Ok((
+            ke1_message.info,
+            Ke2State {
+                km3,
+                hashed_transcript: transcript_hasher.finalize(),
+                session_key,
+            },
+            Ke2Message {
+                server_nonce,
+                server_e_pk: server_e_kp.public().clone(),
+                kem_ciphertext,
+                e_info: ciphertext,
+                mac,
+            },
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn generate_ke3(
+        l2_component: Vec<u8>,
+        ke2_message: Self::KE2Message,
+        ke1_state: &Self::KE1State,
+        serialized_credential_request: &[u8],
+        server_s_pk: PublicKey<G>,
+        client_s_sk: PrivateKey<G>,
+        id_u: Vec<u8>,
+        id_s: Vec<u8>,
+        context: Vec<u8>,
+    ) -> Result<(Vec<u8>, Vec<u8>, Self::KE3Message), ProtocolError> {
+        let mut transcript_hasher = D::new()
+            .chain(STR_3DH_ML_KEM_768)
+            .chain(serialize_context(&context)?)
+            .chain(serialize(&id_u, 2))
+            .chain(serialized_credential_request)
+            .chain(serialize(&id_s, 2))
+            .chain(&l2_component[..])
+            .chain(ke2_message.to_bytes_without_info_or_mac());
+
+        // A tampered ciphertext decapsulates to an unrelated secret, which
+        // the server MAC check below then rejects
+        let mut kem_secret = ke1_state
+            .client_dk
+            .decapsulate(&ke2_message.kem_ciphertext)
+            .map_err(|_| InternalPakeError::KemError)?;
+
+        let derived_keys = derive_3dh_keys::<D, G>(
+            TripleDHComponents {
+                pk1: ke2_message.server_e_pk.clone(),
+                sk1: ke1_state.client_e_sk.clone(),
+                pk2: server_s_pk,
+                sk2: ke1_state.client_e_sk.clone(),
+                pk3: ke2_message.server_e_pk.clone(),
+                sk3: client_s_sk,
+            },
+            &kem_secret,
+            &transcript_hasher.clone().finalize(),
+        );
+        kem_secret.as_mut_slice().zeroize();
+        let (session_key, km2, ke2, km3) = derived_keys?;
+
+        transcript_hasher.update(serialize(&ke2_message.e_info[..], 2));
+
+        let mut server_mac =
+            Hmac::<D>::new_varkey(&km2).map_err(|_| InternalPakeError::HmacError)?;
+        server_mac.update(&transcript_hasher.clone().finalize());
+
+        // Mac::verify compares the tags in constant time
+        server_mac
+            .verify(&ke2_message.mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
+
+        transcript_hasher.update(&ke2_message.mac);
+
+        let mut client_mac =
+            Hmac::<D>::new_varkey(&km3).map_err(|_| InternalPakeError::HmacError)?;
+        client_mac.update(&transcript_hasher.finalize());
+
+        // Compute decryption of e_info
+        let plaintext = xor_with_encryption_pad::<D>(&ke2, &ke2_message.e_info)?;
+
+        Ok((
+            plaintext,
+            session_key.to_vec(),
+            Ke3Message {
+                mac: client_mac.finalize().into_bytes(),
+            },
+        ))
+    }
+
+    #[allow(clippy::type_complexity)]
+    fn finish_ke(
+        ke3_message: Self::KE3Message,
+        ke2_state: &Self::KE2State,
+    ) -> Result<Vec<u8>, ProtocolError> {
+        let mut client_mac =
+            Hmac::<D>::new_varkey(&ke2_state.km3).map_err(|_| InternalPakeError::HmacError)?;
+        client_mac.update(&ke2_state.hashed_transcript);
+
+        client_mac
+            .verify(&ke3_message.mac)
+            .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
+
+        Ok(ke2_state.session_key.to_vec())
+    }
+
+    fn ke2_message_size() -> usize {
+        NonceLen::to_usize()
+            + <G as Group>::ElemLen::to_usize()
+            + CiphertextLen::to_usize()
+            + <<D as FixedOutput>::OutputSize as Unsigned>::to_usize()
+    }
+}
+
+// Copies a slice of the expected length into a fixed-size ML-KEM array
+fn to_array<N: ml_kem_::ArraySize>(bytes: &[u8]) -> Result<Array<u8, N>, InternalPakeError> {
+    Array::try_from(bytes).map_err(|_| InternalPakeError::InvalidByteSequence)
+}
+
+/// The client state produced after the first key exchange message
+pub struct Ke1State<G: Group> {
+    client_e_sk: PrivateKey<G>,
+    client_nonce: GenericArray<u8, NonceLen>,
+    client_dk: DecapsulationKey,
+}
+
+/// The first key exchange message
+pub struct Ke1Message<G: Group> {
+    client_nonce: GenericArray<u8, NonceLen>,
+    info: Vec<u8>,
+    client_e_pk: PublicKey<G>,
+    client_ek: EncapsulationKey,
+}
+
+impl<G: Group> TryFrom<&[u8]> for Ke1State<G> {
+    type Error = PakeError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ScalarLen::to_usize();
+        let nonce_len = NonceLen::to_usize();
+        let checked_bytes = check_slice_size(
+            bytes,
+            key_len + nonce_len + DecapsulationKeyLen::to_usize(),
+            "ke1_state",
+        )?;
+
+        let mut client_dk_bytes = to_array(&checked_bytes[key_len + nonce_len..])?;
+        let client_dk = DecapsulationKey::from_bytes(&client_dk_bytes);
+        client_dk_bytes.as_mut_slice().zeroize();
+
+        Ok(Self {
+            client_e_sk: PrivateKey::from_bytes(&checked_bytes[..key_len])?,
+            client_nonce: GenericArray::clone_from_slice(
+                &checked_bytes[key_len..key_len + nonce_len],
+            ),
+            client_dk,
+        })
+    }
+}
+
+// The decapsulation key is zeroized on drop, but its encodings are copies
+// which are zeroized by hand
+impl<G: Group> ToBytes for Ke1State<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut client_dk_bytes = self.client_dk.as_bytes();
+        let bytes = [
+            &self.client_e_sk.to_arr()[..],
+            &self.client_nonce[..],
+            &client_dk_bytes[..],
+        ]
+        .concat();
+        client_dk_bytes.as_mut_slice().zeroize();
+        bytes
+    }
+}
+
+impl<G: Group> ToBytes for Ke1Message<G> {
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.client_nonce[..],
+            &serialize(&self.info, 2),
+            &self.client_e_pk.to_arr(),
+            &self.client_ek.as_bytes()[..],
+        ]
+        .concat()
+    }
+}
+
+impl<G: Group> TryFrom<&[u8]> for Ke1Message<G> {
+    type Error = PakeError;
+
+    fn try_from(ke1_message_bytes: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ElemLen::to_usize();
+        let nonce_len = NonceLen::to_usize();
+        let checked_nonce =
+            check_slice_size_atleast(ke1_message_bytes, nonce_len, "ke1_message nonce")?;
+
+        let (info, remainder) = tokenize(&checked_nonce[nonce_len..], 2)?;
+
+        let checked_keys = check_slice_size(
+            &remainder,
+            key_len + EncapsulationKeyLen::to_usize(),
+            "ke1_message client_e_pk and client_ek",
+        )?;
+
+        Ok(Self {
+            client_nonce: GenericArray::clone_from_slice(&checked_nonce[..nonce_len]),
+            info,
+            client_e_pk: PublicKey::from_bytes(&checked_keys[..key_len])?,
+            client_ek: EncapsulationKey::from_bytes(&to_array(&checked_keys[key_len..])?),
+        })
+    }
+}
+
+/// The second key exchange message
+pub struct Ke2Message<G: Group, HashLen: ArrayLength<u8>> {
+    server_nonce: GenericArray<u8, NonceLen>,
+    server_e_pk: PublicKey<G>,
+    kem_ciphertext: Ciphertext<MlKem768>,
+    e_info: Vec<u8>,
+    mac: GenericArray<u8, HashLen>,
+}
+
+impl<G: Group, HashLen: ArrayLength<u8>> ToBytes for Ke2Message<G, HashLen> {
+    fn to_bytes(&self) -> Vec<u8> {
+        [
+            &self.to_bytes_without_info_or_mac(),
+            &serialize(&self.e_info, 2),
+            &self.mac[..],
+        ]
+        .concat()
+    }
+}
+
+impl<G: Group, HashLen: ArrayLength<u8>> Ke2Message<G, HashLen> {
+    fn to_bytes_without_info_or_mac(&self) -> Vec<u8> {
+        [
+            &self.server_nonce[..],
+            &self.server_e_pk.to_arr(),
+            &self.kem_ciphertext[..],
+        ]
+        .concat()
+    }
+}
+
+impl<G: Group, HashLen: ArrayLength<u8>> TryFrom<&[u8]> for Ke2Message<G, HashLen> {
+    type Error = PakeError;
+
+    fn try_from(input: &[u8]) -> Result<Self, Self::Error> {
+        let key_len = <G as Group>::ElemLen::to_usize();
+        let nonce_len = NonceLen::to_usize();
+        let ciphertext_len = CiphertextLen::to_usize();
+        let checked_nonce = check_slice_size_atleast(input, nonce_len, "ke2_message nonce")?;
+        let checked_keys = check_slice_size_atleast(
+            &checked_nonce[nonce_len..],
+            key_len + ciphertext_len,
+            "ke2_message server_e_pk and kem_ciphertext",
+        )?;
+        let (e_info, remainder) = tokenize(&checked_keys[key_len + ciphertext_len..], 2)?;
+        let checked_mac = check_slice_size(&remainder, HashLen::to_usize(), "ke2_message mac")?;
+
+        Ok(Self {
+            server_nonce: GenericArray::clone_from_slice(&checked_nonce[..nonce_len]),
+            server_e_pk: PublicKey::from_bytes(&checked_keys[..key_len])?,
+            kem_ciphertext: to_array(&checked_keys[key_len..key_len + ciphertext_len])?,
+            e_info,
+            mac: GenericArray::clone_from_slice(checked_mac),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use curve25519_dalek::ristretto::RistrettoPoint;
+    use rand::rngs::OsRng;
+
+    #[test]
+    fn ke1_round_trip() -> Result<(), ProtocolError> {
+        let mut rng = OsRng;
+        let (ke1_state, ke1_message) = <TripleDHMlKem768 as KeyExchange<
+            sha2::Sha512,
+            RistrettoPoint,
+        >>::generate_ke1(b"info".to_vec(), &mut rng)?;
+
+        let ke1_state_bytes = ke1_state.to_bytes();
+        assert_eq!(
+            ke1_state_bytes,
+            Ke1State::<RistrettoPoint>::try_from(&ke1_state_bytes[..])?.to_bytes()
+        );
+
+        let ke1_message_bytes = ke1_message.to_bytes();
+        let parsed = Ke1Message::<RistrettoPoint>::try_from(&ke1_message_bytes[..])?;
+        assert_eq!(ke1_message_bytes, parsed.to_bytes());
+        assert_eq!(b"info".to_vec(), parsed.info);
+
+        // The encapsulation key is carried in full
+        assert!(Ke1Message::<RistrettoPoint>::try_from(
+            &ke1_message_bytes[..ke1_message_bytes.len() - 1]
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn tampered_kem_ciphertext() -> Result<(), ProtocolError> {
+        type KE = TripleDHMlKem768;
+        let mut rng = OsRng;
+        let client_s_kp = KeyPair::<RistrettoPoint>::generate_random(&mut rng);
+        let server_s_kp = KeyPair::<RistrettoPoint>::generate_random(&mut rng);
+        let (ke1_state, ke1_message) =
+            <KE as KeyExchange<sha2::Sha512, RistrettoPoint>>::generate_ke1(Vec::new(), &mut rng)?;
+        let ke1_message_bytes = ke1_message.to_bytes();
+        let (_, _, ke2_message) = <KE as KeyExchange<sha2::Sha512, RistrettoPoint>>::generate_ke2(
+            &mut rng,
+            ke1_message_bytes.clone(),
+            Vec::new(),
+            ke1_message,
+            client_s_kp.public().clone(),
+            server_s_kp.private().clone(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+            Vec::new(),
+        )?;
+        let ke2_message_bytes = ke2_message.to_bytes();
+
+        let generate_ke3 = |ke2_message_bytes: &[u8]| {
+            <KE as KeyExchange<sha2::Sha512, RistrettoPoint>>::generate_ke3(
+                Vec::new(),
+                Ke2Message::try_from(ke2_message_bytes)?,
+                &ke1_state,
+                &ke1_message_bytes,
+                server_s_kp.public().clone(),
+                client_s_kp.private().clone(),
+                Vec::new(),
+                Vec::new(),
+                Vec::new(),
+            )
+        };
+        generate_ke3(&ke2_message_bytes)?;
+
+        // The ciphertext follows the server nonce and ephemeral public key
+        let mut tampered_bytes = ke2_message_bytes.clone();
+        tampered_bytes[NonceLen::to_usize() + <RistrettoPoint as Group>::ElemLen::to_usize()] ^= 1;
+        assert!(matches!(
+            generate_ke3(&tampered_bytes),
+            Err(ProtocolError::VerificationError(
+                PakeError::KeyExchangeMacValidationError
+            ))
+        ));
+        Ok(())
+    }
+}
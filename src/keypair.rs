@@ -7,7 +7,7 @@
 
 use crate::errors::InternalPakeError;
 use crate::group::Group;
-use generic_array::{typenum::U32, GenericArray};
+use generic_array::GenericArray;
 use generic_bytes::{SizedBytes, TryFromSizedBytesError};
 #[cfg(test)]
 use proptest::prelude::*;
@@ -15,7 +15,6 @@ use proptest::prelude::*;
 use rand::{rngs::StdRng, SeedableRng};
 use rand::{CryptoRng, RngCore};
 use std::fmt::Debug;
-use std::marker::PhantomData;
 use std::ops::Deref;
 
 /// Convenience extension trait of SizedBytes
@@ -31,30 +30,28 @@ impl<T> SizedBytesExt for T where T: SizedBytes {}
 
 /// A Keypair trait with public-private verification
 #[derive(Clone, Debug, PartialEq, Eq)]
-pub struct KeyPair<G> {
-    pk: Key,
-    sk: Key,
-    _g: PhantomData<G>,
+pub struct KeyPair<G: Group> {
+    pk: PublicKey<G>,
+    sk: PrivateKey<G>,
 }
 
 impl<G: Group> KeyPair<G> {
     /// The public key component
-    pub fn public(&self) -> &Key {
+    pub fn public(&self) -> &PublicKey<G> {
         &self.pk
     }
 
     /// The private key component
-    pub fn private(&self) -> &Key {
+    pub fn private(&self) -> &PrivateKey<G> {
         &self.sk
     }
 
     /// A constructor that receives public and private key independently as
     /// bytes
-    pub fn new(public: Key, private: Key) -> Result<Self, InternalPakeError> {
+    pub fn new(public: PublicKey<G>, private: PrivateKey<G>) -> Result<Self, InternalPakeError> {
         Ok(Self {
             pk: public,
             sk: private,
-            _g: PhantomData,
         })
     }
 
@@ -64,38 +61,37 @@ impl<G: Group> KeyPair<G> {
         let sk_bytes = G::scalar_as_bytes(&sk);
         let pk = G::base_point().mult_by_slice(&sk_bytes);
         Self {
-            pk: Key(pk.to_arr().to_vec()),
-            sk: Key(sk_bytes.to_vec()),
-            _g: PhantomData,
+            pk: PublicKey(pk.to_arr()),
+            sk: PrivateKey(sk_bytes),
         }
     }
 
     /// Obtaining a public key from secret bytes. At all times, we should have
     /// &public_from_private(self.private()) == self.public()
-    pub(crate) fn public_from_private(bytes: &Key) -> Key {
-        let bytes_data = GenericArray::<u8, G::ScalarLen>::from_slice(&bytes.0[..]);
-        Key(G::base_point().mult_by_slice(&bytes_data).to_arr().to_vec())
+    pub(crate) fn public_from_private(bytes: &PrivateKey<G>) -> PublicKey<G> {
+        PublicKey(G::base_point().mult_by_slice(&bytes.0).to_arr())
     }
 
     /// Check whether a public key is valid. This is meant to be applied on
     /// material provided through the network which fits the key
     /// representation (i.e. can be mapped to a curve point), but presents
     /// some risk - e.g. small subgroup check
-    pub(crate) fn check_public_key(key: Key) -> Result<Key, InternalPakeError> {
-        G::from_element_slice(GenericArray::from_slice(&key.0)).map(|_| key)
+    pub(crate) fn check_public_key(key: PublicKey<G>) -> Result<PublicKey<G>, InternalPakeError> {
+        G::from_element_slice(&key.0).map(|_| key)
     }
 
     /// Computes the diffie hellman function on a public key and private key
-    pub(crate) fn diffie_hellman(pk: Key, sk: Key) -> Result<Vec<u8>, InternalPakeError> {
-        let pk_data = GenericArray::<u8, G::ElemLen>::from_slice(&pk.0[..]);
-        let point = G::from_element_slice(&pk_data)?;
-        let secret_data = GenericArray::<u8, G::ScalarLen>::from_slice(&sk.0[..]);
-        Ok(G::mult_by_slice(&point, &secret_data).to_arr().to_vec())
+    pub(crate) fn diffie_hellman(
+        pk: PublicKey<G>,
+        sk: PrivateKey<G>,
+    ) -> Result<Vec<u8>, InternalPakeError> {
+        let point = G::from_element_slice(&pk.0)?;
+        Ok(G::mult_by_slice(&point, &sk.0).to_arr().to_vec())
     }
 
     /// Obtains a KeyPair from a slice representing the private key
     pub fn from_private_key_slice(input: &[u8]) -> Result<Self, InternalPakeError> {
-        let sk = Key::from_arr(GenericArray::from_slice(&input))?;
+        let sk = PrivateKey::from_arr(GenericArray::from_slice(input))?;
         let pk = Self::public_from_private(&sk);
         Self::new(pk, sk)
     }
@@ -118,31 +114,65 @@ impl<G: Group + Debug> KeyPair<G> {
     }
 }
 
-/// A minimalist key type built around a \[u8; 32\]
-#[derive(Debug, PartialEq, Eq, Clone)]
+/// A public key, represented by the serialization of a group element
 #[repr(transparent)]
-pub struct Key(Vec<u8>);
+pub struct PublicKey<G: Group>(GenericArray<u8, G::ElemLen>);
 
-impl Deref for Key {
-    type Target = Vec<u8>;
+/// A private key, represented by the serialization of a scalar
+#[repr(transparent)]
+pub struct PrivateKey<G: Group>(GenericArray<u8, G::ScalarLen>);
+
+// These can't be derived without requiring the corresponding bounds on the
+// group itself
+macro_rules! impl_key_traits {
+    ($key:ident, $len:ident) => {
+        impl<G: Group> Clone for $key<G> {
+            fn clone(&self) -> Self {
+                Self(self.0.clone())
+            }
+        }
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
+        impl<G: Group> Debug for $key<G> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                f.debug_tuple(stringify!($key)).field(&self.0).finish()
+            }
+        }
 
-impl SizedBytes for Key {
-    type Len = U32;
+        impl<G: Group> PartialEq for $key<G> {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
 
-    fn to_arr(&self) -> GenericArray<u8, Self::Len> {
-        GenericArray::clone_from_slice(&self.0[..])
-    }
+        impl<G: Group> Eq for $key<G> {}
 
-    fn from_arr(key_bytes: &GenericArray<u8, Self::Len>) -> Result<Self, TryFromSizedBytesError> {
-        Ok(Key(key_bytes.to_vec()))
-    }
+        impl<G: Group> Deref for $key<G> {
+            type Target = GenericArray<u8, G::$len>;
+
+            fn deref(&self) -> &Self::Target {
+                &self.0
+            }
+        }
+
+        impl<G: Group> SizedBytes for $key<G> {
+            type Len = G::$len;
+
+            fn to_arr(&self) -> GenericArray<u8, Self::Len> {
+                self.0.clone()
+            }
+
+            fn from_arr(
+                key_bytes: &GenericArray<u8, Self::Len>,
+            ) -> Result<Self, TryFromSizedBytesError> {
+                Ok(Self(key_bytes.clone()))
+            }
+        }
+    };
 }
 
+impl_key_traits!(PublicKey, ElemLen);
+impl_key_traits!(PrivateKey, ScalarLen);
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -28,15 +28,61 @@
 //!     type SlowHash = opaque_ke::slow_hash::NoOpHash;
 //! }
 //! ```
+//! The key exchange protocol can also be set to `opaque_ke::key_exchange::sigma::SigmaI`, in which each party authenticates
+//! by signing the transcript under its static key, rather than by a Diffie-Hellman computation with it, and the client's
+//! signature is sent encrypted. It is parameterized by a signature scheme: `SigmaI<Schnorr>` signs with Schnorr signatures
+//! over Ristretto255 and fits the ciphersuite above, while `SigmaI<Ecdsa>` signs with deterministic ECDSA over P-256 or P-384
+//! (in both cases, the static keys are signing keys, which should not be shared with the other key exchanges), or to
+//! `opaque_ke::key_exchange::hmqv::HMQV`, which needs fewer group operations than TripleDH and sends a smaller
+//! second message. For protection of the session keys against a future quantum adversary, the `ml-kem` feature
+//! enables `opaque_ke::key_exchange::tripledh_mlkem::TripleDHMlKem768`, which runs TripleDH together with an ML-KEM-768
+//! key encapsulation and derives the session key from both shared secrets.
+//!
 //! See [examples/simple_login.rs](https://github.com/novifinancial/opaque-ke/blob/master/examples/simple_login.rs)
 //! for a working example of a simple password-based login using OPAQUE.
 //!
 //! Note that our choice of slow hashing function in this example, `NoOpHash`, is selected only to ensure
-//! that the tests execute quickly. A real application should use an actual slow hashing function, such as `scrypt`,
-//! which can be enabled through the `slow-hash` feature.
+//! that the tests execute quickly. A real application should use an actual slow hashing function, such as scrypt,
+//! which can be enabled through the `slow-hash` feature and used by setting `type SlowHash = opaque_ke::slow_hash::Scrypt`,
+//! or Argon2id, which can be enabled through the `argon2` feature and used by setting
+//! `type SlowHash = opaque_ke::slow_hash::Argon2id`. For clients which cannot afford a memory-hard function, PBKDF2
+//! can be enabled through the `pbkdf2` feature and used by setting `type SlowHash = opaque_ke::slow_hash::Pbkdf2`.
+//! The cost parameters of the slow hashing function default to the recommended ones, and can be chosen at runtime by
+//! passing an instance through `ClientRegistrationFinishParameters::with_slow_hash`. Parameters chosen this way are stored
+//! with the envelope and returned to the client at login, so that a client on a new device can recover them. Since the
+//! stored parameters are only authenticated once the slow hashing function has run on them, the client first checks
+//! them against a maximum, which is `SlowHash::maximum` unless an instance is passed through
+//! `ClientLoginFinishParameters::with_maximum_slow_hash`, and the login fails with `ExpensiveSlowHashParametersError`
+//! otherwise. The stored parameters must also meet a minimum, and the login fails with `WeakSlowHashParametersError`
+//! otherwise, so that a server cannot downgrade a client to cheap parameters. The minimum is `SlowHash::recommended`
+//! unless an instance is passed through `ClientLoginFinishParameters::with_minimum_slow_hash` (or, failing that,
+//! `ClientLoginFinishParameters::with_slow_hash`). Deployments in which users register from mobile clients with
+//! cheaper cost parameters must relax the minimum explicitly for those users to log in from other clients.
+//!
+//! The server stores the password files of all users, and returns a fake one for users who have not registered, so
+//! that the responses do not reveal whether a user is registered. If clients store their slow hashing parameters,
+//! the server should set those parameters on its `OprfSeed` with `OprfSeed::set_fake_slow_hash`, so that the fake
+//! password files carry them as well.
+//!
+//! The example above uses the Ristretto255 group. Implementations of the NIST P-256, P-384 and P-521 curves,
+//! with points represented in their SEC1 compressed form, can be enabled through the `p256`, `p384` and `p521`
+//! features respectively. These are used by setting, for instance, `type Group = p384::ProjectivePoint` along
+//! with the matching hash function, `type Hash = sha2::Sha384`. Similarly, the decaf448 group can be enabled
+//! through the `decaf448` feature and used by setting `type Group = ed448_goldilocks_plus::DecafPoint` along
+//! with `type Hash = sha2::Sha512`. Note that this is not a standard ciphersuite: RFC 9497 pairs decaf448 with
+//! SHAKE256, whereas this crate hashes to decaf448 with `expand_message_xmd`, so its outputs do not interoperate
+//! with other implementations.
+//!
+//! By default, the client's static private key is generated at random during registration and encrypted in the
+//! envelope. A ciphersuite can instead set `const INNER_ENVELOPE_MODE: InnerEnvelopeMode = InnerEnvelopeMode::Internal`,
+//! in which case the private key is derived from the envelope nonce and the password-derived key, so that the envelope
+//! encrypts nothing and is shorter by the length of a scalar. Clients which already hold a long-term key pair can use
+//! `InnerEnvelopeMode::External` instead, where the key pair is passed through
+//! `ClientRegistrationFinishParameters::with_key_pair`, its private key is encrypted in the envelope with ChaCha20-Poly1305,
+//! and the key pair is returned in `ClientLoginFinishResult::client_static_keypair` at login.
 //!
 //! ## Setup
-//! To set up the protocol, the server begins by generating a static keypair:
+//! To set up the protocol, the server begins by generating a static keypair, along with an [OprfSeed]:
 //! ```
 //! # use opaque_ke::errors::ProtocolError;
 //! # use opaque_ke::ciphersuite::CipherSuite;
@@ -47,13 +93,17 @@
 //! #     type Hash = sha2::Sha512;
 //! #     type SlowHash = opaque_ke::slow_hash::NoOpHash;
 //! # }
+//! use opaque_ke::OprfSeed;
 //! use rand::{rngs::OsRng, RngCore};
 //! let mut rng = OsRng;
 //! let server_kp = Default::generate_random_keypair(&mut rng);
+//! let oprf_seed = OprfSeed::<Default>::generate_random(&mut rng);
 //! # Ok::<(), ProtocolError>(())
 //! ```
 //! The server must persist this keypair for the registration and login steps, where the public component will be
 //! used by the client during both registration and login, and the private component will be used by the server during login.
+//! The server must also persist the seed, which is kept secret: the OPRF key of each client is derived from it and from a
+//! `credential_identifier` which uniquely identifies the client on the server, such as a username or a database row ID.
 //!
 //! ## Registration
 //! The registration protocol between the client and server consists of four steps along with three messages:
@@ -90,8 +140,8 @@
 //! ```
 //!
 //! ### Server Registration Start
-//! In the second step of registration, the server takes as input the instance of [RegistrationRequest] from the client, and
-//! the server's public key `server_kp.public()`.
+//! In the second step of registration, the server takes as input the instance of [RegistrationRequest] from the client,
+//! the server's public key `server_kp.public()`, the [OprfSeed], and the client's `credential_identifier`.
 //! The server runs [ServerRegistration::start] to produce an a [ServerRegistrationStartResult], which consists of
 //! a [RegistrationResponse] to be returned to the client and
 //! a [ServerRegistration] which must be persisted on the server for the final step of server registration.
@@ -118,10 +168,12 @@
 //! use opaque_ke::ServerRegistration;
 //! let mut server_rng = OsRng;
 //! let server_kp = Default::generate_random_keypair(&mut server_rng);
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
 //! let server_registration_start_result = ServerRegistration::<Default>::start(
-//!     &mut server_rng,
+//!     &oprf_seed,
 //!     client_registration_start_result.message,
 //!     server_kp.public(),
+//!     b"username",
 //! )?;
 //! # Ok::<(), ProtocolError>(())
 //! ```
@@ -154,7 +206,8 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //! let client_registration_finish_result = client_registration_start_result.state.finish(
 //!     &mut client_rng,
 //!     server_registration_start_result.message,
@@ -192,7 +245,8 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
 //! let password_file = server_registration_start_result.state.finish(
 //!     client_registration_finish_result.message,
@@ -239,8 +293,9 @@
 //! ### Server Login Start
 //! In the second step of login, the server takes as input
 //! a [CredentialRequest] from the client,
-//! the server's private key `server_kp.private()`, and
-//! the password file output from registration.
+//! the server's private key `server_kp.private()`,
+//! the password file output from registration, and
+//! the [OprfSeed] along with the `credential_identifier` used during registration.
 //! The server runs [ServerLogin::start] to produce an output consisting of
 //! a [CredentialResponse] which is returned to the client, and
 //! a [ServerLogin] which must be persisted on the server for the final step of login.
@@ -266,7 +321,8 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
 //! # let password_file_bytes = server_registration_start_result.state.finish(client_registration_finish_result.message)?.serialize();
 //! # let client_login_start_result = ClientLogin::<Default>::start(
@@ -279,14 +335,22 @@
 //! let mut server_rng = OsRng;
 //! let server_login_start_result = ServerLogin::start(
 //!     &mut server_rng,
-//!     password_file,
+//!     Some(password_file),
 //!     &server_kp.private(),
+//!     &oprf_seed,
 //!     client_login_start_result.message,
+//!     b"username",
 //!     ServerLoginStartParameters::default(),
 //! )?;
 //! # Ok::<(), ProtocolError>(())
 //! ```
 //!
+//! If no password file is registered under the `credential_identifier`, the server should still run
+//! [ServerLogin::start], passing `None` as the password file. The server then responds with a [CredentialResponse]
+//! built from a fake password file derived from the [OprfSeed], which the client cannot distinguish from a real one.
+//! The login fails just as it would with an incorrect password, so that the server does not reveal which
+//! credential identifiers are registered.
+//!
 //! ### Client Login Finish
 //! In the third step of login, the client takes as input a [CredentialResponse] from the server.
 //! The client runs [ClientLogin::finish] and produces an output consisting of
@@ -314,7 +378,8 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
 //! # let password_file_bytes = server_registration_start_result.state.finish(client_registration_finish_result.message)?.serialize();
 //! # let client_login_start_result = ClientLogin::<Default>::start(
@@ -327,7 +392,7 @@
 //! #     &password_file_bytes[..],
 //! #   )?;
 //! # let server_login_start_result =
-//! #     ServerLogin::start(&mut server_rng, password_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::default())?;
+//! #     ServerLogin::start(&mut server_rng, Some(password_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
 //! let client_login_finish_result = client_login_start_result.state.finish(
 //!     server_login_start_result.message,
 //!     ClientLoginFinishParameters::default(),
@@ -360,7 +425,8 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
 //! # let password_file_bytes = server_registration_start_result.state.finish(client_registration_finish_result.message)?.serialize();
 //! # let client_login_start_result = ClientLogin::<Default>::start(
@@ -373,7 +439,7 @@
 //! #     &password_file_bytes[..],
 //! #   )?;
 //! # let server_login_start_result =
-//! #     ServerLogin::start(&mut server_rng, password_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::default())?;
+//! #     ServerLogin::start(&mut server_rng, Some(password_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
 //! # let client_login_finish_result = client_login_start_result.state.finish(
 //! #   server_login_start_result.message,
 //! #   ClientLoginFinishParameters::default(),
@@ -436,7 +502,8 @@
 //! # let mut server_rng = OsRng;
 //! // During setup, server generates its static keypair
 //! let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //!
 //! // During setup or registration, the server transmits its static public key to the client
 //! let server_s_pk = server_kp.public(); // obtained from the server
@@ -452,7 +519,7 @@
 //! #     &password_file_bytes[..],
 //! #   )?;
 //! # let server_login_start_result =
-//! #     ServerLogin::start(&mut server_rng, password_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::default())?;
+//! #     ServerLogin::start(&mut server_rng, Some(password_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
 //!
 //! // And then later, during login...
 //! let client_login_finish_result = client_login_start_result.state.finish(
@@ -511,7 +578,8 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //! // During registration...
 //! let client_registration_finish_result = client_registration_start_result.state.finish(
 //!     &mut client_rng,
@@ -529,7 +597,7 @@
 //! #     &password_file_bytes[..],
 //! #   )?;
 //! # let server_login_start_result =
-//! #     ServerLogin::start(&mut server_rng, password_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::default())?;
+//! #     ServerLogin::start(&mut server_rng, Some(password_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
 //!
 //! // And then later, during login...
 //! let client_login_finish_result = client_login_start_result.state.finish(
@@ -552,7 +620,7 @@
 //!
 //! But, for applications that wish to cryptographically bind these identities to
 //! the registered password file as well as the session key output by the login phase, these custom identifiers can be specified through
-//! [ClientRegistrationFinishParameters::WithIdentifiers] in [Client Registration Finish](#client-registration-finish):
+//! [ClientRegistrationFinishParameters::with_identifiers] in [Client Registration Finish](#client-registration-finish):
 //! ```
 //! # use opaque_ke::{
 //! #   errors::ProtocolError,
@@ -575,11 +643,12 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
 //! let client_registration_finish_result = client_registration_start_result.state.finish(
 //!     &mut client_rng,
 //!     server_registration_start_result.message,
-//!     ClientRegistrationFinishParameters::WithIdentifiers(
+//!     ClientRegistrationFinishParameters::default().with_identifiers(
 //!         b"username".to_vec(),
 //!         b"facebook.com".to_vec(),
 //!     ),
@@ -587,7 +656,7 @@
 //! # Ok::<(), ProtocolError>(())
 //! ```
 //!
-//! The same identifiers must also be supplied using [ServerLoginStartParameters::WithIdentifiers] in [Server Login Start](#server-login-start):
+//! The same identifiers must also be supplied using [ServerLoginStartParameters::with_identifiers] in [Server Login Start](#server-login-start):
 //! ```
 //! # use opaque_ke::{
 //! #   errors::ProtocolError,
@@ -610,8 +679,9 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
-//! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::WithIdentifiers(b"username".to_vec(), b"facebook.com".to_vec()))?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
+//! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default().with_identifiers(b"username".to_vec(), b"facebook.com".to_vec()))?;
 //! # let password_file_bytes = server_registration_start_result.state.finish(client_registration_finish_result.message)?.serialize();
 //! # let client_login_start_result = ClientLogin::<Default>::start(
 //! #   &mut client_rng,
@@ -623,18 +693,18 @@
 //! # let mut server_rng = OsRng;
 //! let server_login_start_result = ServerLogin::start(
 //!     &mut server_rng,
-//!     password_file,
+//!     Some(password_file),
 //!     &server_kp.private(),
+//!     &oprf_seed,
 //!     client_login_start_result.message,
-//!     ServerLoginStartParameters::WithIdentifiers(
-//!         b"username".to_vec(),
-//!         b"facebook.com".to_vec(),
-//!     ),
+//!     b"username",
+//!     ServerLoginStartParameters::default()
+//!         .with_identifiers(b"username".to_vec(), b"facebook.com".to_vec()),
 //! )?;
 //! # Ok::<(), ProtocolError>(())
 //! ```
 //!
-//! as well as [ClientLoginFinishParameters::WithIdentifiers] in [Client Login Finish](#client-login-finish):
+//! as well as [ClientLoginFinishParameters::with_identifiers] in [Client Login Finish](#client-login-finish):
 //! ```
 //! # use opaque_ke::{
 //! #   errors::ProtocolError,
@@ -657,8 +727,9 @@
 //! # )?;
 //! # let mut server_rng = OsRng;
 //! # let server_kp = Default::generate_random_keypair(&mut server_rng);
-//! # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
-//! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::WithIdentifiers(b"username".to_vec(), b"facebook.com".to_vec()))?;
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
+//! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default().with_identifiers(b"username".to_vec(), b"facebook.com".to_vec()))?;
 //! # let password_file_bytes = server_registration_start_result.state.finish(client_registration_finish_result.message)?.serialize();
 //! # let client_login_start_result = ClientLogin::<Default>::start(
 //! #     &mut client_rng,
@@ -670,10 +741,10 @@
 //! #     &password_file_bytes[..],
 //! #   )?;
 //! # let server_login_start_result =
-//! #     ServerLogin::start(&mut server_rng, password_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::WithIdentifiers(b"username".to_vec(), b"facebook.com".to_vec()))?;
+//! #     ServerLogin::start(&mut server_rng, Some(password_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default().with_identifiers(b"username".to_vec(), b"facebook.com".to_vec()))?;
 //! let client_login_finish_result = client_login_start_result.state.finish(
 //!     server_login_start_result.message,
-//!     ClientLoginFinishParameters::WithIdentifiers(
+//!     ClientLoginFinishParameters::default().with_identifiers(
 //!         b"username".to_vec(),
 //!         b"facebook.com".to_vec(),
 //!     ),
@@ -690,13 +761,163 @@
 //! additional data alongside the first two messages of the protocol, with confidential data being supported for the second message.
 //!
 //! The following three messages support passing of additional data:
-//! - The first login message, where the client can populate [ClientLoginStartParameters::WithInfo] with plaintext additional data, and
-//! the server can retrieve using the `plain_info` field of [ServerLoginStartResult].
-//! - The second login message, where the server can populate [ServerLoginStartParameters::WithInfo] with confidential additional data,
-//! and the client can retrieve using the `confidential_info` field of [ClientLoginFinishResult].
+//! - The first login message, where the client can populate [ClientLoginStartParameters::with_info] with plaintext additional data, and
+//!   the server can retrieve using the `plain_info` field of [ServerLoginStartResult].
+//! - The second login message, where the server can populate [ServerLoginStartParameters::with_info] with confidential additional data,
+//!   and the client can retrieve using the `confidential_info` field of [ClientLoginFinishResult].
+//!
+//! For the second login message, [ServerLoginStartParameters::with_info] can be combined with
+//! [ServerLoginStartParameters::with_identifiers] to specify these fields in addition to [custom identifiers](#custom-identifiers).
+//!
+//! ## Login Context
+//!
+//! A login can also be bound to a context which both parties know but which is not sent, such as a TLS exporter value, a digest of
+//! the enclosing HTTP request, or an application name. The client supplies it through [ClientLoginStartParameters::with_context],
+//! and the server through [ServerLoginStartParameters::with_context], alongside any info or identifiers. The context is
+//! at most [MAX_CONTEXT_LEN] bytes long, and a longer context is rejected with a [ContextTooLongError](errors::InternalPakeError::ContextTooLongError). It is
+//! absorbed into the key exchange transcript, so that a login in which the two parties supplied different contexts fails with a MAC
+//! validation error. The context is always absorbed together with its length, so a login without a context is bound to the
+//! empty context.
+//!
+//! ## Envelope Secrets
+//!
+//! As an alternative to encrypting client-side secrets with the [export key](#export-key), a secret of up to [MAX_SECRET_LEN] bytes,
+//! such as a recovery key or a TOTP seed, can be stored in the envelope itself by passing it through
+//! [ClientRegistrationFinishParameters::with_secret], which can be combined with the other parameters, such as
+//! [custom identifiers](#custom-identifiers).
+//! The secret is encrypted along with the client's private key, and authenticated along with the server's static public key and the
+//! identifiers, and is returned in the `secret` field of [ClientLoginFinishResult]. Note that an envelope which holds a secret is longer
+//! than one which does not, and its length reveals that of the secret. Applications which store secrets should therefore give them the
+//! same length for every user, padding them if need be, and the server should set that length on its `OprfSeed` with
+//! [OprfSeed::set_fake_secret_len], so that the envelopes which it returns for unregistered users hold a secret of the same length.
+//!
+//! ## Password Change
+//!
+//! A client which knows its current password can change it without a separate login, by combining a login under the old password
+//! with a registration under the new one in a single session. [ClientPasswordChange::start] produces a [PasswordChangeRequest], which
+//! the server answers with [ServerPasswordChange::start] using the user's current password file. The key exchange of the login also
+//! authenticates the server's registration response, so that the client only seals its new envelope in [ClientPasswordChange::finish]
+//! once it has verified that response, and it then binds the new registration to the session key with a MAC. The server
+//! only obtains the new password file from [ServerPasswordChange::finish] once the login and this MAC have been verified, and should
+//! replace the user's password file with it then:
+//! ```
+//! # use opaque_ke::{
+//! #   errors::ProtocolError,
+//! #   ClientRegistration, ClientRegistrationFinishParameters, ServerRegistration, ClientLoginStartParameters, ClientLoginFinishParameters, ServerLoginStartParameters,
+//! #   ClientPasswordChange, ServerPasswordChange,
+//! #   slow_hash::NoOpHash,
+//! # };
+//! # use opaque_ke::ciphersuite::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type KeyExchange = opaque_ke::key_exchange::tripledh::TripleDH;
+//! #     type Hash = sha2::Sha512;
+//! #     type SlowHash = opaque_ke::slow_hash::NoOpHash;
+//! # }
+//! # use rand::{rngs::OsRng, RngCore};
+//! # let mut client_rng = OsRng;
+//! # let client_registration_start_result = ClientRegistration::<Default>::start(
+//! #     &mut client_rng,
+//! #     b"password",
+//! # )?;
+//! # let mut server_rng = OsRng;
+//! # let server_kp = Default::generate_random_keypair(&mut server_rng);
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
+//! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
+//! # let password_file = server_registration_start_result.state.finish(client_registration_finish_result.message)?;
+//! let client_start_result = ClientPasswordChange::<Default>::start(
+//!     &mut client_rng,
+//!     b"password",
+//!     b"new password",
+//!     ClientLoginStartParameters::default(),
+//! )?;
+//! let server_start_result = ServerPasswordChange::start(
+//!     &mut server_rng,
+//!     Some(password_file),
+//!     &server_kp.private(),
+//!     &oprf_seed,
+//!     client_start_result.message,
+//!     b"username",
+//!     ServerLoginStartParameters::default(),
+//! )?;
+//! let client_finish_result = client_start_result.state.finish(
+//!     &mut client_rng,
+//!     server_start_result.message,
+//!     ClientLoginFinishParameters::default(),
+//!     ClientRegistrationFinishParameters::default(),
+//! )?;
+//! let server_finish_result = server_start_result.state.finish(client_finish_result.message)?;
+//! let new_password_file_bytes = server_finish_result.password_file.serialize();
+//! # Ok::<(), ProtocolError>(())
+//! ```
 //!
-//! For the second login message, the `WithInfoAndIdentifiers` variant can be used to specify these fields in addition to
-//! [custom identifiers](#custom-identifiers), with the ordering of the fields as `WithInfoAndIdentifiers(confidential_info, username, server_name)`.
+//! ## Server Key Rotation
+//!
+//! Since the envelope of each user authenticates the server's static public key, the server cannot simply replace its key pair
+//! without locking out every registered user. Instead, the server can keep its key pairs in a [ServerKeySet], each under a key ID,
+//! and use [ServerRegistration::start_with_key_set] and [ServerLogin::start_with_key_set] in place of [ServerRegistration::start]
+//! and [ServerLogin::start]. Password files then record the ID of the key pair they were sealed under (see
+//! [ServerRegistration::key_id]), and logins use that key pair, even after [ServerKeySet::rotate] has made another one current.
+//! Password files without a key ID are looked up under [LEGACY_SERVER_KEY_ID].
+//!
+//! To migrate password files to the current key pair over time, the server can enable [ServerKeySet::set_request_reseal], in
+//! which case logins with an older password file set the `reseal` field of [ClientLoginFinishResult], which is sent as the last
+//! byte of the [CredentialResponse], after the OPAQUE message itself. The client can then re-seal
+//! its envelope with a [password change](#password-change) to the same password, which the server handles with
+//! [ServerPasswordChange::start_with_key_set]. Once no password file is sealed under a previous key pair, it can be dropped
+//! with [ServerKeySet::remove].
+//!
+//! ## Offloading the Slow Hashing Function
+//!
+//! With realistic cost parameters, the slow hashing function in [ClientRegistration::finish] and [ClientLogin::finish] takes long enough
+//! to stall an async runtime. Each of these can instead be run in two halves: [ClientRegistration::finish_oprf] and [ClientLogin::finish_oprf]
+//! return a [KeyStretch], which owns everything the slow hashing function needs, alongside the state needed to complete the protocol.
+//! The [KeyStretch] can then be moved onto another thread, such as the blocking thread pool of the runtime, and the resulting
+//! [StretchedKey] passed to [ClientRegistrationPending::finish] or [ClientLoginPending::finish] respectively:
+//! ```
+//! # use opaque_ke::{
+//! #   errors::ProtocolError,
+//! #   ClientRegistration, ClientRegistrationFinishParameters, ServerRegistration, ClientLogin, ClientLoginStartParameters, ClientLoginFinishParameters, ServerLogin, ServerLoginStartParameters,
+//! #   slow_hash::NoOpHash,
+//! # };
+//! # use opaque_ke::ciphersuite::CipherSuite;
+//! # struct Default;
+//! # impl CipherSuite for Default {
+//! #     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+//! #     type KeyExchange = opaque_ke::key_exchange::tripledh::TripleDH;
+//! #     type Hash = sha2::Sha512;
+//! #     type SlowHash = opaque_ke::slow_hash::NoOpHash;
+//! # }
+//! # use rand::{rngs::OsRng, RngCore};
+//! # let mut client_rng = OsRng;
+//! # let client_registration_start_result = ClientRegistration::<Default>::start(
+//! #     &mut client_rng,
+//! #     b"password",
+//! # )?;
+//! # let mut server_rng = OsRng;
+//! # let server_kp = Default::generate_random_keypair(&mut server_rng);
+//! # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+//! # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
+//! # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
+//! # let password_file = server_registration_start_result.state.finish(client_registration_finish_result.message)?;
+//! # let client_login_start_result = ClientLogin::<Default>::start(
+//! #     &mut client_rng,
+//! #     b"password",
+//! #     ClientLoginStartParameters::default(),
+//! # )?;
+//! # let server_login_start_result =
+//! #     ServerLogin::start(&mut server_rng, Some(password_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
+//! let oprf_result = client_login_start_result.state.finish_oprf(
+//!     server_login_start_result.message,
+//!     ClientLoginFinishParameters::default(),
+//! )?;
+//! let key_stretch = oprf_result.key_stretch;
+//! let stretched_key = std::thread::spawn(move || key_stretch.compute()).join().unwrap()?;
+//! let client_login_finish_result = oprf_result.state.finish(stretched_key)?;
+//! # Ok::<(), ProtocolError>(())
+//! ```
 //!
 //!
 
@@ -728,10 +949,7 @@ pub mod map_to_curve;
 pub mod key_exchange;
 pub mod keypair;
 
-#[cfg(feature = "bench")]
 pub mod oprf;
-#[cfg(not(feature = "bench"))]
-mod oprf;
 
 pub mod slow_hash;
 
@@ -745,16 +963,28 @@ mod tests;
 pub use rand;
 
 pub use crate::messages::{
-    CredentialFinalization, CredentialRequest, CredentialResponse, RegistrationRequest,
-    RegistrationResponse, RegistrationUpload,
+    CredentialFinalization, CredentialRequest, CredentialResponse, PasswordChangeFinalization,
+    PasswordChangeRequest, PasswordChangeResponse, RegistrationRequest, RegistrationResponse,
+    RegistrationUpload,
+};
+pub use crate::opaque::{
+    ClientLogin, ClientRegistration, OprfSeed, ServerKeySet, ServerLogin, ServerRegistration,
+    LEGACY_SERVER_KEY_ID,
 };
-pub use crate::opaque::{ClientLogin, ClientRegistration, ServerLogin, ServerRegistration};
 pub use crate::opaque::{
     ClientLoginFinishParameters, ClientLoginStartParameters, ClientRegistrationFinishParameters,
     ServerLoginStartParameters,
 };
 pub use crate::opaque::{
-    ClientLoginFinishResult, ClientLoginStartResult, ClientRegistrationFinishResult,
-    ClientRegistrationStartResult, ServerLoginFinishResult, ServerLoginStartResult,
-    ServerRegistrationStartResult,
+    ClientLoginFinishResult, ClientLoginOprfResult, ClientLoginStartResult,
+    ClientRegistrationFinishResult, ClientRegistrationOprfResult, ClientRegistrationStartResult,
+    ServerLoginFinishResult, ServerLoginStartResult, ServerRegistrationStartResult,
+};
+pub use crate::opaque::{ClientLoginPending, ClientRegistrationPending, KeyStretch, StretchedKey};
+pub use crate::opaque::{
+    ClientPasswordChange, ClientPasswordChangeFinishResult, ClientPasswordChangeStartResult,
+    ServerPasswordChange, ServerPasswordChangeFinishResult, ServerPasswordChangeStartResult,
 };
+
+pub use crate::envelope::{InnerEnvelopeMode, MAX_SECRET_LEN};
+pub use crate::key_exchange::tripledh::MAX_CONTEXT_LEN;
@@ -10,7 +10,7 @@ use crate::errors::InternalPakeError;
 use crate::group::Group;
 use crate::hash::Hash;
 use crate::serialization::i2osp;
-use curve25519_dalek::ristretto::RistrettoPoint;
+use curve25519_dalek::{ristretto::RistrettoPoint, scalar::Scalar};
 use digest::{BlockInput, Digest};
 use generic_array::typenum::Unsigned;
 use generic_array::GenericArray;
@@ -21,9 +21,22 @@ pub trait GroupWithMapToCurve: Group {
     /// <https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-05.txt>
     const SUITE_ID: usize;
 
+    /// The ciphersuite identifier as dictated by
+    /// <https://www.rfc-editor.org/rfc/rfc9497>, which names the group along
+    /// with the hash function it is defined for. When the group is paired
+    /// with a different hash function, it only serves to separate domains.
+    const IDENTIFIER: &'static str;
+
     /// transforms a password and domain separation tag (DST) into a curve point
     fn map_to_curve<H: Hash>(msg: &[u8], dst: &[u8]) -> Result<Self, InternalPakeError>;
 
+    /// transforms a message and domain separation tag (DST) into a uniformly
+    /// random scalar
+    fn hash_to_scalar<H: Hash>(
+        msg: &[u8],
+        dst: &[u8],
+    ) -> Result<<Self as Group>::Scalar, InternalPakeError>;
+
     /// Generates the contextString parameter as defined in
     /// <https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-05.txt>
     fn get_context_string(mode: u8) -> Vec<u8> {
@@ -31,21 +44,40 @@ pub trait GroupWithMapToCurve: Group {
     }
 }
 
+/// A group which, along with the hash function H, forms one of the
+/// ciphersuites of <https://www.rfc-editor.org/rfc/rfc9497>. The
+/// partially-oblivious mode of the OPRF is only available for these pairs.
+pub trait PoprfSuite<H: Hash>: GroupWithMapToCurve {}
+
+impl PoprfSuite<sha2::Sha512> for RistrettoPoint {}
+
 impl GroupWithMapToCurve for RistrettoPoint {
     const SUITE_ID: usize = 0x0001;
+    const IDENTIFIER: &'static str = "ristretto255-SHA512";
 
     // Implements the hash_to_ristretto255() function from
     // https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-10.txt
     fn map_to_curve<H: Hash>(msg: &[u8], dst: &[u8]) -> Result<Self, InternalPakeError> {
         let uniform_bytes =
-            expand_message_xmd::<H>(msg, dst, <H as Digest>::OutputSize::to_usize())?;
+            expand_message_xmd::<H>(msg, dst, <Self as Group>::UniformBytesLen::to_usize())?;
         Ok(<Self as Group>::hash_to_curve(
             &GenericArray::clone_from_slice(&uniform_bytes[..]),
         ))
     }
+
+    // Reduces 64 uniform bytes, interpreted in little-endian order, modulo
+    // the group order
+    fn hash_to_scalar<H: Hash>(msg: &[u8], dst: &[u8]) -> Result<Scalar, InternalPakeError> {
+        let uniform_bytes = expand_message_xmd::<H>(msg, dst, 64)?;
+        let mut bits = [0u8; 64];
+        bits.copy_from_slice(&uniform_bytes);
+        Ok(Scalar::from_bytes_mod_order_wide(&bits))
+    }
 }
 
 // Computes ceil(x / y)
+// usize::is_multiple_of would raise the minimum supported Rust version
+#[allow(clippy::manual_is_multiple_of)]
 fn div_ceil(x: usize, y: usize) -> usize {
     let additive = (x % y != 0) as usize;
     x / y + additive
@@ -82,7 +114,7 @@ pub(crate) fn expand_message_xmd<H: Hash>(
 
     let mut h = H::new();
     h.update(&b[0]);
-    h.update(&i2osp(1, 1));
+    h.update(i2osp(1, 1));
     h.update(&dst_prime);
     b.push(h.finalize_reset().to_vec()); // b[1]
 
@@ -91,7 +123,7 @@ pub(crate) fn expand_message_xmd<H: Hash>(
 
     for i in 2..(ell + 1) {
         h.update(xor(&b[0], &b[i - 1])?);
-        h.update(&i2osp(i, 1));
+        h.update(i2osp(i, 1));
         h.update(&dst_prime);
         b.push(h.finalize_reset().to_vec()); // b[i]
         uniform_bytes.extend_from_slice(&b[i]);
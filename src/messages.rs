@@ -14,8 +14,10 @@ use crate::{
     },
     group::Group,
     key_exchange::traits::{KeyExchange, ToBytes},
-    keypair::{Key, KeyPair, SizedBytesExt},
+    keypair::{KeyPair, PublicKey, SizedBytesExt},
+    serialization::{serialize, tokenize},
 };
+use digest::Digest;
 use generic_array::{typenum::Unsigned, GenericArray};
 use generic_bytes::SizedBytes;
 use std::convert::TryFrom;
@@ -38,7 +40,7 @@ impl<CS: CipherSuite> RegistrationRequest<CS> {
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
         let elem_len = <CS::Group as Group>::ElemLen::to_usize();
-        let checked_slice = check_slice_size(&input, elem_len, "first_message_bytes")?;
+        let checked_slice = check_slice_size(input, elem_len, "first_message_bytes")?;
         // Check that the message is actually containing an element of the
         // correct subgroup
         let arr = GenericArray::from_slice(checked_slice);
@@ -53,31 +55,36 @@ pub struct RegistrationResponse<CS: CipherSuite> {
     /// The server's oprf output
     pub(crate) beta: CS::Group,
     /// Server's static public key
-    pub(crate) server_s_pk: Vec<u8>,
+    pub(crate) server_s_pk: PublicKey<CS::Group>,
 }
 
 impl<CS: CipherSuite> RegistrationResponse<CS> {
     /// Serialization into bytes
     pub fn serialize(&self) -> Vec<u8> {
-        [self.beta.to_arr().to_vec(), self.server_s_pk.clone()].concat()
+        [
+            self.beta.to_arr().to_vec(),
+            self.server_s_pk.to_arr().to_vec(),
+        ]
+        .concat()
     }
 
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
         let elem_len = <CS::Group as Group>::ElemLen::to_usize();
-        let key_len = <Key as SizedBytes>::Len::to_usize();
+        let key_len = <PublicKey<CS::Group> as SizedBytes>::Len::to_usize();
         let checked_slice =
-            check_slice_size(&input, elem_len + key_len, "registration_response_bytes")?;
+            check_slice_size(input, elem_len + key_len, "registration_response_bytes")?;
 
         // Check that the message is actually containing an element of the
         // correct subgroup
         let arr = GenericArray::from_slice(&checked_slice[..elem_len]);
         let beta = CS::Group::from_element_slice(arr)?;
 
-        Ok(Self {
-            server_s_pk: checked_slice[elem_len..].to_vec(),
-            beta,
-        })
+        let server_s_pk = KeyPair::<CS::Group>::check_public_key(PublicKey::from_bytes(
+            &checked_slice[elem_len..],
+        )?)?;
+
+        Ok(Self { server_s_pk, beta })
     }
 }
 
@@ -86,9 +93,9 @@ impl<CS: CipherSuite> RegistrationResponse<CS> {
 pub struct RegistrationUpload<CS: CipherSuite> {
     /// The "envelope" generated by the user, containing sealed
     /// cryptographic identifiers
-    pub(crate) envelope: Envelope<CS::Hash>,
+    pub(crate) envelope: Envelope<CS>,
     /// The user's public key
-    pub(crate) client_s_pk: Key,
+    pub(crate) client_s_pk: PublicKey<CS::Group>,
 }
 
 impl<CS: CipherSuite> RegistrationUpload<CS> {
@@ -103,11 +110,11 @@ impl<CS: CipherSuite> RegistrationUpload<CS> {
 
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
-        let key_len = <Key as SizedBytes>::Len::to_usize();
+        let key_len = <PublicKey<CS::Group> as SizedBytes>::Len::to_usize();
 
-        let checked_slice = check_slice_size_atleast(&input, key_len, "registration_upload_bytes")?;
+        let checked_slice = check_slice_size_atleast(input, key_len, "registration_upload_bytes")?;
 
-        let (envelope, remainder) = Envelope::<CS::Hash>::deserialize(&checked_slice[key_len..])?;
+        let (envelope, remainder) = Envelope::<CS>::deserialize(&checked_slice[key_len..])?;
 
         if !remainder.is_empty() {
             return Err(PakeError::SerializationError.into());
@@ -115,7 +122,7 @@ impl<CS: CipherSuite> RegistrationUpload<CS> {
 
         Ok(Self {
             envelope,
-            client_s_pk: KeyPair::<CS::Group>::check_public_key(Key::from_bytes(
+            client_s_pk: KeyPair::<CS::Group>::check_public_key(PublicKey::from_bytes(
                 &checked_slice[..key_len],
             )?)?,
         })
@@ -142,8 +149,7 @@ impl<CS: CipherSuite> CredentialRequest<CS> {
     pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
         let elem_len = <CS::Group as Group>::ElemLen::to_usize();
 
-        let checked_slice =
-            check_slice_size_atleast(&input, elem_len, "login_first_message_bytes")?;
+        let checked_slice = check_slice_size_atleast(input, elem_len, "login_first_message_bytes")?;
 
         // Check that the message is actually containing an element of the
         // correct subgroup
@@ -164,9 +170,12 @@ impl<CS: CipherSuite> CredentialRequest<CS> {
 pub struct CredentialResponse<CS: CipherSuite> {
     /// the server's oprf output
     pub(crate) beta: CS::Group,
-    pub(crate) server_s_pk: Key,
+    pub(crate) server_s_pk: PublicKey<CS::Group>,
     /// the user's sealed information,
-    pub(crate) envelope: Envelope<CS::Hash>,
+    pub(crate) envelope: Envelope<CS>,
+    /// whether the server asks the client to re-seal its envelope, which is
+    /// sent as a trailing byte outside of the key exchange transcript
+    pub(crate) reseal: bool,
     pub(crate) ke2_message: <CS::KeyExchange as KeyExchange<CS::Hash, CS::Group>>::KE2Message,
 }
 
@@ -176,14 +185,15 @@ impl<CS: CipherSuite> CredentialResponse<CS> {
         [
             Self::serialize_without_ke(&self.beta, &self.server_s_pk, &self.envelope),
             self.ke2_message.to_bytes(),
+            vec![self.reseal as u8],
         ]
         .concat()
     }
 
     pub(crate) fn serialize_without_ke(
         beta: &CS::Group,
-        server_s_pk: &Key,
-        envelope: &Envelope<CS::Hash>,
+        server_s_pk: &PublicKey<CS::Group>,
+        envelope: &Envelope<CS>,
     ) -> Vec<u8> {
         [
             &beta.to_arr(),
@@ -196,9 +206,19 @@ impl<CS: CipherSuite> CredentialResponse<CS> {
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
         let elem_len = <CS::Group as Group>::ElemLen::to_usize();
-        let key_len = <Key as SizedBytes>::Len::to_usize();
+        let key_len = <PublicKey<CS::Group> as SizedBytes>::Len::to_usize();
         let checked_slice =
-            check_slice_size_atleast(input, elem_len + key_len, "login_second_message_bytes")?;
+            check_slice_size_atleast(input, elem_len + key_len + 1, "login_second_message_bytes")?;
+        // The re-seal request is the last byte, which is not part of the
+        // OPAQUE message
+        let (reseal_byte, checked_slice) = checked_slice
+            .split_last()
+            .ok_or(PakeError::SerializationError)?;
+        let reseal = match reseal_byte {
+            0 => false,
+            1 => true,
+            _ => return Err(PakeError::SerializationError.into()),
+        };
 
         // Check that the message is actually containing an element of the
         // correct subgroup
@@ -206,24 +226,26 @@ impl<CS: CipherSuite> CredentialResponse<CS> {
         let arr = GenericArray::from_slice(beta_bytes);
         let beta = CS::Group::from_element_slice(arr)?;
 
-        let unchecked_server_s_pk = Key::from_bytes(&checked_slice[elem_len..elem_len + key_len])?;
+        let unchecked_server_s_pk =
+            PublicKey::from_bytes(&checked_slice[elem_len..elem_len + key_len])?;
         let server_s_pk = KeyPair::<CS::Group>::check_public_key(unchecked_server_s_pk)?;
 
         let (envelope, remainder) =
-            Envelope::<CS::Hash>::deserialize(&checked_slice[elem_len + key_len..])?;
+            Envelope::<CS>::deserialize(&checked_slice[elem_len + key_len..])?;
 
         let ke2_message_size = CS::KeyExchange::ke2_message_size();
         let checked_remainder =
             check_slice_size_atleast(&remainder, ke2_message_size, "login_second_message_bytes")?;
         let ke2_message =
             <CS::KeyExchange as KeyExchange<CS::Hash, CS::Group>>::KE2Message::try_from(
-                &checked_remainder,
+                checked_remainder,
             )?;
 
         Ok(Self {
             beta,
             server_s_pk,
             envelope,
+            reseal,
             ke2_message,
         })
     }
@@ -248,3 +270,104 @@ impl<CS: CipherSuite> CredentialFinalization<CS> {
         Ok(Self { ke3_message })
     }
 }
+
+/// The message sent by the client to the server, to initiate a password
+/// change. It combines a login request under the old password with a
+/// registration request under the new one.
+pub struct PasswordChangeRequest<CS: CipherSuite> {
+    pub(crate) registration_request: RegistrationRequest<CS>,
+    pub(crate) credential_request: CredentialRequest<CS>,
+}
+
+impl<CS: CipherSuite> PasswordChangeRequest<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            self.registration_request.serialize(),
+            self.credential_request.serialize(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
+        let elem_len = <CS::Group as Group>::ElemLen::to_usize();
+        let checked_slice =
+            check_slice_size_atleast(input, elem_len, "password_change_request_bytes")?;
+
+        Ok(Self {
+            registration_request: RegistrationRequest::deserialize(&checked_slice[..elem_len])?,
+            credential_request: CredentialRequest::deserialize(&checked_slice[elem_len..])?,
+        })
+    }
+}
+
+/// The answer sent by the server to the client, upon reception of the
+/// password change attempt
+pub struct PasswordChangeResponse<CS: CipherSuite> {
+    pub(crate) registration_response: RegistrationResponse<CS>,
+    pub(crate) credential_response: CredentialResponse<CS>,
+}
+
+impl<CS: CipherSuite> PasswordChangeResponse<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            self.registration_response.serialize(),
+            self.credential_response.serialize(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
+        let elem_len = <CS::Group as Group>::ElemLen::to_usize();
+        let key_len = <PublicKey<CS::Group> as SizedBytes>::Len::to_usize();
+        let checked_slice =
+            check_slice_size_atleast(input, elem_len + key_len, "password_change_response_bytes")?;
+
+        Ok(Self {
+            registration_response: RegistrationResponse::deserialize(
+                &checked_slice[..elem_len + key_len],
+            )?,
+            credential_response: CredentialResponse::deserialize(
+                &checked_slice[elem_len + key_len..],
+            )?,
+        })
+    }
+}
+
+/// The final message from the client, which completes the login under the
+/// old password and uploads the envelope sealed under the new one, along
+/// with a MAC binding the upload to the session
+pub struct PasswordChangeFinalization<CS: CipherSuite> {
+    pub(crate) credential_finalization: CredentialFinalization<CS>,
+    pub(crate) registration_upload: RegistrationUpload<CS>,
+    pub(crate) mac: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+}
+
+impl<CS: CipherSuite> PasswordChangeFinalization<CS> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [
+            &self.mac[..],
+            &serialize(&self.credential_finalization.serialize(), 2),
+            &self.registration_upload.serialize(),
+        ]
+        .concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
+        let mac_len = <CS::Hash as Digest>::OutputSize::to_usize();
+        let checked_slice =
+            check_slice_size_atleast(input, mac_len, "password_change_finalization_bytes")?;
+        let (credential_finalization, remainder) = tokenize(&checked_slice[mac_len..], 2)?;
+
+        Ok(Self {
+            credential_finalization: CredentialFinalization::deserialize(&credential_finalization)?,
+            registration_upload: RegistrationUpload::deserialize(&remainder)?,
+            mac: GenericArray::clone_from_slice(&checked_slice[..mac_len]),
+        })
+    }
+}
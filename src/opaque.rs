@@ -7,22 +7,29 @@
 
 use crate::{
     ciphersuite::CipherSuite,
-    envelope::{mode_from_ids, Envelope},
-    errors::{utils::check_slice_size_atleast, InternalPakeError, PakeError, ProtocolError},
+    envelope::{mode_from_ids, Envelope, IdentifierMode, InnerEnvelopeMode, MAX_SECRET_LEN},
+    errors::{
+        utils::{check_slice_size, check_slice_size_atleast},
+        InternalPakeError, PakeError, ProtocolError,
+    },
     group::Group,
-    hash::Hash,
-    key_exchange::traits::{KeyExchange, ToBytes},
-    keypair::{Key, KeyPair, SizedBytesExt},
-    map_to_curve::GroupWithMapToCurve,
+    key_exchange::{
+        traits::{KeyExchange, ToBytes},
+        tripledh::check_context_len,
+    },
+    keypair::{KeyPair, PrivateKey, PublicKey, SizedBytesExt},
     oprf,
     serialization::{serialize, tokenize},
-    slow_hash::SlowHash,
-    CredentialFinalization, CredentialRequest, CredentialResponse, RegistrationRequest,
-    RegistrationResponse, RegistrationUpload,
+    slow_hash::{self, SlowHash},
+    CredentialFinalization, CredentialRequest, CredentialResponse, PasswordChangeFinalization,
+    PasswordChangeRequest, PasswordChangeResponse, RegistrationRequest, RegistrationResponse,
+    RegistrationUpload,
 };
 use digest::Digest;
 use generic_array::{typenum::Unsigned, GenericArray};
 use generic_bytes::SizedBytes;
+use hkdf::Hkdf;
+use hmac::{Hmac, Mac, NewMac};
 use rand::{CryptoRng, RngCore};
 use std::{convert::TryFrom, marker::PhantomData};
 use zeroize::Zeroize;
@@ -75,17 +82,58 @@ impl<CS: CipherSuite> ClientRegistration<CS> {
     }
 }
 
-/// Optional parameters for client registration finish
-pub enum ClientRegistrationFinishParameters {
-    /// Specifying the identifiers idU and idS (corresponding to custom identifier mode)
-    WithIdentifiers(Vec<u8>, Vec<u8>),
-    /// No identifiers specified (corresponding to base mode)
-    Default,
+/// Optional parameters for client registration finish. The default
+/// parameters specify no identifiers (corresponding to base mode), no key
+/// pair and no secret, and use the recommended instance of the slow hashing
+/// function.
+pub struct ClientRegistrationFinishParameters<CS: CipherSuite> {
+    identifiers: Option<(Vec<u8>, Vec<u8>)>,
+    slow_hash: Option<CS::SlowHash>,
+    client_s_kp: Option<KeyPair<CS::Group>>,
+    secret: Option<Vec<u8>>,
 }
 
-impl Default for ClientRegistrationFinishParameters {
+impl<CS: CipherSuite> Default for ClientRegistrationFinishParameters<CS> {
     fn default() -> Self {
-        Self::Default
+        Self {
+            identifiers: None,
+            slow_hash: None,
+            client_s_kp: None,
+            secret: None,
+        }
+    }
+}
+
+impl<CS: CipherSuite> ClientRegistrationFinishParameters<CS> {
+    /// Specifies the identifiers idU and idS (corresponding to custom identifier mode)
+    pub fn with_identifiers(mut self, id_u: Vec<u8>, id_s: Vec<u8>) -> Self {
+        self.identifiers = Some((id_u, id_s));
+        self
+    }
+
+    /// Specifies the instance of the slow hashing function, which carries
+    /// its cost parameters. These parameters are stored with the envelope,
+    /// so that the client can recover them at login.
+    pub fn with_slow_hash(mut self, slow_hash: CS::SlowHash) -> Self {
+        self.slow_hash = Some(slow_hash);
+        self
+    }
+
+    /// Specifies the client's existing static key pair, whose private key is
+    /// encrypted in the envelope. This is required when the envelope mode of
+    /// the ciphersuite is `InnerEnvelopeMode::External`, and rejected
+    /// otherwise. A key pair whose public key is not the one of its private
+    /// key is rejected with a `MismatchedKeyPairError`.
+    pub fn with_key_pair(mut self, client_s_kp: KeyPair<CS::Group>) -> Self {
+        self.client_s_kp = Some(client_s_kp);
+        self
+    }
+
+    /// Specifies a secret of at most [MAX_SECRET_LEN] bytes, which is
+    /// encrypted in the envelope and returned to the client at login
+    pub fn with_secret(mut self, secret: Vec<u8>) -> Self {
+        self.secret = Some(secret);
+        self
     }
 }
 
@@ -125,7 +173,7 @@ impl<CS: CipherSuite> ClientRegistration<CS> {
         blinding_factor_rng: &mut R,
         password: &[u8],
     ) -> Result<ClientRegistrationStartResult<CS>, ProtocolError> {
-        let (token, alpha) = oprf::blind::<R, CS::Group, CS::Hash>(&password, blinding_factor_rng)?;
+        let (token, alpha) = oprf::blind::<R, CS::Group, CS::Hash>(password, blinding_factor_rng)?;
 
         Ok(ClientRegistrationStartResult {
             message: RegistrationRequest::<CS> { alpha },
@@ -142,6 +190,25 @@ pub struct ClientRegistrationFinishResult<CS: CipherSuite> {
     pub export_key: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
 }
 
+/// Contains the fields that are returned by [ClientRegistration::finish_oprf]
+pub struct ClientRegistrationOprfResult<CS: CipherSuite> {
+    /// The slow hashing computation, which can be run on a separate thread
+    pub key_stretch: KeyStretch<CS>,
+    /// The state that the client must keep in order to complete registration
+    /// with the stretched key
+    pub state: ClientRegistrationPending<CS>,
+}
+
+/// The state elements the client holds while the slow hashing function runs
+/// during registration
+pub struct ClientRegistrationPending<CS: CipherSuite> {
+    server_s_pk: PublicKey<CS::Group>,
+    optional_ids: Option<(Vec<u8>, Vec<u8>)>,
+    ksf_parameters: Option<Vec<u8>>,
+    client_s_kp: Option<KeyPair<CS::Group>>,
+    secret: Option<Vec<u8>>,
+}
+
 impl<CS: CipherSuite> ClientRegistration<CS> {
     /// "Unblinds" the server's answer and returns a final message containing
     /// cryptographic identifiers, to be sent to the server on setup finalization
@@ -152,7 +219,7 @@ impl<CS: CipherSuite> ClientRegistration<CS> {
     /// # Example
     ///
     /// ```
-    /// use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, ServerRegistration};
+    /// use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, OprfSeed, ServerRegistration};
     /// # use opaque_ke::errors::ProtocolError;
     /// # use opaque_ke::keypair::KeyPair;
     /// use rand::{rngs::OsRng, RngCore};
@@ -167,9 +234,10 @@ impl<CS: CipherSuite> ClientRegistration<CS> {
     /// let mut client_rng = OsRng;
     /// let mut server_rng = OsRng;
     /// let server_kp = Default::generate_random_keypair(&mut server_rng);
+    /// let oprf_seed = OprfSeed::<Default>::generate_random(&mut server_rng);
     /// let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
     /// let server_registration_start_result =
-    /// ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+    /// ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
     /// let mut client_rng = OsRng;
     /// let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
     /// # Ok::<(), ProtocolError>(())
@@ -178,29 +246,125 @@ impl<CS: CipherSuite> ClientRegistration<CS> {
         self,
         rng: &mut R,
         r2: RegistrationResponse<CS>,
-        params: ClientRegistrationFinishParameters,
+        params: ClientRegistrationFinishParameters<CS>,
     ) -> Result<ClientRegistrationFinishResult<CS>, ProtocolError> {
-        let optional_ids = match params {
-            ClientRegistrationFinishParameters::WithIdentifiers(id_u, id_s) => Some((id_u, id_s)),
-            ClientRegistrationFinishParameters::Default => None,
-        };
-        let client_static_keypair = CS::generate_random_keypair(rng);
+        let ClientRegistrationOprfResult { key_stretch, state } = self.finish_oprf(r2, params)?;
+        state.finish(rng, key_stretch.compute()?)
+    }
 
-        let password_derived_key =
-            get_password_derived_key::<CS::Group, CS::SlowHash, CS::Hash>(&self.token, r2.beta)?;
+    /// The first half of [ClientRegistration::finish], which "unblinds" the
+    /// server's answer and returns the slow hashing computation separately
+    /// from the state needed to complete registration. This lets the slow
+    /// hashing function run on a separate thread, such as the blocking thread
+    /// pool of an async runtime.
+    ///
+    /// # Arguments
+    /// * `message` - the server's answer to the initial registration attempt
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, OprfSeed, ServerRegistration};
+    /// # use opaque_ke::errors::ProtocolError;
+    /// use rand::{rngs::OsRng, RngCore};
+    /// use opaque_ke::ciphersuite::CipherSuite;
+    /// struct Default;
+    /// impl CipherSuite for Default {
+    ///     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    ///     type KeyExchange = opaque_ke::key_exchange::tripledh::TripleDH;
+    ///     type Hash = sha2::Sha512;
+    ///     type SlowHash = opaque_ke::slow_hash::NoOpHash;
+    /// }
+    /// let mut client_rng = OsRng;
+    /// let mut server_rng = OsRng;
+    /// let server_kp = Default::generate_random_keypair(&mut server_rng);
+    /// let oprf_seed = OprfSeed::<Default>::generate_random(&mut server_rng);
+    /// let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
+    /// let server_registration_start_result =
+    /// ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
+    /// let oprf_result = client_registration_start_result.state.finish_oprf(server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
+    /// let key_stretch = oprf_result.key_stretch;
+    /// let stretched_key = std::thread::spawn(move || key_stretch.compute()).join().unwrap()?;
+    /// let client_registration_finish_result = oprf_result.state.finish(&mut client_rng, stretched_key)?;
+    /// # Ok::<(), ProtocolError>(())
+    /// ```
+    pub fn finish_oprf(
+        self,
+        r2: RegistrationResponse<CS>,
+        params: ClientRegistrationFinishParameters<CS>,
+    ) -> Result<ClientRegistrationOprfResult<CS>, ProtocolError> {
+        let ClientRegistrationFinishParameters {
+            identifiers: optional_ids,
+            slow_hash,
+            client_s_kp,
+            secret,
+        } = params;
+
+        // A key pair is taken in external mode only, which is checked before
+        // running the slow hashing function
+        if client_s_kp.is_some() != (CS::INNER_ENVELOPE_MODE == InnerEnvelopeMode::External) {
+            return Err(InternalPakeError::IncompatibleEnvelopeModeError.into());
+        }
+        // A mismatched key pair would otherwise register the wrong public key,
+        // on which every login would then fail
+        if let Some(client_s_kp) = &client_s_kp {
+            if &KeyPair::<CS::Group>::public_from_private(client_s_kp.private())
+                != client_s_kp.public()
+            {
+                return Err(InternalPakeError::MismatchedKeyPairError.into());
+            }
+        }
+
+        // Parameters chosen by the client are stored with the envelope, so
+        // that they can be recovered at login
+        let ksf_parameters = slow_hash
+            .as_ref()
+            .map(slow_hash::serialize_parameter_block::<CS::Hash, CS::SlowHash>);
+
+        Ok(ClientRegistrationOprfResult {
+            key_stretch: KeyStretch::new(
+                &self.token,
+                r2.beta,
+                slow_hash.unwrap_or_else(CS::SlowHash::recommended),
+            ),
+            state: ClientRegistrationPending {
+                server_s_pk: r2.server_s_pk,
+                optional_ids,
+                ksf_parameters,
+                client_s_kp,
+                secret,
+            },
+        })
+    }
+}
 
-        let (envelope, export_key) = Envelope::<CS::Hash>::seal(
+impl<CS: CipherSuite> ClientRegistrationPending<CS> {
+    /// The second half of [ClientRegistration::finish], which seals the
+    /// envelope with the output of [KeyStretch::compute] and returns the
+    /// final message, to be sent to the server on setup finalization
+    ///
+    /// # Arguments
+    /// * `stretched_key` - the output of the slow hashing computation
+    pub fn finish<R: CryptoRng + RngCore>(
+        self,
+        rng: &mut R,
+        stretched_key: StretchedKey<CS>,
+    ) -> Result<ClientRegistrationFinishResult<CS>, ProtocolError> {
+        let (envelope, client_s_pk, export_key) = Envelope::<CS>::seal(
             rng,
-            &password_derived_key,
-            &client_static_keypair.private().to_arr().to_vec(),
-            &r2.server_s_pk,
-            optional_ids,
+            &stretched_key.key,
+            CS::INNER_ENVELOPE_MODE,
+            self.client_s_kp.as_ref(),
+            self.secret.as_deref(),
+            &self.server_s_pk,
+            self.optional_ids,
+            self.ksf_parameters,
         )?;
 
         Ok(ClientRegistrationFinishResult {
             message: RegistrationUpload {
                 envelope,
-                client_s_pk: client_static_keypair.public().clone(),
+                client_s_pk,
             },
             export_key,
         })
@@ -235,6 +399,205 @@ impl<CS: CipherSuite> Drop for ClientLogin<CS> {
     }
 }
 
+static STR_OPRF_KEY: &[u8] = b"OprfKey";
+static STR_OPAQUE_DERIVE_KEY_PAIR: &[u8] = b"OPAQUE-DeriveKeyPair";
+static STR_FAKE_CLIENT_KEY: &[u8] = b"FakeClientKey";
+static STR_FAKE_ENVELOPE: &[u8] = b"FakeEnvelope";
+static STR_PASSWORD_CHANGE: &[u8] = b"PasswordChange";
+
+/// The secret seed the server holds across all registrations, from which the
+/// OPRF key of each user is derived, along with the settings of the fake
+/// password files returned for users who have not registered
+pub struct OprfSeed<CS: CipherSuite> {
+    seed: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    fake_ksf_parameters: Option<Vec<u8>>,
+    fake_secret_len: Option<usize>,
+}
+
+impl<CS: CipherSuite> OprfSeed<CS> {
+    /// Samples a fresh seed
+    pub fn generate_random<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        let mut seed = GenericArray::default();
+        rng.fill_bytes(&mut seed);
+        Self {
+            seed,
+            fake_ksf_parameters: None,
+            fake_secret_len: None,
+        }
+    }
+
+    /// Serialization into bytes, which only covers the seed
+    pub fn serialize(&self) -> Vec<u8> {
+        self.seed.to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
+        let checked_bytes = check_slice_size(
+            input,
+            <CS::Hash as Digest>::OutputSize::to_usize(),
+            "oprf_seed_bytes",
+        )?;
+        Ok(Self {
+            seed: GenericArray::clone_from_slice(checked_bytes),
+            fake_ksf_parameters: None,
+            fake_secret_len: None,
+        })
+    }
+
+    /// Sets the slow hashing parameters which the fake password files of
+    /// unregistered users carry. These should be the parameters which clients
+    /// store with their envelopes at registration, if they do, so that the
+    /// responses for unregistered users look like those for registered ones.
+    pub fn set_fake_slow_hash(&mut self, slow_hash: &CS::SlowHash) {
+        self.fake_ksf_parameters =
+            Some(slow_hash::serialize_parameter_block::<CS::Hash, CS::SlowHash>(slow_hash));
+    }
+
+    /// Sets the length of the secret which the fake password files of
+    /// unregistered users carry, which can be at most [MAX_SECRET_LEN]. This
+    /// should be the length of the secrets which clients store in their
+    /// envelopes at registration, if they do, so that the responses for
+    /// unregistered users look like those for registered ones.
+    pub fn set_fake_secret_len(&mut self, secret_len: usize) -> Result<(), ProtocolError> {
+        if secret_len > MAX_SECRET_LEN {
+            return Err(InternalPakeError::SecretTooLongError.into());
+        }
+        self.fake_secret_len = Some(secret_len);
+        Ok(())
+    }
+
+    /// Derives the OPRF key of the user with the given credential identifier:
+    /// seed = Expand(oprf_seed, credential_identifier || "OprfKey", Nok),
+    /// followed by DeriveKeyPair(seed, "OPAQUE-DeriveKeyPair")
+    pub(crate) fn derive_oprf_key(
+        &self,
+        credential_identifier: &[u8],
+    ) -> Result<<CS::Group as Group>::Scalar, InternalPakeError> {
+        self.derive_scalar(credential_identifier, STR_OPRF_KEY)
+    }
+
+    // Derives a scalar for the given credential identifier and label, using
+    // the same construction as for OPRF keys
+    fn derive_scalar(
+        &self,
+        credential_identifier: &[u8],
+        label: &[u8],
+    ) -> Result<<CS::Group as Group>::Scalar, InternalPakeError> {
+        let mut ikm = GenericArray::<u8, <CS::Group as Group>::ScalarLen>::default();
+        self.expand(credential_identifier, label, &mut ikm)?;
+        let scalar = oprf::derive_key::<CS::Group, CS::Hash>(&ikm, STR_OPAQUE_DERIVE_KEY_PAIR);
+        ikm.as_mut_slice().zeroize();
+        scalar
+    }
+
+    fn expand(
+        &self,
+        credential_identifier: &[u8],
+        label: &[u8],
+        output: &mut [u8],
+    ) -> Result<(), InternalPakeError> {
+        Hkdf::<CS::Hash>::from_prk(&self.seed)
+            .map_err(|_| InternalPakeError::HkdfError)?
+            .expand(&[credential_identifier, label].concat(), output)
+            .map_err(|_| InternalPakeError::HkdfError)
+    }
+}
+
+impl<CS: CipherSuite> Zeroize for OprfSeed<CS> {
+    fn zeroize(&mut self) {
+        self.seed.as_mut_slice().zeroize();
+    }
+}
+
+impl<CS: CipherSuite> Drop for OprfSeed<CS> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+/// The key ID under which [ServerKeySet] looks up password files which do not
+/// record one, such as those from [ServerRegistration::start]
+pub const LEGACY_SERVER_KEY_ID: u32 = 0;
+
+/// The static key pairs the server holds across key rotations, each under a
+/// key ID which is stored in the password files sealed under it. New
+/// registrations use the current key pair, while logins use the key pair
+/// that the user's password file was sealed under.
+pub struct ServerKeySet<CS: CipherSuite> {
+    current_key_id: u32,
+    current: KeyPair<CS::Group>,
+    previous: Vec<(u32, KeyPair<CS::Group>)>,
+    request_reseal: bool,
+}
+
+impl<CS: CipherSuite> ServerKeySet<CS> {
+    /// Creates a key set with a single key pair, which is the current one
+    pub fn new(key_id: u32, keypair: KeyPair<CS::Group>) -> Self {
+        Self {
+            current_key_id: key_id,
+            current: keypair,
+            previous: Vec::new(),
+            request_reseal: false,
+        }
+    }
+
+    /// Adds a key pair and makes it the current one. The previous key pairs
+    /// are kept to log in the users whose password files were sealed under
+    /// them.
+    pub fn rotate(&mut self, key_id: u32, keypair: KeyPair<CS::Group>) {
+        let previous_key_id = std::mem::replace(&mut self.current_key_id, key_id);
+        let previous = std::mem::replace(&mut self.current, keypair);
+        self.previous.retain(|(id, _)| *id != key_id);
+        if previous_key_id != key_id {
+            self.previous.push((previous_key_id, previous));
+        }
+    }
+
+    /// Removes a previous key pair, once no password file is sealed under it
+    /// anymore. The current key pair cannot be removed.
+    pub fn remove(&mut self, key_id: u32) -> Option<KeyPair<CS::Group>> {
+        let index = self.previous.iter().position(|(id, _)| *id == key_id)?;
+        Some(self.previous.remove(index).1)
+    }
+
+    /// Sets whether logins with a password file sealed under a previous key
+    /// pair ask the client to re-seal its envelope under the current one.
+    /// The request is a hint outside of the key exchange transcript, so a
+    /// network attacker can add or strip it, which at most causes a needless
+    /// or a skipped re-seal.
+    ///
+    /// Note that a login with a password file sealed under a previous key
+    /// pair reveals that the user is registered to anyone who observes it,
+    /// since the response carries the previous public key, whereas the
+    /// responses for unregistered users carry the current one. The request,
+    /// which only accompanies such responses, reveals nothing more.
+    pub fn set_request_reseal(&mut self, request_reseal: bool) {
+        self.request_reseal = request_reseal;
+    }
+
+    /// The ID of the current key pair
+    pub fn current_key_id(&self) -> u32 {
+        self.current_key_id
+    }
+
+    /// The current key pair, used for new registrations
+    pub fn current(&self) -> &KeyPair<CS::Group> {
+        &self.current
+    }
+
+    /// The key pair with the given ID, if it is in the set
+    pub fn get(&self, key_id: u32) -> Option<&KeyPair<CS::Group>> {
+        if key_id == self.current_key_id {
+            return Some(&self.current);
+        }
+        self.previous
+            .iter()
+            .find(|(id, _)| *id == key_id)
+            .map(|(_, keypair)| keypair)
+    }
+}
+
 /// Contains the fields that are returned by a server registration start
 pub struct ServerRegistrationStartResult<CS: CipherSuite> {
     /// The registration resposne message to send to the client
@@ -245,53 +608,102 @@ pub struct ServerRegistrationStartResult<CS: CipherSuite> {
 
 /// The state elements the server holds to record a registration
 pub struct ServerRegistration<CS: CipherSuite> {
-    envelope: Option<Envelope<CS::Hash>>,
-    client_s_pk: Option<Key>,
-    pub(crate) oprf_key: <CS::Group as Group>::Scalar,
+    envelope: Option<Envelope<CS>>,
+    client_s_pk: Option<PublicKey<CS::Group>>,
+    key_id: Option<u32>,
 }
 
 impl<CS: CipherSuite> ServerRegistration<CS> {
     /// Serialization into bytes
     pub fn serialize(&self) -> Vec<u8> {
-        let mut output: Vec<u8> = CS::Group::scalar_as_bytes(&self.oprf_key).to_vec();
+        let mut output: Vec<u8> = Vec::new();
         self.client_s_pk
             .iter()
             .for_each(|v| output.extend_from_slice(&v.to_arr()));
         self.envelope
             .iter()
             .for_each(|v| output.extend_from_slice(&v.to_bytes()));
+        self.key_id
+            .iter()
+            .for_each(|v| output.extend_from_slice(&v.to_be_bytes()));
         output
     }
 
     /// Deserialization from bytes
     pub fn deserialize(input: &[u8]) -> Result<Self, ProtocolError> {
-        let scalar_len = <CS::Group as Group>::ScalarLen::to_usize();
-        if input.len() == scalar_len {
+        if input.is_empty() {
             return Ok(Self {
-                oprf_key: CS::Group::from_scalar_slice(GenericArray::from_slice(input))?,
                 client_s_pk: None,
                 envelope: None,
+                key_id: None,
             });
         }
 
         // Need to do this check manually because envelope is variable-size
-        let key_len = <Key as SizedBytes>::Len::to_usize();
+        let key_len = <PublicKey<CS::Group> as SizedBytes>::Len::to_usize();
 
-        let checked_bytes =
-            check_slice_size_atleast(&input, scalar_len + key_len, "server_registration_bytes")?;
+        let checked_bytes = check_slice_size_atleast(input, key_len, "server_registration_bytes")?;
 
-        let oprf_key_bytes = GenericArray::from_slice(&checked_bytes[..scalar_len]);
-        let oprf_key = CS::Group::from_scalar_slice(oprf_key_bytes)?;
-        let unchecked_client_s_pk =
-            Key::from_bytes(&checked_bytes[scalar_len..scalar_len + key_len])?;
+        let unchecked_client_s_pk = PublicKey::from_bytes(&checked_bytes[..key_len])?;
         let client_s_pk = KeyPair::<CS::Group>::check_public_key(unchecked_client_s_pk)?;
 
-        let envelope = Envelope::<CS::Hash>::from_bytes(&checked_bytes[scalar_len + key_len..])?;
+        // The envelope is followed by the ID of the server key it was sealed
+        // under, if any
+        let (envelope, remainder) = Envelope::<CS>::deserialize(&checked_bytes[key_len..])
+            .map_err(|_| InternalPakeError::InvalidEnvelopeStructureError)?;
+        let key_id = match remainder.len() {
+            0 => None,
+            _ => {
+                let mut key_id = [0u8; 4];
+                key_id.copy_from_slice(check_slice_size(
+                    &remainder,
+                    4,
+                    "server_registration_key_id",
+                )?);
+                Some(u32::from_be_bytes(key_id))
+            }
+        };
 
         Ok(Self {
             envelope: Some(envelope),
             client_s_pk: Some(client_s_pk),
-            oprf_key,
+            key_id,
+        })
+    }
+
+    /// The ID of the server key which the envelope was sealed under, for a
+    /// password file registered with [ServerRegistration::start_with_key_set]
+    pub fn key_id(&self) -> Option<u32> {
+        self.key_id
+    }
+
+    // A stand-in for the password file of a user who has not registered,
+    // derived deterministically from the OPRF seed so that repeated login
+    // attempts for the same credential identifier see the same record. The
+    // server derives the client public key from the OPRF seed, so that no
+    // client knows its private key, and the envelope consists of pseudorandom
+    // bytes which no password opens, along with the slow hashing parameters
+    // and the length of the secret configured on the seed.
+    fn fake(
+        oprf_seed: &OprfSeed<CS>,
+        credential_identifier: &[u8],
+        identifier_mode: IdentifierMode,
+    ) -> Result<Self, ProtocolError> {
+        let client_s_sk = oprf_seed.derive_scalar(credential_identifier, STR_FAKE_CLIENT_KEY)?;
+        let client_s_pk = PublicKey::from_arr(&(CS::Group::base_point() * &client_s_sk).to_arr())?;
+
+        let envelope = Envelope::<CS>::fake(
+            CS::INNER_ENVELOPE_MODE,
+            identifier_mode,
+            oprf_seed.fake_ksf_parameters.clone(),
+            oprf_seed.fake_secret_len,
+            |bytes| oprf_seed.expand(credential_identifier, STR_FAKE_ENVELOPE, bytes),
+        )?;
+
+        Ok(Self {
+            envelope: Some(envelope),
+            client_s_pk: Some(client_s_pk),
+            key_id: None,
         })
     }
 
@@ -299,7 +711,9 @@ impl<CS: CipherSuite> ServerRegistration<CS> {
     /// sent back to the client, as well as a ServerRegistration
     ///
     /// # Arguments
+    /// * `oprf_seed` - the server's OPRF seed, from which the user's OPRF key is derived
     /// * `message`   - the initial registration message
+    /// * `credential_identifier` - an identifier for the user, unique on the server
     ///
     /// # Example
     ///
@@ -318,32 +732,62 @@ impl<CS: CipherSuite> ServerRegistration<CS> {
     /// let mut client_rng = OsRng;
     /// let mut server_rng = OsRng;
     /// let server_kp = Default::generate_random_keypair(&mut server_rng);
+    /// let oprf_seed = OprfSeed::<Default>::generate_random(&mut server_rng);
     /// let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
-    /// let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+    /// let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
     /// # Ok::<(), ProtocolError>(())
     /// ```
-    pub fn start<R: RngCore + CryptoRng>(
-        rng: &mut R,
+    pub fn start(
+        oprf_seed: &OprfSeed<CS>,
+        message: RegistrationRequest<CS>,
+        server_s_pk: &PublicKey<CS::Group>,
+        credential_identifier: &[u8],
+    ) -> Result<ServerRegistrationStartResult<CS>, ProtocolError> {
+        let oprf_key = oprf_seed.derive_oprf_key(credential_identifier)?;
+        Ok(Self::start_with_oprf_key(&oprf_key, message, server_s_pk))
+    }
+
+    /// Same as [ServerRegistration::start], with the server's current key
+    /// pair taken from a key set. The resulting password file records the ID
+    /// of the key pair, so that [ServerLogin::start_with_key_set] can find it
+    /// after later rotations.
+    pub fn start_with_key_set(
+        oprf_seed: &OprfSeed<CS>,
         message: RegistrationRequest<CS>,
-        server_s_pk: &Key,
+        server_keys: &ServerKeySet<CS>,
+        credential_identifier: &[u8],
     ) -> Result<ServerRegistrationStartResult<CS>, ProtocolError> {
-        // RFC: generate oprf_key (salt) and v_u = g^oprf_key
-        let oprf_key = CS::Group::random_scalar(rng);
+        let mut result = Self::start(
+            oprf_seed,
+            message,
+            server_keys.current().public(),
+            credential_identifier,
+        )?;
+        result.state.key_id = Some(server_keys.current_key_id());
+        Ok(result)
+    }
 
+    // Registration start with an OPRF key supplied directly rather than
+    // derived from a seed, as in the test vectors
+    pub(crate) fn start_with_oprf_key(
+        oprf_key: &<CS::Group as Group>::Scalar,
+        message: RegistrationRequest<CS>,
+        server_s_pk: &PublicKey<CS::Group>,
+    ) -> ServerRegistrationStartResult<CS> {
         // Compute beta = alpha^oprf_key
-        let beta = oprf::evaluate::<CS::Group>(message.alpha, &oprf_key);
+        let beta = oprf::evaluate::<CS::Group>(message.alpha, oprf_key);
 
-        Ok(ServerRegistrationStartResult {
+        ServerRegistrationStartResult {
             message: RegistrationResponse {
                 beta,
-                server_s_pk: server_s_pk.to_arr().to_vec(),
+                server_s_pk: server_s_pk.clone(),
             },
             state: Self {
                 envelope: None,
                 client_s_pk: None,
-                oprf_key,
+                key_id: None,
             },
-        })
+        }
     }
 
     /// From the client's cryptographic identifiers, fully populates and
@@ -369,8 +813,9 @@ impl<CS: CipherSuite> ServerRegistration<CS> {
     /// let mut client_rng = OsRng;
     /// let mut server_rng = OsRng;
     /// let server_kp = Default::generate_random_keypair(&mut server_rng);
+    /// let oprf_seed = OprfSeed::<Default>::generate_random(&mut server_rng);
     /// let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
-    /// let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+    /// let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
     /// let mut client_rng = OsRng;
     /// let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
     /// let client_record = server_registration_start_result.state.finish(client_registration_finish_result.message)?;
@@ -380,7 +825,7 @@ impl<CS: CipherSuite> ServerRegistration<CS> {
         Ok(Self {
             envelope: Some(message.envelope),
             client_s_pk: Some(message.client_s_pk),
-            oprf_key: self.oprf_key,
+            key_id: self.key_id,
         })
     }
 }
@@ -394,6 +839,7 @@ pub struct ClientLogin<CS: CipherSuite> {
     token: oprf::Token<CS::Group>,
     ke1_state: <CS::KeyExchange as KeyExchange<CS::Hash, CS::Group>>::KE1State,
     serialized_credential_request: Vec<u8>,
+    context: Vec<u8>,
 }
 
 impl<CS: CipherSuite> ClientLogin<CS> {
@@ -402,6 +848,7 @@ impl<CS: CipherSuite> ClientLogin<CS> {
         let output: Vec<u8> = [
             &CS::Group::scalar_as_bytes(&self.token.blind)[..],
             &serialize(&self.serialized_credential_request, 2),
+            &serialize(&self.context, 2),
             &serialize(&self.ke1_state.to_bytes(), 2),
             &self.token.data,
         ]
@@ -426,6 +873,7 @@ impl<CS: CipherSuite> ClientLogin<CS> {
         let blinding_factor = CS::Group::from_scalar_slice(blinding_factor_bytes)?;
 
         let (serialized_credential_request, remainder) = tokenize(&checked_slice[scalar_len..], 2)?;
+        let (context, remainder) = tokenize(&remainder, 2)?;
         let (ke1_state_bytes, password) = tokenize(&remainder, 2)?;
 
         let ke1_state = <CS::KeyExchange as KeyExchange<CS::Hash, CS::Group>>::KE1State::try_from(
@@ -438,19 +886,32 @@ impl<CS: CipherSuite> ClientLogin<CS> {
             },
             ke1_state,
             serialized_credential_request,
+            context,
         })
     }
 }
 
-/// Optional parameters for client login start
-pub enum ClientLoginStartParameters {
-    /// Specifying a plaintext info field that will be sent to the server
-    WithInfo(Vec<u8>),
+/// Optional parameters for client login start. The default parameters send
+/// no info and bind no application context.
+#[derive(Default)]
+pub struct ClientLoginStartParameters {
+    info: Option<Vec<u8>>,
+    context: Option<Vec<u8>>,
 }
 
-impl Default for ClientLoginStartParameters {
-    fn default() -> Self {
-        Self::WithInfo(Vec::new())
+impl ClientLoginStartParameters {
+    /// Specifies a plaintext info field that will be sent to the server
+    pub fn with_info(mut self, info: Vec<u8>) -> Self {
+        self.info = Some(info);
+        self
+    }
+
+    /// Specifies an application context of at most [MAX_CONTEXT_LEN](crate::MAX_CONTEXT_LEN) bytes,
+    /// such as a TLS exporter value or an application name, that is bound to
+    /// the login and must match the context supplied to the server
+    pub fn with_context(mut self, context: Vec<u8>) -> Self {
+        self.context = Some(context);
+        self
     }
 }
 
@@ -462,17 +923,63 @@ pub struct ClientLoginStartResult<CS: CipherSuite> {
     pub state: ClientLogin<CS>,
 }
 
-/// Optional parameters for client login finish
-pub enum ClientLoginFinishParameters {
-    /// Specifying a user identifier and server identifier that will be matched against the client
-    WithIdentifiers(Vec<u8>, Vec<u8>),
-    /// No info and no custom identifiers
-    Default,
+/// Optional parameters for client login finish, where `SH` is the slow
+/// hashing function of the ciphersuite. The default parameters have no
+/// custom identifiers, and use the slow hashing parameters stored with the
+/// envelope, which must be no weaker than [SlowHash::recommended] and within
+/// [SlowHash::maximum], or the recommended instance of the slow hashing
+/// function if there are none.
+pub struct ClientLoginFinishParameters<SH> {
+    identifiers: Option<(Vec<u8>, Vec<u8>)>,
+    slow_hash: Option<SH>,
+    minimum_slow_hash: Option<SH>,
+    maximum_slow_hash: Option<SH>,
 }
 
-impl Default for ClientLoginFinishParameters {
+impl<SH> Default for ClientLoginFinishParameters<SH> {
     fn default() -> Self {
-        Self::Default
+        Self {
+            identifiers: None,
+            slow_hash: None,
+            minimum_slow_hash: None,
+            maximum_slow_hash: None,
+        }
+    }
+}
+
+impl<SH> ClientLoginFinishParameters<SH> {
+    /// Specifies a user identifier and server identifier that will be matched against the client
+    pub fn with_identifiers(mut self, id_u: Vec<u8>, id_s: Vec<u8>) -> Self {
+        self.identifiers = Some((id_u, id_s));
+        self
+    }
+
+    /// Specifies the instance of the slow hashing function, which must match
+    /// the one used during registration if its parameters were not stored
+    /// with the envelope. Otherwise, the stored parameters are used instead,
+    /// and must be no weaker than those of this instance, unless a minimum is
+    /// specified with [Self::with_minimum_slow_hash].
+    pub fn with_slow_hash(mut self, slow_hash: SH) -> Self {
+        self.slow_hash = Some(slow_hash);
+        self
+    }
+
+    /// Specifies the weakest instance of the slow hashing function that the
+    /// client accepts for parameters stored with the envelope, in place of
+    /// [SlowHash::recommended]. This relaxes the minimum for envelopes sealed
+    /// with cheaper parameters, which would otherwise let a malicious server
+    /// downgrade the client's key stretching.
+    pub fn with_minimum_slow_hash(mut self, minimum_slow_hash: SH) -> Self {
+        self.minimum_slow_hash = Some(minimum_slow_hash);
+        self
+    }
+
+    /// Specifies the most expensive instance of the slow hashing function
+    /// that the client computes for parameters stored with the envelope, in
+    /// place of [SlowHash::maximum]
+    pub fn with_maximum_slow_hash(mut self, maximum_slow_hash: SH) -> Self {
+        self.maximum_slow_hash = Some(maximum_slow_hash);
+        self
     }
 }
 
@@ -485,9 +992,35 @@ pub struct ClientLoginFinishResult<CS: CipherSuite> {
     /// The client-side export key
     pub export_key: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
     /// The server's static public key
-    pub server_s_pk: Key,
+    pub server_s_pk: PublicKey<CS::Group>,
     /// The confidential info sent by the client
     pub confidential_info: Vec<u8>,
+    /// The client's static key pair, as supplied at registration, which is
+    /// only returned in `InnerEnvelopeMode::External`
+    pub client_static_keypair: Option<KeyPair<CS::Group>>,
+    /// The secret stored in the envelope at registration, if any
+    pub secret: Option<Vec<u8>>,
+    /// Whether the server asked the client to re-seal its envelope under the
+    /// server's current static key, which the client can do with a
+    /// [ClientPasswordChange] to the same password
+    pub reseal: bool,
+}
+
+/// Contains the fields that are returned by [ClientLogin::finish_oprf]
+pub struct ClientLoginOprfResult<CS: CipherSuite> {
+    /// The slow hashing computation, which can be run on a separate thread
+    pub key_stretch: KeyStretch<CS>,
+    /// The state that the client must keep in order to complete the login
+    /// with the stretched key
+    pub state: ClientLoginPending<CS>,
+}
+
+/// The state elements the client holds while the slow hashing function runs
+/// during login
+pub struct ClientLoginPending<CS: CipherSuite> {
+    login: ClientLogin<CS>,
+    credential_response: CredentialResponse<CS>,
+    optional_ids: Option<(Vec<u8>, Vec<u8>)>,
 }
 
 impl<CS: CipherSuite> ClientLogin<CS> {
@@ -519,9 +1052,11 @@ impl<CS: CipherSuite> ClientLogin<CS> {
         password: &[u8],
         params: ClientLoginStartParameters,
     ) -> Result<ClientLoginStartResult<CS>, ProtocolError> {
-        let ClientLoginStartParameters::WithInfo(info) = params;
+        let info = params.info.unwrap_or_default();
+        let context = params.context.unwrap_or_default();
+        check_context_len(&context)?;
 
-        let (token, alpha) = oprf::blind::<R, CS::Group, CS::Hash>(&password, rng)?;
+        let (token, alpha) = oprf::blind::<R, CS::Group, CS::Hash>(password, rng)?;
 
         let (ke1_state, ke1_message) = CS::KeyExchange::generate_ke1(info, rng)?;
 
@@ -534,6 +1069,7 @@ impl<CS: CipherSuite> ClientLogin<CS> {
                 token,
                 ke1_state,
                 serialized_credential_request,
+                context,
             },
         })
     }
@@ -548,7 +1084,7 @@ impl<CS: CipherSuite> ClientLogin<CS> {
     ///
     /// ```
     /// use opaque_ke::{ClientLogin, ClientLoginStartParameters, ClientLoginFinishParameters, ServerLogin, ServerLoginStartParameters};
-    /// # use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, ServerRegistration};
+    /// # use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, OprfSeed, ServerRegistration};
     /// # use opaque_ke::errors::ProtocolError;
     /// # use opaque_ke::keypair::KeyPair;
     /// use rand::{rngs::OsRng, RngCore};
@@ -564,60 +1100,178 @@ impl<CS: CipherSuite> ClientLogin<CS> {
     /// # let mut server_rng = OsRng;
     /// # let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
     /// # let server_kp = Default::generate_random_keypair(&mut server_rng);
-    /// # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+    /// # let oprf_seed = opaque_ke::OprfSeed::<Default>::generate_random(&mut server_rng);
+    /// # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
     /// # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
     /// # let p_file = server_registration_start_result.state.finish(client_registration_finish_result.message)?;
     /// let client_login_start_result = ClientLogin::<Default>::start(&mut client_rng, b"hunter2", ClientLoginStartParameters::default())?;
-    /// let server_login_start_result = ServerLogin::start(&mut server_rng, p_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::default())?;
+    /// let server_login_start_result = ServerLogin::start(&mut server_rng, Some(p_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
     /// let client_login_finish_result = client_login_start_result.state.finish(server_login_start_result.message, ClientLoginFinishParameters::default())?;
     /// # Ok::<(), ProtocolError>(())
     /// ```
     pub fn finish(
         self,
         l2: CredentialResponse<CS>,
-        params: ClientLoginFinishParameters,
+        params: ClientLoginFinishParameters<CS::SlowHash>,
     ) -> Result<ClientLoginFinishResult<CS>, ProtocolError> {
-        let optional_ids = match params {
-            ClientLoginFinishParameters::Default => None,
-            ClientLoginFinishParameters::WithIdentifiers(id_u, id_s) => Some((id_u, id_s)),
+        let ClientLoginOprfResult { key_stretch, state } = self.finish_oprf(l2, params)?;
+        state.finish(key_stretch.compute()?)
+    }
+
+    /// The first half of [ClientLogin::finish], which "unblinds" the server's
+    /// answer and returns the slow hashing computation separately from the
+    /// state needed to complete the login. This lets the slow hashing
+    /// function run on a separate thread, such as the blocking thread pool of
+    /// an async runtime.
+    ///
+    /// # Arguments
+    /// * `message` - the server's answer to the initial login attempt
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use opaque_ke::{ClientLogin, ClientLoginStartParameters, ClientLoginFinishParameters, ServerLogin, ServerLoginStartParameters};
+    /// # use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, OprfSeed, ServerRegistration};
+    /// # use opaque_ke::errors::ProtocolError;
+    /// use rand::{rngs::OsRng, RngCore};
+    /// use opaque_ke::ciphersuite::CipherSuite;
+    /// struct Default;
+    /// impl CipherSuite for Default {
+    ///     type Group = curve25519_dalek::ristretto::RistrettoPoint;
+    ///     type KeyExchange = opaque_ke::key_exchange::tripledh::TripleDH;
+    ///     type Hash = sha2::Sha512;
+    ///     type SlowHash = opaque_ke::slow_hash::NoOpHash;
+    /// }
+    /// let mut client_rng = OsRng;
+    /// # let mut server_rng = OsRng;
+    /// # let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
+    /// # let server_kp = Default::generate_random_keypair(&mut server_rng);
+    /// # let oprf_seed = OprfSeed::<Default>::generate_random(&mut server_rng);
+    /// # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
+    /// # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
+    /// # let p_file = server_registration_start_result.state.finish(client_registration_finish_result.message)?;
+    /// let client_login_start_result = ClientLogin::<Default>::start(&mut client_rng, b"hunter2", ClientLoginStartParameters::default())?;
+    /// let server_login_start_result = ServerLogin::start(&mut server_rng, Some(p_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
+    /// let oprf_result = client_login_start_result.state.finish_oprf(server_login_start_result.message, ClientLoginFinishParameters::default())?;
+    /// let key_stretch = oprf_result.key_stretch;
+    /// let stretched_key = std::thread::spawn(move || key_stretch.compute()).join().unwrap()?;
+    /// let client_login_finish_result = oprf_result.state.finish(stretched_key)?;
+    /// # Ok::<(), ProtocolError>(())
+    /// ```
+    pub fn finish_oprf(
+        self,
+        l2: CredentialResponse<CS>,
+        params: ClientLoginFinishParameters<CS::SlowHash>,
+    ) -> Result<ClientLoginOprfResult<CS>, ProtocolError> {
+        let ClientLoginFinishParameters {
+            identifiers: optional_ids,
+            slow_hash,
+            minimum_slow_hash,
+            maximum_slow_hash,
+        } = params;
+
+        let slow_hash = match l2.envelope.get_ksf_parameters() {
+            // The stored parameters are only authenticated once the envelope
+            // is opened, which requires running the slow hashing function on
+            // them, so they are checked against the minimum and the maximum
+            // beforehand. The minimum is the supplied minimum, or else the
+            // supplied instance, or else the recommended instance.
+            Some(ksf_parameters) => {
+                let stored = slow_hash::deserialize_parameter_block::<CS::Hash, CS::SlowHash>(
+                    ksf_parameters,
+                )?;
+                let maximum_slow_hash = maximum_slow_hash.unwrap_or_else(CS::SlowHash::maximum);
+                if !stored.within_maximum(&maximum_slow_hash) {
+                    return Err(InternalPakeError::ExpensiveSlowHashParametersError.into());
+                }
+                let minimum_slow_hash = minimum_slow_hash
+                    .or(slow_hash)
+                    .unwrap_or_else(CS::SlowHash::recommended);
+                if !stored.meets_minimum(&minimum_slow_hash) {
+                    return Err(InternalPakeError::WeakSlowHashParametersError.into());
+                }
+                stored
+            }
+            None => slow_hash.unwrap_or_else(CS::SlowHash::recommended),
         };
 
-        let server_s_pk_bytes = l2.server_s_pk.to_arr().to_vec();
+        Ok(ClientLoginOprfResult {
+            key_stretch: KeyStretch::new(&self.token, l2.beta, slow_hash),
+            state: ClientLoginPending {
+                login: self,
+                credential_response: l2,
+                optional_ids,
+            },
+        })
+    }
+}
+
+impl<CS: CipherSuite> ClientLoginPending<CS> {
+    /// The second half of [ClientLogin::finish], which opens the envelope
+    /// with the output of [KeyStretch::compute] and returns the final
+    /// message, to be sent to the server
+    ///
+    /// # Arguments
+    /// * `stretched_key` - the output of the slow hashing computation
+    pub fn finish(
+        self,
+        stretched_key: StretchedKey<CS>,
+    ) -> Result<ClientLoginFinishResult<CS>, ProtocolError> {
+        self.finish_with_bound_data(stretched_key, &[])
+    }
 
-        let password_derived_key =
-            get_password_derived_key::<CS::Group, CS::SlowHash, CS::Hash>(&self.token, l2.beta)?;
+    // Completes a login whose key exchange also authenticates `bound_data`,
+    // which the server supplied to [ServerLogin::start_with_bound_data]
+    fn finish_with_bound_data(
+        self,
+        stretched_key: StretchedKey<CS>,
+        bound_data: &[u8],
+    ) -> Result<ClientLoginFinishResult<CS>, ProtocolError> {
+        let l2 = self.credential_response;
+        let optional_ids = self.optional_ids;
         let opened_envelope = &l2
             .envelope
-            .open(&password_derived_key, &server_s_pk_bytes, &optional_ids)
+            .open(&stretched_key.key, &l2.server_s_pk, &optional_ids)
             .map_err(|e| match e {
                 InternalPakeError::SealOpenHmacError => PakeError::InvalidLoginError,
                 err => PakeError::from(err),
             })?;
 
-        let client_s_sk = Key::from_bytes(&opened_envelope.client_s_sk)?;
+        let client_s_sk = opened_envelope.client_s_sk.clone();
+        let client_static_keypair = match l2.envelope.get_mode() {
+            InnerEnvelopeMode::External => Some(KeyPair::new(
+                KeyPair::<CS::Group>::public_from_private(&client_s_sk),
+                client_s_sk.clone(),
+            )?),
+            InnerEnvelopeMode::Base | InnerEnvelopeMode::Internal => None,
+        };
 
         let (id_u, id_s) = match optional_ids {
             None => (
                 KeyPair::<CS::Group>::public_from_private(&client_s_sk)
                     .to_arr()
                     .to_vec(),
-                server_s_pk_bytes,
+                l2.server_s_pk.to_arr().to_vec(),
             ),
             Some((id_u, id_s)) => (id_u, id_s),
         };
 
-        let credential_response_component =
-            CredentialResponse::<CS>::serialize_without_ke(&l2.beta, &l2.server_s_pk, &l2.envelope);
+        let credential_response_component = [
+            CredentialResponse::<CS>::serialize_without_ke(&l2.beta, &l2.server_s_pk, &l2.envelope),
+            bound_data.to_vec(),
+        ]
+        .concat();
 
         let (confidential_info, session_key, ke3_message) = CS::KeyExchange::generate_ke3(
             credential_response_component,
             l2.ke2_message,
-            &self.ke1_state,
-            &self.serialized_credential_request,
+            &self.login.ke1_state,
+            &self.login.serialized_credential_request,
             l2.server_s_pk.clone(),
             client_s_sk,
             id_u,
             id_s,
+            self.login.context.clone(),
         )?;
 
         Ok(ClientLoginFinishResult {
@@ -626,6 +1280,9 @@ impl<CS: CipherSuite> ClientLogin<CS> {
             session_key,
             export_key: opened_envelope.export_key.clone(),
             server_s_pk: l2.server_s_pk,
+            client_static_keypair,
+            secret: opened_envelope.secret.clone(),
+            reseal: l2.reseal,
         })
     }
 }
@@ -637,20 +1294,37 @@ pub struct ServerLogin<CS: CipherSuite> {
 }
 
 /// Optional parameters for server login start
-pub enum ServerLoginStartParameters {
-    /// Specifying a confidential info field that will be sent to the client
-    WithInfo(Vec<u8>),
-    /// Specifying a user identifier and server identifier that will be matched against the client
-    WithIdentifiers(Vec<u8>, Vec<u8>),
-    /// Specifying a confidential info field that will be sent to the client,
-    /// along with a user identifier and and server identifier that will be matched against the client
-    /// (in that order)
-    WithInfoAndIdentifiers(Vec<u8>, Vec<u8>, Vec<u8>),
-}
-
-impl Default for ServerLoginStartParameters {
-    fn default() -> Self {
-        Self::WithInfo(Vec::new())
+#[derive(Default)]
+pub struct ServerLoginStartParameters {
+    info: Option<Vec<u8>>,
+    identifiers: Option<(Vec<u8>, Vec<u8>)>,
+    context: Option<Vec<u8>>,
+}
+
+impl ServerLoginStartParameters {
+    /// Specifies a confidential info field that will be sent to the client
+    pub fn with_info(mut self, info: Vec<u8>) -> Self {
+        self.info = Some(info);
+        self
+    }
+
+    /// Specifies a user identifier and server identifier that will be matched against the client
+    pub fn with_identifiers(mut self, id_u: Vec<u8>, id_s: Vec<u8>) -> Self {
+        self.identifiers = Some((id_u, id_s));
+        self
+    }
+
+    /// Specifies an application context of at most [MAX_CONTEXT_LEN](crate::MAX_CONTEXT_LEN) bytes
+    /// that is bound to the login and must match the context supplied to the
+    /// client
+    pub fn with_context(mut self, context: Vec<u8>) -> Self {
+        self.context = Some(context);
+        self
+    }
+
+    // The mode of the envelope which these parameters expect to open
+    fn envelope_mode(&self) -> IdentifierMode {
+        mode_from_ids(&self.identifiers)
     }
 }
 
@@ -662,6 +1336,9 @@ pub struct ServerLoginStartResult<CS: CipherSuite> {
     pub state: ServerLogin<CS>,
     /// The plaintext info sent by the client
     pub plain_info: Vec<u8>,
+    /// Whether the client was asked to re-seal its envelope under the
+    /// current key pair of the server's key set
+    pub reseal: bool,
 }
 
 /// Contains the fields that are returned by a server login finish
@@ -690,13 +1367,17 @@ impl<CS: CipherSuite> ServerLogin<CS> {
     /// sent back to the client, as well as a ServerLogin
     ///
     /// # Arguments
-    /// * `message`   - the initial registration message
+    /// * `password_file` - the user's password file, or `None` if the user has not registered,
+    ///   in which case a fake response is returned and the login fails in [ServerLogin::finish]
+    /// * `oprf_seed` - the server's OPRF seed, from which the user's OPRF key is derived
+    /// * `message`   - the initial login message
+    /// * `credential_identifier` - the identifier the user registered under
     ///
     /// # Example
     ///
     /// ```
     /// use opaque_ke::{ClientLogin, ClientLoginStartParameters, ServerLogin, ServerLoginStartParameters};
-    /// # use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, ServerRegistration};
+    /// # use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, OprfSeed, ServerRegistration};
     /// # use opaque_ke::errors::ProtocolError;
     /// # use opaque_ke::keypair::KeyPair;
     /// use rand::{rngs::OsRng, RngCore};
@@ -711,37 +1392,189 @@ impl<CS: CipherSuite> ServerLogin<CS> {
     /// let mut client_rng = OsRng;
     /// let mut server_rng = OsRng;
     /// let server_kp = Default::generate_random_keypair(&mut server_rng);
+    /// let oprf_seed = OprfSeed::<Default>::generate_random(&mut server_rng);
     /// # let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
-    /// # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+    /// # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
     /// # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
     /// # let p_file = server_registration_start_result.state.finish(client_registration_finish_result.message)?;
     /// let client_login_start_result = ClientLogin::<Default>::start(&mut client_rng, b"hunter2", ClientLoginStartParameters::default())?;
-    /// let server_login_start_result = ServerLogin::start(&mut server_rng, p_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::default())?;
+    /// let server_login_start_result = ServerLogin::start(&mut server_rng, Some(p_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
     /// # Ok::<(), ProtocolError>(())
     /// ```
     pub fn start<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        password_file: Option<ServerRegistration<CS>>,
+        server_s_sk: &PrivateKey<CS::Group>,
+        oprf_seed: &OprfSeed<CS>,
+        l1: CredentialRequest<CS>,
+        credential_identifier: &[u8],
+        params: ServerLoginStartParameters,
+    ) -> Result<ServerLoginStartResult<CS>, ProtocolError> {
+        Self::start_from_seed(
+            rng,
+            password_file,
+            server_s_sk,
+            oprf_seed,
+            l1,
+            credential_identifier,
+            params,
+            &[],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn start_from_seed<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        password_file: Option<ServerRegistration<CS>>,
+        server_s_sk: &PrivateKey<CS::Group>,
+        oprf_seed: &OprfSeed<CS>,
+        l1: CredentialRequest<CS>,
+        credential_identifier: &[u8],
+        params: ServerLoginStartParameters,
+        bound_data: &[u8],
+    ) -> Result<ServerLoginStartResult<CS>, ProtocolError> {
+        let oprf_key = oprf_seed.derive_oprf_key(credential_identifier)?;
+        let password_file = match password_file {
+            Some(password_file) => password_file,
+            None => {
+                ServerRegistration::fake(oprf_seed, credential_identifier, params.envelope_mode())?
+            }
+        };
+        Self::start_with_bound_data(
+            rng,
+            password_file,
+            server_s_sk,
+            &oprf_key,
+            l1,
+            params,
+            false,
+            bound_data,
+        )
+    }
+
+    /// Same as [ServerLogin::start], with the server's key pair taken from a
+    /// key set, as the one which the password file was sealed under. If the
+    /// key set requests it, and the password file was sealed under a previous
+    /// key pair, the client is asked to re-seal its envelope under the
+    /// current one.
+    ///
+    /// # Arguments
+    /// * `password_file` - the user's password file, or `None` if the user has not registered,
+    ///   in which case a fake response is returned under the current key pair
+    /// * `server_keys` - the server's key set
+    /// * `oprf_seed` - the server's OPRF seed, from which the user's OPRF key is derived
+    /// * `message`   - the initial login message
+    /// * `credential_identifier` - the identifier the user registered under
+    pub fn start_with_key_set<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        password_file: Option<ServerRegistration<CS>>,
+        server_keys: &ServerKeySet<CS>,
+        oprf_seed: &OprfSeed<CS>,
+        l1: CredentialRequest<CS>,
+        credential_identifier: &[u8],
+        params: ServerLoginStartParameters,
+    ) -> Result<ServerLoginStartResult<CS>, ProtocolError> {
+        Self::start_from_key_set(
+            rng,
+            password_file,
+            server_keys,
+            oprf_seed,
+            l1,
+            credential_identifier,
+            params,
+            server_keys.request_reseal,
+            &[],
+        )
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn start_from_key_set<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        password_file: Option<ServerRegistration<CS>>,
+        server_keys: &ServerKeySet<CS>,
+        oprf_seed: &OprfSeed<CS>,
+        l1: CredentialRequest<CS>,
+        credential_identifier: &[u8],
+        params: ServerLoginStartParameters,
+        request_reseal: bool,
+        bound_data: &[u8],
+    ) -> Result<ServerLoginStartResult<CS>, ProtocolError> {
+        let oprf_key = oprf_seed.derive_oprf_key(credential_identifier)?;
+        let (password_file, key_id) = match password_file {
+            Some(password_file) => {
+                let key_id = password_file.key_id.unwrap_or(LEGACY_SERVER_KEY_ID);
+                (password_file, key_id)
+            }
+            None => (
+                ServerRegistration::fake(oprf_seed, credential_identifier, params.envelope_mode())?,
+                server_keys.current_key_id(),
+            ),
+        };
+        let server_s_kp = server_keys
+            .get(key_id)
+            .ok_or(PakeError::UnknownServerKeyError)?;
+        let reseal = request_reseal && key_id != server_keys.current_key_id();
+        Self::start_with_bound_data(
+            rng,
+            password_file,
+            server_s_kp.private(),
+            &oprf_key,
+            l1,
+            params,
+            reseal,
+            bound_data,
+        )
+    }
+
+    // Login start with an OPRF key supplied directly rather than derived from
+    // a seed, as in the test vectors, which never asks the client to re-seal
+    #[cfg(test)]
+    pub(crate) fn start_with_oprf_key<R: RngCore + CryptoRng>(
         rng: &mut R,
         password_file: ServerRegistration<CS>,
-        server_s_sk: &Key,
+        server_s_sk: &PrivateKey<CS::Group>,
+        oprf_key: &<CS::Group as Group>::Scalar,
         l1: CredentialRequest<CS>,
         params: ServerLoginStartParameters,
+    ) -> Result<ServerLoginStartResult<CS>, ProtocolError> {
+        let reseal = false;
+        Self::start_with_bound_data(
+            rng,
+            password_file,
+            server_s_sk,
+            oprf_key,
+            l1,
+            params,
+            reseal,
+            &[],
+        )
+    }
+
+    // Login start in which the key exchange also authenticates `bound_data`,
+    // which the client must supply in turn to
+    // [ClientLoginPending::finish_with_bound_data]
+    #[allow(clippy::too_many_arguments)]
+    fn start_with_bound_data<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        password_file: ServerRegistration<CS>,
+        server_s_sk: &PrivateKey<CS::Group>,
+        oprf_key: &<CS::Group as Group>::Scalar,
+        l1: CredentialRequest<CS>,
+        params: ServerLoginStartParameters,
+        reseal: bool,
+        bound_data: &[u8],
     ) -> Result<ServerLoginStartResult<CS>, ProtocolError> {
         let client_s_pk = password_file
             .client_s_pk
             .ok_or(InternalPakeError::SealError)?;
 
-        let (e_info, optional_ids) = match params {
-            ServerLoginStartParameters::WithInfo(e_info) => (e_info, None),
-            ServerLoginStartParameters::WithIdentifiers(id_u, id_s) => {
-                (Vec::new(), Some((id_u, id_s)))
-            }
-            ServerLoginStartParameters::WithInfoAndIdentifiers(e_info, id_u, id_s) => {
-                (e_info, Some((id_u, id_s)))
-            }
-        };
+        let e_info = params.info.unwrap_or_default();
+        let optional_ids = params.identifiers;
+        let context = params.context.unwrap_or_default();
+        check_context_len(&context)?;
 
         let envelope = password_file.envelope.ok_or(InternalPakeError::SealError)?;
-        if envelope.get_mode() != mode_from_ids(&optional_ids) {
+        if envelope.get_identifier_mode() != mode_from_ids(&optional_ids) {
             return Err(InternalPakeError::IncompatibleEnvelopeModeError.into());
         }
 
@@ -756,11 +1589,14 @@ impl<CS: CipherSuite> ServerLogin<CS> {
         };
 
         let l1_bytes = &l1.serialize();
-        let beta = oprf::evaluate(l1.alpha, &password_file.oprf_key);
-        let server_s_pk = KeyPair::<CS::Group>::public_from_private(&server_s_sk);
+        let beta = oprf::evaluate(l1.alpha, oprf_key);
+        let server_s_pk = KeyPair::<CS::Group>::public_from_private(server_s_sk);
 
-        let credential_response_component =
-            CredentialResponse::<CS>::serialize_without_ke(&beta, &server_s_pk, &envelope);
+        let credential_response_component = [
+            CredentialResponse::<CS>::serialize_without_ke(&beta, &server_s_pk, &envelope),
+            bound_data.to_vec(),
+        ]
+        .concat();
 
         let (plain_info, ke2_state, ke2_message) = CS::KeyExchange::generate_ke2(
             rng,
@@ -772,17 +1608,20 @@ impl<CS: CipherSuite> ServerLogin<CS> {
             id_u,
             id_s,
             e_info,
+            context,
         )?;
 
         let credential_response = CredentialResponse {
             beta,
             server_s_pk,
             envelope,
+            reseal,
             ke2_message,
         };
 
         Ok(ServerLoginStartResult {
             plain_info,
+            reseal,
             message: credential_response,
             state: Self {
                 _cs: PhantomData,
@@ -801,7 +1640,7 @@ impl<CS: CipherSuite> ServerLogin<CS> {
     ///
     /// ```
     /// use opaque_ke::{ClientLogin, ClientLoginFinishParameters, ClientLoginStartParameters, ServerLogin, ServerLoginStartParameters};
-    /// # use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, ServerRegistration};
+    /// # use opaque_ke::{ClientRegistration, ClientRegistrationFinishParameters, OprfSeed, ServerRegistration};
     /// # use opaque_ke::errors::ProtocolError;
     /// # use opaque_ke::keypair::KeyPair;
     /// use rand::{rngs::OsRng, RngCore};
@@ -816,12 +1655,13 @@ impl<CS: CipherSuite> ServerLogin<CS> {
     /// let mut client_rng = OsRng;
     /// let mut server_rng = OsRng;
     /// let server_kp = Default::generate_random_keypair(&mut server_rng);
+    /// let oprf_seed = OprfSeed::<Default>::generate_random(&mut server_rng);
     /// # let client_registration_start_result = ClientRegistration::<Default>::start(&mut client_rng, b"hunter2")?;
-    /// # let server_registration_start_result = ServerRegistration::<Default>::start(&mut server_rng, client_registration_start_result.message, server_kp.public())?;
+    /// # let server_registration_start_result = ServerRegistration::<Default>::start(&oprf_seed, client_registration_start_result.message, server_kp.public(), b"username")?;
     /// # let client_registration_finish_result = client_registration_start_result.state.finish(&mut client_rng, server_registration_start_result.message, ClientRegistrationFinishParameters::default())?;
     /// # let p_file = server_registration_start_result.state.finish(client_registration_finish_result.message)?;
     /// let client_login_start_result = ClientLogin::<Default>::start(&mut client_rng, b"hunter2", ClientLoginStartParameters::default())?;
-    /// let server_login_start_result = ServerLogin::start(&mut server_rng, p_file, &server_kp.private(), client_login_start_result.message, ServerLoginStartParameters::default())?;
+    /// let server_login_start_result = ServerLogin::start(&mut server_rng, Some(p_file), &server_kp.private(), &oprf_seed, client_login_start_result.message, b"username", ServerLoginStartParameters::default())?;
     /// let client_login_finish_result = client_login_start_result.state.finish(server_login_start_result.message, ClientLoginFinishParameters::default())?;
     /// let mut server_transport = server_login_start_result.state.finish(client_login_finish_result.message)?;
     /// # Ok::<(), ProtocolError>(())
@@ -845,11 +1685,390 @@ impl<CS: CipherSuite> ServerLogin<CS> {
     }
 }
 
-// Helper functions
-fn get_password_derived_key<G: GroupWithMapToCurve, SH: SlowHash<D>, D: Hash>(
-    token: &oprf::Token<G>,
-    beta: G,
-) -> Result<Vec<u8>, InternalPakeError> {
-    let oprf_output = oprf::finalize::<G, D>(&token.data, &token.blind, beta);
-    SH::hash(oprf_output)
+// Password change
+// ===============
+
+/// The state elements the client holds to change its password, which
+/// combine a login under the old password with a registration under the new
+/// one
+pub struct ClientPasswordChange<CS: CipherSuite> {
+    login: ClientLogin<CS>,
+    registration: ClientRegistration<CS>,
+    serialized_registration_request: Vec<u8>,
+}
+
+/// Contains the fields that are returned by a client password change start
+pub struct ClientPasswordChangeStartResult<CS: CipherSuite> {
+    /// The message to send to the server to begin the password change
+    pub message: PasswordChangeRequest<CS>,
+    /// The state that the client must keep in order to complete the protocol
+    pub state: ClientPasswordChange<CS>,
+}
+
+/// Contains the fields that are returned by a client password change finish
+pub struct ClientPasswordChangeFinishResult<CS: CipherSuite> {
+    /// The message to send to the server to complete the protocol
+    pub message: PasswordChangeFinalization<CS>,
+    /// The session key of the login under the old password
+    pub session_key: Vec<u8>,
+    /// The client-side export key under the new password
+    pub export_key: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    /// The server's static public key
+    pub server_s_pk: PublicKey<CS::Group>,
+}
+
+impl<CS: CipherSuite> ClientPasswordChange<CS> {
+    /// Returns an initial message which blinds both the old and the new
+    /// password, to send to the server, as well as a ClientPasswordChange
+    ///
+    /// # Arguments
+    /// * `old_password` - The password the user is registered with
+    /// * `new_password` - The password to register instead
+    /// * `params` - Optional parameters for the login under the old password
+    pub fn start<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        old_password: &[u8],
+        new_password: &[u8],
+        params: ClientLoginStartParameters,
+    ) -> Result<ClientPasswordChangeStartResult<CS>, ProtocolError> {
+        let login_start_result = ClientLogin::<CS>::start(rng, old_password, params)?;
+        let registration_start_result = ClientRegistration::<CS>::start(rng, new_password)?;
+        let serialized_registration_request = registration_start_result.message.serialize();
+
+        Ok(ClientPasswordChangeStartResult {
+            message: PasswordChangeRequest {
+                registration_request: registration_start_result.message,
+                credential_request: login_start_result.message,
+            },
+            state: Self {
+                login: login_start_result.state,
+                registration: registration_start_result.state,
+                serialized_registration_request,
+            },
+        })
+    }
+
+    /// Completes the login under the old password, and seals a new envelope
+    /// under the new password, returning the final message to send to the
+    /// server. Note that in `InnerEnvelopeMode::External`, the client's key
+    /// pair must be supplied again through `registration_params`.
+    ///
+    /// # Arguments
+    /// * `message` - the server's answer to the password change request
+    /// * `login_params` - optional parameters for the login under the old password
+    /// * `registration_params` - optional parameters for the registration under the new password
+    pub fn finish<R: RngCore + CryptoRng>(
+        self,
+        rng: &mut R,
+        message: PasswordChangeResponse<CS>,
+        login_params: ClientLoginFinishParameters<CS::SlowHash>,
+        registration_params: ClientRegistrationFinishParameters<CS>,
+    ) -> Result<ClientPasswordChangeFinishResult<CS>, ProtocolError> {
+        let PasswordChangeResponse {
+            registration_response,
+            credential_response,
+        } = message;
+        // The new envelope is sealed under a different server key only when
+        // the server asks for it to be re-sealed
+        if !credential_response.reseal
+            && registration_response.server_s_pk != credential_response.server_s_pk
+        {
+            return Err(PakeError::IncompatibleServerStaticPublicKeyError.into());
+        }
+        let serialized_registration_response = registration_response.serialize();
+
+        // The key exchange authenticates the registration response, so that
+        // the new envelope is only sealed under a server static public key
+        // which the server vouched for in this session
+        let ClientLoginOprfResult { key_stretch, state } =
+            self.login.finish_oprf(credential_response, login_params)?;
+        let login_finish_result = state.finish_with_bound_data(
+            key_stretch.compute()?,
+            &[
+                &self.serialized_registration_request[..],
+                &serialized_registration_response[..],
+            ]
+            .concat(),
+        )?;
+        let registration_finish_result =
+            self.registration
+                .finish(rng, registration_response, registration_params)?;
+
+        let mac = password_change_mac::<CS>(
+            &login_finish_result.session_key,
+            &self.serialized_registration_request,
+            &serialized_registration_response,
+            &registration_finish_result.message,
+        )?
+        .finalize()
+        .into_bytes();
+
+        Ok(ClientPasswordChangeFinishResult {
+            message: PasswordChangeFinalization {
+                credential_finalization: login_finish_result.message,
+                registration_upload: registration_finish_result.message,
+                mac,
+            },
+            session_key: login_finish_result.session_key,
+            export_key: registration_finish_result.export_key,
+            server_s_pk: login_finish_result.server_s_pk,
+        })
+    }
+}
+
+/// The state elements the server holds to record a password change
+pub struct ServerPasswordChange<CS: CipherSuite> {
+    login: ServerLogin<CS>,
+    registration: ServerRegistration<CS>,
+    serialized_registration_request: Vec<u8>,
+    serialized_registration_response: Vec<u8>,
+}
+
+/// Contains the fields that are returned by a server password change start
+pub struct ServerPasswordChangeStartResult<CS: CipherSuite> {
+    /// The message to send back to the client
+    pub message: PasswordChangeResponse<CS>,
+    /// The state that the server must keep in order to finish the protocol
+    pub state: ServerPasswordChange<CS>,
+    /// The plaintext info sent by the client
+    pub plain_info: Vec<u8>,
+}
+
+/// Contains the fields that are returned by a server password change finish
+pub struct ServerPasswordChangeFinishResult<CS: CipherSuite> {
+    /// The session key between client and server
+    pub session_key: Vec<u8>,
+    /// The user's new password file, which replaces the old one
+    pub password_file: ServerRegistration<CS>,
+}
+
+impl<CS: CipherSuite> ServerPasswordChange<CS> {
+    /// From the client's blinded passwords, returns a response to be sent
+    /// back to the client, as well as a ServerPasswordChange
+    ///
+    /// # Arguments
+    /// * `password_file` - the user's current password file, or `None` if the user has not
+    ///   registered, in which case the password change fails in [ServerPasswordChange::finish]
+    /// * `oprf_seed` - the server's OPRF seed, from which the user's OPRF key is derived
+    /// * `message`   - the initial password change message
+    /// * `credential_identifier` - the identifier the user registered under
+    /// * `params` - optional parameters for the login under the old password
+    pub fn start<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        password_file: Option<ServerRegistration<CS>>,
+        server_s_sk: &PrivateKey<CS::Group>,
+        oprf_seed: &OprfSeed<CS>,
+        message: PasswordChangeRequest<CS>,
+        credential_identifier: &[u8],
+        params: ServerLoginStartParameters,
+    ) -> Result<ServerPasswordChangeStartResult<CS>, ProtocolError> {
+        let serialized_registration_request = message.registration_request.serialize();
+        let server_s_pk = KeyPair::<CS::Group>::public_from_private(server_s_sk);
+        let registration_start_result = ServerRegistration::<CS>::start(
+            oprf_seed,
+            message.registration_request,
+            &server_s_pk,
+            credential_identifier,
+        )?;
+        let login_start_result = ServerLogin::<CS>::start_from_seed(
+            rng,
+            password_file,
+            server_s_sk,
+            oprf_seed,
+            message.credential_request,
+            credential_identifier,
+            params,
+            &Self::bound_data(&serialized_registration_request, &registration_start_result),
+        )?;
+
+        Ok(Self::start_result(
+            serialized_registration_request,
+            registration_start_result,
+            login_start_result,
+        ))
+    }
+
+    /// Same as [ServerPasswordChange::start], with the server's key pairs
+    /// taken from a key set. The login uses the key pair which the current
+    /// password file was sealed under, and the new password file is sealed
+    /// under the current key pair, as in [ServerRegistration::start_with_key_set].
+    /// If these differ, the client is asked to re-seal its envelope
+    /// regardless of [ServerKeySet::set_request_reseal], which makes a
+    /// password change to the same password the way to re-seal an envelope.
+    pub fn start_with_key_set<R: RngCore + CryptoRng>(
+        rng: &mut R,
+        password_file: Option<ServerRegistration<CS>>,
+        server_keys: &ServerKeySet<CS>,
+        oprf_seed: &OprfSeed<CS>,
+        message: PasswordChangeRequest<CS>,
+        credential_identifier: &[u8],
+        params: ServerLoginStartParameters,
+    ) -> Result<ServerPasswordChangeStartResult<CS>, ProtocolError> {
+        let serialized_registration_request = message.registration_request.serialize();
+        let registration_start_result = ServerRegistration::<CS>::start_with_key_set(
+            oprf_seed,
+            message.registration_request,
+            server_keys,
+            credential_identifier,
+        )?;
+        let login_start_result = ServerLogin::<CS>::start_from_key_set(
+            rng,
+            password_file,
+            server_keys,
+            oprf_seed,
+            message.credential_request,
+            credential_identifier,
+            params,
+            true,
+            &Self::bound_data(&serialized_registration_request, &registration_start_result),
+        )?;
+
+        Ok(Self::start_result(
+            serialized_registration_request,
+            registration_start_result,
+            login_start_result,
+        ))
+    }
+
+    // The registration messages, which the key exchange of the login under
+    // the old password authenticates to the client
+    fn bound_data(
+        serialized_registration_request: &[u8],
+        registration_start_result: &ServerRegistrationStartResult<CS>,
+    ) -> Vec<u8> {
+        [
+            serialized_registration_request,
+            &registration_start_result.message.serialize()[..],
+        ]
+        .concat()
+    }
+
+    fn start_result(
+        serialized_registration_request: Vec<u8>,
+        registration_start_result: ServerRegistrationStartResult<CS>,
+        login_start_result: ServerLoginStartResult<CS>,
+    ) -> ServerPasswordChangeStartResult<CS> {
+        ServerPasswordChangeStartResult {
+            state: Self {
+                login: login_start_result.state,
+                registration: registration_start_result.state,
+                serialized_registration_request,
+                serialized_registration_response: registration_start_result.message.serialize(),
+            },
+            message: PasswordChangeResponse {
+                registration_response: registration_start_result.message,
+                credential_response: login_start_result.message,
+            },
+            plain_info: login_start_result.plain_info,
+        }
+    }
+
+    /// From the client's final message, checks the client's authentication
+    /// under the old password, and only then returns the new password file.
+    /// The server should replace the user's password file with it only once
+    /// this succeeds.
+    ///
+    /// # Arguments
+    /// * `message` - the client's final password change message
+    pub fn finish(
+        self,
+        message: PasswordChangeFinalization<CS>,
+    ) -> Result<ServerPasswordChangeFinishResult<CS>, ProtocolError> {
+        let login_finish_result = self.login.finish(message.credential_finalization)?;
+
+        password_change_mac::<CS>(
+            &login_finish_result.session_key,
+            &self.serialized_registration_request,
+            &self.serialized_registration_response,
+            &message.registration_upload,
+        )?
+        .verify(&message.mac)
+        .map_err(|_| PakeError::KeyExchangeMacValidationError)?;
+
+        Ok(ServerPasswordChangeFinishResult {
+            session_key: login_finish_result.session_key,
+            password_file: self.registration.finish(message.registration_upload)?,
+        })
+    }
+}
+
+// The MAC which binds the registration under the new password to the
+// session established under the old one
+fn password_change_mac<CS: CipherSuite>(
+    session_key: &[u8],
+    serialized_registration_request: &[u8],
+    serialized_registration_response: &[u8],
+    registration_upload: &RegistrationUpload<CS>,
+) -> Result<Hmac<CS::Hash>, InternalPakeError> {
+    let mut mac_key = vec![0u8; <CS::Hash as Digest>::OutputSize::to_usize()];
+    Hkdf::<CS::Hash>::new(None, session_key)
+        .expand(STR_PASSWORD_CHANGE, &mut mac_key)
+        .map_err(|_| InternalPakeError::HkdfError)?;
+    let mut mac =
+        Hmac::<CS::Hash>::new_varkey(&mac_key).map_err(|_| InternalPakeError::HmacError)?;
+    mac_key.zeroize();
+    mac.update(serialized_registration_request);
+    mac.update(serialized_registration_response);
+    mac.update(&registration_upload.serialize());
+    Ok(mac)
+}
+
+// Key stretching
+// ==============
+
+/// The computation of the slow hashing function over the OPRF output, split
+/// out of [ClientRegistration::finish] and [ClientLogin::finish] so that it
+/// can be run on a separate thread
+pub struct KeyStretch<CS: CipherSuite> {
+    oprf_output: GenericArray<u8, <CS::Hash as Digest>::OutputSize>,
+    slow_hash: CS::SlowHash,
+}
+
+/// The password-derived key output by [KeyStretch::compute]
+pub struct StretchedKey<CS: CipherSuite> {
+    key: Vec<u8>,
+    _cs: PhantomData<CS>,
+}
+
+impl<CS: CipherSuite> KeyStretch<CS> {
+    fn new(token: &oprf::Token<CS::Group>, beta: CS::Group, slow_hash: CS::SlowHash) -> Self {
+        Self {
+            oprf_output: oprf::finalize::<CS::Group, CS::Hash>(&token.data, &token.blind, beta),
+            slow_hash,
+        }
+    }
+
+    /// Computes the slow hashing function over the OPRF output
+    pub fn compute(self) -> Result<StretchedKey<CS>, ProtocolError> {
+        Ok(StretchedKey {
+            key: self.slow_hash.hash(self.oprf_output.clone())?,
+            _cs: PhantomData,
+        })
+    }
+}
+
+// This can't be derived because of the use of a phantom parameter
+impl<CS: CipherSuite> Zeroize for KeyStretch<CS> {
+    fn zeroize(&mut self) {
+        self.oprf_output.as_mut_slice().zeroize();
+    }
+}
+
+impl<CS: CipherSuite> Drop for KeyStretch<CS> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+// This can't be derived because of the use of a phantom parameter
+impl<CS: CipherSuite> Zeroize for StretchedKey<CS> {
+    fn zeroize(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+impl<CS: CipherSuite> Drop for StretchedKey<CS> {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
 }
@@ -3,23 +3,108 @@
 // This source code is licensed under the MIT license found in the
 // LICENSE file in the root directory of this source tree.
 
+//! A standalone implementation of the DH-OPRF from
+//! <https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-06.txt>, in both its
+//! base and verifiable modes, which OPAQUE uses to derive the randomized
+//! password. The partially-oblivious mode, in which the output is bound to
+//! public info, follows <https://www.rfc-editor.org/rfc/rfc9497>, and is only
+//! available for the (group, hash) pairs which form one of its ciphersuites,
+//! as given by [PoprfSuite]. It can also be used on its own, e.g. for
+//! privacy-preserving lookups:
+//!
+//! ```
+//! # use opaque_ke::errors::InternalPakeError;
+//! use curve25519_dalek::ristretto::RistrettoPoint;
+//! use opaque_ke::oprf::{BlindedElement, EvaluationElement, OprfClient, OprfServer};
+//! use rand::rngs::OsRng;
+//! use sha2::Sha512;
+//!
+//! let mut rng = OsRng;
+//! let server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+//!
+//! let (client, blinded_element) =
+//!     OprfClient::<RistrettoPoint, Sha512>::blind(b"hunter2", &mut rng)?;
+//! let message = blinded_element.serialize();
+//!
+//! let evaluation_element = server.evaluate(&BlindedElement::deserialize(&message)?);
+//! let message = evaluation_element.serialize();
+//!
+//! let output = client.finalize(&EvaluationElement::deserialize(&message)?)?;
+//! # Ok::<(), InternalPakeError>(())
+//! ```
+
 use crate::{
-    errors::InternalPakeError, group::Group, hash::Hash, map_to_curve::GroupWithMapToCurve,
-    serialization::serialize,
+    errors::{utils::check_slice_size, InternalPakeError},
+    group::Group,
+    hash::Hash,
+    keypair::{PrivateKey, PublicKey},
+    map_to_curve::{GroupWithMapToCurve, PoprfSuite},
+    serialization::{i2osp, serialize},
 };
 use digest::Digest;
-use generic_array::GenericArray;
+use generic_array::{typenum::Unsigned, GenericArray};
+use generic_bytes::SizedBytes;
 use rand::{CryptoRng, RngCore};
+use std::marker::PhantomData;
 
 /// Used to store the OPRF input and blinding factor
-pub struct Token<Grp: Group> {
+pub(crate) struct Token<Grp: Group> {
     pub(crate) data: Vec<u8>,
     pub(crate) blind: Grp::Scalar,
 }
 
+/// A Chaum-Pedersen discrete log equality proof, attached by the server to a
+/// verifiable OPRF evaluation, that the evaluated element was computed with
+/// the OPRF key corresponding to the server's OPRF public key
+pub struct Proof<Grp: Group> {
+    pub(crate) c: Grp::Scalar,
+    pub(crate) s: Grp::Scalar,
+}
+
+impl<Grp: Group> Clone for Proof<Grp> {
+    fn clone(&self) -> Self {
+        Self {
+            c: self.c.clone(),
+            s: self.s.clone(),
+        }
+    }
+}
+
+impl<Grp: Group> Proof<Grp> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        [Grp::scalar_as_bytes(&self.c), Grp::scalar_as_bytes(&self.s)].concat()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalPakeError> {
+        let scalar_len = Grp::ScalarLen::to_usize();
+        let input = check_slice_size(input, 2 * scalar_len, "proof_bytes")?;
+        Ok(Self {
+            c: Grp::from_scalar_slice(GenericArray::from_slice(&input[..scalar_len]))?,
+            s: Grp::from_scalar_slice(GenericArray::from_slice(&input[scalar_len..]))?,
+        })
+    }
+}
+
 static STR_VOPRF: &[u8] = b"VOPRF06-HashToGroup-";
+static STR_VOPRF_HASH_TO_SCALAR: &[u8] = b"VOPRF06-HashToScalar-";
 static STR_VOPRF_FINALIZE: &[u8] = b"VOPRF06-Finalize-";
+static STR_VOPRF_SEED: &[u8] = b"VOPRF06-Seed-";
+static STR_VOPRF_COMPOSITE: &[u8] = b"VOPRF06-Composite-";
+static STR_VOPRF_CHALLENGE: &[u8] = b"VOPRF06-Challenge-";
+static STR_HASH_TO_GROUP: &[u8] = b"HashToGroup-";
+static STR_HASH_TO_SCALAR: &[u8] = b"HashToScalar-";
+static STR_SEED: &[u8] = b"Seed-";
+static STR_COMPOSITE: &[u8] = b"Composite";
+static STR_CHALLENGE: &[u8] = b"Challenge";
+static STR_INFO: &[u8] = b"Info";
+static STR_FINALIZE: &[u8] = b"Finalize";
+static STR_DERIVE_KEYPAIR: &[u8] = b"DeriveKeyPair";
+static STR_OPRF_V1: &[u8] = b"OPRFV1-";
 static MODE_BASE: u8 = 0x00;
+static MODE_VERIFIABLE: u8 = 0x01;
+static MODE_POPRF: u8 = 0x02;
 
 /// Computes the first step for the multiplicative blinding version of DH-OPRF. This
 /// message is sent from the client (who holds the input) to the server (who holds the OPRF key).
@@ -29,9 +114,27 @@ pub(crate) fn blind<R: RngCore + CryptoRng, G: GroupWithMapToCurve, H: Hash>(
     input: &[u8],
     blinding_factor_rng: &mut R,
 ) -> Result<(Token<G>, G), InternalPakeError> {
-    let blind = G::random_scalar(blinding_factor_rng);
     let dst = [STR_VOPRF, &G::get_context_string(MODE_BASE)].concat();
-    let mapped_point = G::map_to_curve::<H>(input, &dst)?;
+    blind_with_dst::<R, G, H>(input, blinding_factor_rng, &dst)
+}
+
+/// Computes the first step of the verifiable mode of DH-OPRF, which differs
+/// from [blind] only in the domain separation of the mapped input.
+pub(crate) fn blind_verifiable<R: RngCore + CryptoRng, G: GroupWithMapToCurve, H: Hash>(
+    input: &[u8],
+    blinding_factor_rng: &mut R,
+) -> Result<(Token<G>, G), InternalPakeError> {
+    let dst = [STR_VOPRF, &G::get_context_string(MODE_VERIFIABLE)].concat();
+    blind_with_dst::<R, G, H>(input, blinding_factor_rng, &dst)
+}
+
+fn blind_with_dst<R: RngCore + CryptoRng, G: GroupWithMapToCurve, H: Hash>(
+    input: &[u8],
+    blinding_factor_rng: &mut R,
+    dst: &[u8],
+) -> Result<(Token<G>, G), InternalPakeError> {
+    let blind = G::random_scalar(blinding_factor_rng);
+    let mapped_point = G::map_to_curve::<H>(input, dst)?;
     let blind_token = mapped_point * &blind;
     Ok((
         Token {
@@ -48,6 +151,66 @@ pub(crate) fn evaluate<G: Group>(point: G, oprf_key: &G::Scalar) -> G {
     point * oprf_key
 }
 
+/// Computes the OPRF public key which the server publishes in the verifiable
+/// mode of DH-OPRF, and against which evaluation proofs are verified
+pub(crate) fn public_key<G: Group>(oprf_key: &G::Scalar) -> G {
+    G::base_point() * oprf_key
+}
+
+/// Deterministically derives an OPRF key from a seed and some public info,
+/// as per DeriveKeyPair() from https://www.rfc-editor.org/rfc/rfc9497,
+/// using the context string of the base mode
+pub(crate) fn derive_key<G: GroupWithMapToCurve, H: Hash>(
+    seed: &[u8],
+    info: &[u8],
+) -> Result<G::Scalar, InternalPakeError> {
+    let derive_input = [seed, &serialize(info, 2)].concat();
+    let dst = [STR_DERIVE_KEYPAIR, &context_string::<G>(MODE_BASE)].concat();
+    for counter in 0..=255u8 {
+        let oprf_key = G::hash_to_scalar::<H>(&[&derive_input[..], &[counter]].concat(), &dst)?;
+        if !G::scalar_as_bytes(&oprf_key).iter().all(|&b| b == 0) {
+            return Ok(oprf_key);
+        }
+    }
+    Err(InternalPakeError::DeriveKeyPairError)
+}
+
+/// Computes the second step of the verifiable mode of DH-OPRF, which
+/// additionally returns a proof that the evaluation was performed with the
+/// private key corresponding to the server's OPRF public key.
+pub(crate) fn evaluate_verifiable<R: RngCore + CryptoRng, G: GroupWithMapToCurve, H: Hash>(
+    point: G,
+    oprf_key: &G::Scalar,
+    rng: &mut R,
+) -> Result<(G, Proof<G>), InternalPakeError> {
+    let (evaluated_elements, proof) =
+        evaluate_verifiable_batch::<R, G, H>(&[point], oprf_key, rng)?;
+    Ok((evaluated_elements[0], proof))
+}
+
+/// Computes the second step of the verifiable mode of DH-OPRF for several
+/// blinded elements at once, with a single proof covering all of the
+/// evaluations.
+pub(crate) fn evaluate_verifiable_batch<R: RngCore + CryptoRng, G: GroupWithMapToCurve, H: Hash>(
+    points: &[G],
+    oprf_key: &G::Scalar,
+    rng: &mut R,
+) -> Result<(Vec<G>, Proof<G>), InternalPakeError> {
+    let evaluated_elements: Vec<G> = points
+        .iter()
+        .map(|&point| evaluate(point, oprf_key))
+        .collect();
+    let proof = generate_proof::<R, G, H>(
+        &ProofDomain::verifiable::<G>(),
+        oprf_key,
+        public_key(oprf_key),
+        points,
+        &evaluated_elements,
+        rng,
+    )?;
+    Ok((evaluated_elements, proof))
+}
+
 /// Computes the third step for the multiplicative blinding version of DH-OPRF, in which
 /// the client unblinds the server's message.
 pub(crate) fn finalize<G: GroupWithMapToCurve, H: Hash>(
@@ -56,52 +219,717 @@ pub(crate) fn finalize<G: GroupWithMapToCurve, H: Hash>(
     evaluated_element: G,
 ) -> GenericArray<u8, <H as Digest>::OutputSize> {
     let unblinded_element = evaluated_element * &G::scalar_invert(blind);
-    finalize_after_unblind::<G, H>(input, unblinded_element)
+    finalize_after_unblind::<G, H>(input, unblinded_element, MODE_BASE)
+}
+
+/// Computes the third step of the verifiable mode of DH-OPRF, in which the
+/// client checks the server's proof against its OPRF public key before
+/// unblinding the server's message.
+pub(crate) fn finalize_verifiable<G: GroupWithMapToCurve, H: Hash>(
+    input: &[u8],
+    blind: &G::Scalar,
+    blinded_element: G,
+    evaluated_element: G,
+    proof: &Proof<G>,
+    public_key: G,
+) -> Result<GenericArray<u8, <H as Digest>::OutputSize>, InternalPakeError> {
+    verify_proof::<G, H>(
+        &ProofDomain::verifiable::<G>(),
+        public_key,
+        &[blinded_element],
+        &[evaluated_element],
+        proof,
+    )?;
+    let unblinded_element = evaluated_element * &G::scalar_invert(blind);
+    Ok(finalize_after_unblind::<G, H>(
+        input,
+        unblinded_element,
+        MODE_VERIFIABLE,
+    ))
+}
+
+/// Computes the third step of the verifiable mode of DH-OPRF for several
+/// evaluations at once, checking the single proof which covers all of them
+/// before unblinding any of the server's messages.
+pub(crate) fn finalize_verifiable_batch<G: GroupWithMapToCurve, H: Hash>(
+    tokens: &[&Token<G>],
+    blinded_elements: &[G],
+    evaluated_elements: &[G],
+    proof: &Proof<G>,
+    public_key: G,
+) -> Result<Vec<GenericArray<u8, <H as Digest>::OutputSize>>, InternalPakeError> {
+    if tokens.len() != evaluated_elements.len() {
+        return Err(InternalPakeError::SizeError {
+            name: "evaluated_elements",
+            len: tokens.len(),
+            actual_len: evaluated_elements.len(),
+        });
+    }
+    verify_proof::<G, H>(
+        &ProofDomain::verifiable::<G>(),
+        public_key,
+        blinded_elements,
+        evaluated_elements,
+        proof,
+    )?;
+    Ok(tokens
+        .iter()
+        .zip(evaluated_elements.iter())
+        .map(|(token, &evaluated_element)| {
+            let unblinded_element = evaluated_element * &G::scalar_invert(&token.blind);
+            finalize_after_unblind::<G, H>(&token.data, unblinded_element, MODE_VERIFIABLE)
+        })
+        .collect())
 }
 
 fn finalize_after_unblind<G: GroupWithMapToCurve, H: Hash>(
     input: &[u8],
     unblinded_element: G,
+    mode: u8,
 ) -> GenericArray<u8, <H as Digest>::OutputSize> {
-    let finalize_dst = [STR_VOPRF_FINALIZE, &G::get_context_string(MODE_BASE)].concat();
+    let finalize_dst = [STR_VOPRF_FINALIZE, &G::get_context_string(mode)].concat();
     let hash_input = [
         serialize(input, 2),
-        serialize(&unblinded_element.to_arr().to_vec(), 2),
+        serialize(&unblinded_element.to_arr(), 2),
         serialize(&finalize_dst, 2),
     ]
     .concat();
     <H as Digest>::digest(&hash_input)
 }
 
-////////////////////////
-// Benchmarking shims //
-////////////////////////
-
-#[cfg(feature = "bench")]
-#[doc(hidden)]
-#[inline]
-pub fn blind_shim<R: RngCore + CryptoRng, G: GroupWithMapToCurve, H: Hash>(
+/// Computes the first step of the partially-oblivious mode of DH-OPRF, in
+/// which the input is bound to public info known to both parties. Along with
+/// the client state and the blinded element, this returns the server's OPRF
+/// public key tweaked by the info, against which the proof is verified.
+pub(crate) fn blind_poprf<R: RngCore + CryptoRng, G: PoprfSuite<H>, H: Hash>(
     input: &[u8],
+    info: &[u8],
+    public_key: G,
     blinding_factor_rng: &mut R,
-) -> Result<(Token<G>, G), InternalPakeError> {
-    blind::<R, G, H>(input, blinding_factor_rng)
+) -> Result<(Token<G>, G, G), InternalPakeError> {
+    let tweaked_key = G::base_point() * &poprf_tweak::<G, H>(info)? + public_key;
+    if tweaked_key.to_arr() == G::identity().to_arr() {
+        return Err(InternalPakeError::InvalidPoprfInfoError);
+    }
+    let dst = [STR_HASH_TO_GROUP, &context_string::<G>(MODE_POPRF)].concat();
+    let (token, blinded_element) = blind_with_dst::<R, G, H>(input, blinding_factor_rng, &dst)?;
+    Ok((token, blinded_element, tweaked_key))
 }
 
-#[cfg(feature = "bench")]
-#[doc(hidden)]
-#[inline]
-pub fn evaluate_shim<G: Group>(point: G, oprf_key: &G::Scalar) -> G {
-    evaluate(point, oprf_key)
+/// Computes the second step of the partially-oblivious mode of DH-OPRF for
+/// one or more blinded elements, in which the server evaluates with its OPRF
+/// key tweaked by the public info, and proves that it did so.
+pub(crate) fn evaluate_poprf_batch<R: RngCore + CryptoRng, G: PoprfSuite<H>, H: Hash>(
+    points: &[G],
+    oprf_key: &G::Scalar,
+    info: &[u8],
+    rng: &mut R,
+) -> Result<(Vec<G>, Proof<G>), InternalPakeError> {
+    let tweaked_oprf_key = oprf_key.clone() + poprf_tweak::<G, H>(info)?;
+    if G::scalar_as_bytes(&tweaked_oprf_key)
+        .iter()
+        .all(|&b| b == 0)
+    {
+        return Err(InternalPakeError::InvalidPoprfInfoError);
+    }
+    let inverse = G::scalar_invert(&tweaked_oprf_key);
+    let evaluated_elements: Vec<G> = points.iter().map(|&point| point * &inverse).collect();
+    // The proof shows that the evaluated elements, raised to the tweaked key,
+    // give back the blinded elements
+    let proof = generate_proof::<R, G, H>(
+        &ProofDomain::poprf::<G, H>(),
+        &tweaked_oprf_key,
+        public_key(&tweaked_oprf_key),
+        &evaluated_elements,
+        points,
+        rng,
+    )?;
+    Ok((evaluated_elements, proof))
 }
 
-#[cfg(feature = "bench")]
-#[doc(hidden)]
-#[inline]
-pub fn finalize_shim<G: GroupWithMapToCurve, H: Hash>(
-    token: &Token<G>,
-    point: G,
-) -> Result<GenericArray<u8, <H as Digest>::OutputSize>, InternalPakeError> {
-    Ok(finalize::<G, H>(&token.data, &token.blind, point))
+/// Computes the third step of the partially-oblivious mode of DH-OPRF for
+/// one or more evaluations, checking the server's proof against the tweaked
+/// public key before unblinding any of the server's messages.
+pub(crate) fn finalize_poprf_batch<G: PoprfSuite<H>, H: Hash>(
+    tokens: &[&Token<G>],
+    blinded_elements: &[G],
+    evaluated_elements: &[G],
+    info: &[u8],
+    proof: &Proof<G>,
+    tweaked_key: G,
+) -> Result<Vec<GenericArray<u8, <H as Digest>::OutputSize>>, InternalPakeError> {
+    if tokens.len() != evaluated_elements.len() {
+        return Err(InternalPakeError::SizeError {
+            name: "evaluated_elements",
+            len: tokens.len(),
+            actual_len: evaluated_elements.len(),
+        });
+    }
+    verify_proof::<G, H>(
+        &ProofDomain::poprf::<G, H>(),
+        tweaked_key,
+        evaluated_elements,
+        blinded_elements,
+        proof,
+    )?;
+    Ok(tokens
+        .iter()
+        .zip(evaluated_elements.iter())
+        .map(|(token, &evaluated_element)| {
+            let unblinded_element = evaluated_element * &G::scalar_invert(&token.blind);
+            let hash_input = [
+                serialize(&token.data, 2),
+                serialize(info, 2),
+                serialize(&unblinded_element.to_arr(), 2),
+                STR_FINALIZE.to_vec(),
+            ]
+            .concat();
+            <H as Digest>::digest(&hash_input)
+        })
+        .collect())
+}
+
+// The contextString of the given mode, as defined in
+// https://www.rfc-editor.org/rfc/rfc9497, which replaces the draft suite ID
+// with the identifier of the (group, hash) pair
+fn context_string<G: GroupWithMapToCurve>(mode: u8) -> Vec<u8> {
+    [
+        STR_OPRF_V1,
+        &i2osp(mode as usize, 1),
+        b"-",
+        G::IDENTIFIER.as_bytes(),
+    ]
+    .concat()
+}
+
+// The scalar by which the public info tweaks the OPRF key
+fn poprf_tweak<G: PoprfSuite<H>, H: Hash>(info: &[u8]) -> Result<G::Scalar, InternalPakeError> {
+    let framed_info = [STR_INFO, &serialize(info, 2)].concat();
+    let dst = [STR_HASH_TO_SCALAR, &context_string::<G>(MODE_POPRF)].concat();
+    G::hash_to_scalar::<H>(&framed_info, &dst)
+}
+
+// The domain separation of the DLEQ proofs. The verifiable mode follows
+// https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-06.txt, whereas the
+// POPRF mode, which only exists in later versions of the draft, follows
+// https://www.rfc-editor.org/rfc/rfc9497
+struct ProofDomain {
+    hash_to_scalar_dst: Vec<u8>,
+    seed_dst: Vec<u8>,
+    composite_label: Vec<u8>,
+    challenge_label: Vec<u8>,
+}
+
+impl ProofDomain {
+    fn verifiable<G: GroupWithMapToCurve>() -> Self {
+        let context_string = G::get_context_string(MODE_VERIFIABLE);
+        Self {
+            hash_to_scalar_dst: [STR_VOPRF_HASH_TO_SCALAR, &context_string].concat(),
+            seed_dst: [STR_VOPRF_SEED, &context_string].concat(),
+            composite_label: serialize(&[STR_VOPRF_COMPOSITE, &context_string].concat(), 2),
+            challenge_label: serialize(&[STR_VOPRF_CHALLENGE, &context_string].concat(), 2),
+        }
+    }
+
+    fn poprf<G: PoprfSuite<H>, H: Hash>() -> Self {
+        let context_string = context_string::<G>(MODE_POPRF);
+        Self {
+            hash_to_scalar_dst: [STR_HASH_TO_SCALAR, &context_string].concat(),
+            seed_dst: [STR_SEED, &context_string].concat(),
+            composite_label: STR_COMPOSITE.to_vec(),
+            challenge_label: STR_CHALLENGE.to_vec(),
+        }
+    }
+}
+
+// Combines the blinded and evaluated elements into a single pair (M, Z) with
+// random coefficients derived from the public key and the elements, so that
+// one proof covers all of them. Corresponds to ComputeComposites() from
+// https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-06.txt
+fn compute_composites<G: GroupWithMapToCurve, H: Hash>(
+    domain: &ProofDomain,
+    public_key: G,
+    blinded_elements: &[G],
+    evaluated_elements: &[G],
+) -> Result<(G, G), InternalPakeError> {
+    // An empty batch would be trivially covered by any proof
+    if blinded_elements.is_empty() || blinded_elements.len() != evaluated_elements.len() {
+        return Err(InternalPakeError::InvalidProofError);
+    }
+
+    let seed = <H as Digest>::digest(
+        &[
+            serialize(&public_key.to_arr(), 2),
+            serialize(&domain.seed_dst, 2),
+        ]
+        .concat(),
+    );
+
+    let mut m = G::identity();
+    let mut z = G::identity();
+    for (i, (c, d)) in blinded_elements
+        .iter()
+        .zip(evaluated_elements.iter())
+        .enumerate()
+    {
+        let h2_input = [
+            serialize(&seed, 2),
+            i2osp(i, 2),
+            serialize(&c.to_arr(), 2),
+            serialize(&d.to_arr(), 2),
+            domain.composite_label.clone(),
+        ]
+        .concat();
+        let di = G::hash_to_scalar::<H>(&h2_input, &domain.hash_to_scalar_dst)?;
+        m = m + *c * &di;
+        z = z + *d * &di;
+    }
+    Ok((m, z))
+}
+
+fn compute_challenge<G: GroupWithMapToCurve, H: Hash>(
+    domain: &ProofDomain,
+    public_key: G,
+    m: G,
+    z: G,
+    t2: G,
+    t3: G,
+) -> Result<G::Scalar, InternalPakeError> {
+    let h2_input = [
+        serialize(&public_key.to_arr(), 2),
+        serialize(&m.to_arr(), 2),
+        serialize(&z.to_arr(), 2),
+        serialize(&t2.to_arr(), 2),
+        serialize(&t3.to_arr(), 2),
+        domain.challenge_label.clone(),
+    ]
+    .concat();
+    G::hash_to_scalar::<H>(&h2_input, &domain.hash_to_scalar_dst)
+}
+
+// Corresponds to GenerateProof() from
+// https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-06.txt
+fn generate_proof<R: RngCore + CryptoRng, G: GroupWithMapToCurve, H: Hash>(
+    domain: &ProofDomain,
+    oprf_key: &G::Scalar,
+    public_key: G,
+    blinded_elements: &[G],
+    evaluated_elements: &[G],
+    rng: &mut R,
+) -> Result<Proof<G>, InternalPakeError> {
+    let (m, z) =
+        compute_composites::<G, H>(domain, public_key, blinded_elements, evaluated_elements)?;
+
+    let r = G::random_scalar(rng);
+    let t2 = G::base_point() * &r;
+    let t3 = m * &r;
+
+    let c = compute_challenge::<G, H>(domain, public_key, m, z, t2, t3)?;
+    let s = r - c.clone() * oprf_key;
+    Ok(Proof { c, s })
+}
+
+// Corresponds to VerifyProof() from
+// https://www.ietf.org/archive/id/draft-irtf-cfrg-voprf-06.txt
+fn verify_proof<G: GroupWithMapToCurve, H: Hash>(
+    domain: &ProofDomain,
+    public_key: G,
+    blinded_elements: &[G],
+    evaluated_elements: &[G],
+    proof: &Proof<G>,
+) -> Result<(), InternalPakeError> {
+    let (m, z) =
+        compute_composites::<G, H>(domain, public_key, blinded_elements, evaluated_elements)?;
+
+    let t2 = G::base_point() * &proof.s + public_key * &proof.c;
+    let t3 = m * &proof.s + z * &proof.c;
+
+    let expected_c = compute_challenge::<G, H>(domain, public_key, m, z, t2, t3)?;
+    if G::scalar_as_bytes(&expected_c) != G::scalar_as_bytes(&proof.c) {
+        return Err(InternalPakeError::InvalidProofError);
+    }
+    Ok(())
+}
+
+////////////////
+// Public API //
+// ========== //
+////////////////
+
+/// The blinded client input, sent from the client to the server
+pub struct BlindedElement<G: GroupWithMapToCurve> {
+    pub(crate) value: G,
+}
+
+impl<G: GroupWithMapToCurve> Clone for BlindedElement<G> {
+    fn clone(&self) -> Self {
+        Self { value: self.value }
+    }
+}
+
+impl<G: GroupWithMapToCurve> BlindedElement<G> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        self.value.to_arr().to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalPakeError> {
+        Ok(Self {
+            value: deserialize_element(input, "blinded_element_bytes")?,
+        })
+    }
+}
+
+/// The server's evaluation of a [BlindedElement], sent from the server to
+/// the client
+pub struct EvaluationElement<G: GroupWithMapToCurve> {
+    pub(crate) value: G,
+}
+
+impl<G: GroupWithMapToCurve> Clone for EvaluationElement<G> {
+    fn clone(&self) -> Self {
+        Self { value: self.value }
+    }
+}
+
+impl<G: GroupWithMapToCurve> EvaluationElement<G> {
+    /// Serialization into bytes
+    pub fn serialize(&self) -> Vec<u8> {
+        self.value.to_arr().to_vec()
+    }
+
+    /// Deserialization from bytes
+    pub fn deserialize(input: &[u8]) -> Result<Self, InternalPakeError> {
+        Ok(Self {
+            value: deserialize_element(input, "evaluation_element_bytes")?,
+        })
+    }
+}
+
+fn deserialize_element<G: Group>(input: &[u8], name: &'static str) -> Result<G, InternalPakeError> {
+    let checked_slice = check_slice_size(input, G::ElemLen::to_usize(), name)?;
+    G::from_element_slice(GenericArray::from_slice(checked_slice))
+}
+
+/// The client side of a standalone OPRF evaluation, which holds the input
+/// and the blinding factor between [OprfClient::blind] and
+/// [OprfClient::finalize]
+pub struct OprfClient<G: GroupWithMapToCurve, H: Hash> {
+    token: Token<G>,
+    blinded_element: G,
+    mode: u8,
+    _hash: PhantomData<H>,
+}
+
+impl<G: GroupWithMapToCurve, H: Hash> OprfClient<G, H> {
+    /// Blinds the input for an evaluation in the base mode, returning the
+    /// client state along with the message to send to the server
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<G>), InternalPakeError> {
+        let (token, blinded_element) = blind::<R, G, H>(input, blinding_factor_rng)?;
+        Ok(Self::new(token, blinded_element, MODE_BASE))
+    }
+
+    /// Blinds the input for an evaluation in the verifiable mode, in which
+    /// the server proves that it used the key behind its OPRF public key
+    pub fn blind_verifiable<R: RngCore + CryptoRng>(
+        input: &[u8],
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<G>), InternalPakeError> {
+        let (token, blinded_element) = blind_verifiable::<R, G, H>(input, blinding_factor_rng)?;
+        Ok(Self::new(token, blinded_element, MODE_VERIFIABLE))
+    }
+
+    fn new(token: Token<G>, blinded_element: G, mode: u8) -> (Self, BlindedElement<G>) {
+        (
+            Self {
+                token,
+                blinded_element,
+                mode,
+                _hash: PhantomData,
+            },
+            BlindedElement {
+                value: blinded_element,
+            },
+        )
+    }
+
+    /// Unblinds the server's evaluation of a base mode blinded input into
+    /// the OPRF output
+    pub fn finalize(
+        &self,
+        evaluation_element: &EvaluationElement<G>,
+    ) -> Result<GenericArray<u8, <H as Digest>::OutputSize>, InternalPakeError> {
+        if self.mode != MODE_BASE {
+            return Err(InternalPakeError::IncompatibleOprfModeError);
+        }
+        Ok(finalize::<G, H>(
+            &self.token.data,
+            &self.token.blind,
+            evaluation_element.value,
+        ))
+    }
+
+    /// Checks the server's proof against its OPRF public key, and then
+    /// unblinds the server's evaluation of a verifiable mode blinded input
+    /// into the OPRF output
+    pub fn finalize_verifiable(
+        &self,
+        evaluation_element: &EvaluationElement<G>,
+        proof: &Proof<G>,
+        public_key: &PublicKey<G>,
+    ) -> Result<GenericArray<u8, <H as Digest>::OutputSize>, InternalPakeError> {
+        if self.mode != MODE_VERIFIABLE {
+            return Err(InternalPakeError::IncompatibleOprfModeError);
+        }
+        let public_key = G::from_element_slice(public_key)?;
+        finalize_verifiable::<G, H>(
+            &self.token.data,
+            &self.token.blind,
+            self.blinded_element,
+            evaluation_element.value,
+            proof,
+            public_key,
+        )
+    }
+
+    /// Checks the server's single proof for a batch of verifiable mode
+    /// evaluations, and then unblinds each evaluation into the OPRF output of
+    /// the corresponding client, in order
+    pub fn finalize_verifiable_batch(
+        clients: &[Self],
+        evaluation_elements: &[EvaluationElement<G>],
+        proof: &Proof<G>,
+        public_key: &PublicKey<G>,
+    ) -> Result<Vec<GenericArray<u8, <H as Digest>::OutputSize>>, InternalPakeError> {
+        if clients.iter().any(|client| client.mode != MODE_VERIFIABLE) {
+            return Err(InternalPakeError::IncompatibleOprfModeError);
+        }
+        let public_key = G::from_element_slice(public_key)?;
+        let tokens: Vec<&Token<G>> = clients.iter().map(|client| &client.token).collect();
+        let blinded_elements: Vec<G> = clients
+            .iter()
+            .map(|client| client.blinded_element)
+            .collect();
+        let evaluated_elements: Vec<G> = evaluation_elements
+            .iter()
+            .map(|element| element.value)
+            .collect();
+        finalize_verifiable_batch::<G, H>(
+            &tokens,
+            &blinded_elements,
+            &evaluated_elements,
+            proof,
+            public_key,
+        )
+    }
+}
+
+/// The client side of a partially-oblivious OPRF evaluation, in which the
+/// output is additionally bound to public info known to both parties
+pub struct PoprfClient<G: GroupWithMapToCurve, H: Hash> {
+    token: Token<G>,
+    blinded_element: G,
+    info: Vec<u8>,
+    tweaked_key: G,
+    _hash: PhantomData<H>,
+}
+
+impl<G: PoprfSuite<H>, H: Hash> PoprfClient<G, H> {
+    /// Blinds the input for an evaluation bound to the public info, under the
+    /// server's OPRF public key
+    pub fn blind<R: RngCore + CryptoRng>(
+        input: &[u8],
+        info: &[u8],
+        public_key: &PublicKey<G>,
+        blinding_factor_rng: &mut R,
+    ) -> Result<(Self, BlindedElement<G>), InternalPakeError> {
+        let public_key = G::from_element_slice(public_key)?;
+        let (token, blinded_element, tweaked_key) =
+            blind_poprf::<R, G, H>(input, info, public_key, blinding_factor_rng)?;
+        Ok((
+            Self {
+                token,
+                blinded_element,
+                info: info.to_vec(),
+                tweaked_key,
+                _hash: PhantomData,
+            },
+            BlindedElement {
+                value: blinded_element,
+            },
+        ))
+    }
+
+    /// Checks the server's proof, and then unblinds the server's evaluation
+    /// into the POPRF output
+    pub fn finalize(
+        &self,
+        evaluation_element: &EvaluationElement<G>,
+        proof: &Proof<G>,
+    ) -> Result<GenericArray<u8, <H as Digest>::OutputSize>, InternalPakeError> {
+        let mut outputs = Self::finalize_batch(
+            std::slice::from_ref(self),
+            std::slice::from_ref(evaluation_element),
+            proof,
+        )?;
+        Ok(outputs.remove(0))
+    }
+
+    /// Checks the server's single proof for a batch of evaluations bound to
+    /// the same public info, and then unblinds each evaluation into the POPRF
+    /// output of the corresponding client, in order
+    pub fn finalize_batch(
+        clients: &[Self],
+        evaluation_elements: &[EvaluationElement<G>],
+        proof: &Proof<G>,
+    ) -> Result<Vec<GenericArray<u8, <H as Digest>::OutputSize>>, InternalPakeError> {
+        let first = clients
+            .first()
+            .ok_or(InternalPakeError::InvalidProofError)?;
+        if clients.iter().any(|client| {
+            client.info != first.info || client.tweaked_key.to_arr() != first.tweaked_key.to_arr()
+        }) {
+            return Err(InternalPakeError::InvalidPoprfInfoError);
+        }
+        let tokens: Vec<&Token<G>> = clients.iter().map(|client| &client.token).collect();
+        let blinded_elements: Vec<G> = clients
+            .iter()
+            .map(|client| client.blinded_element)
+            .collect();
+        let evaluated_elements: Vec<G> = evaluation_elements
+            .iter()
+            .map(|element| element.value)
+            .collect();
+        finalize_poprf_batch::<G, H>(
+            &tokens,
+            &blinded_elements,
+            &evaluated_elements,
+            &first.info,
+            proof,
+            first.tweaked_key,
+        )
+    }
+}
+
+/// The server side of a standalone OPRF evaluation, which holds the OPRF
+/// key
+pub struct OprfServer<G: GroupWithMapToCurve, H: Hash> {
+    oprf_key: G::Scalar,
+    _hash: PhantomData<H>,
+}
+
+impl<G: GroupWithMapToCurve, H: Hash> OprfServer<G, H> {
+    /// Creates a server with a freshly generated OPRF key
+    pub fn new<R: RngCore + CryptoRng>(rng: &mut R) -> Self {
+        Self {
+            oprf_key: G::random_scalar(rng),
+            _hash: PhantomData,
+        }
+    }
+
+    /// Creates a server from an existing OPRF key
+    pub fn new_with_key(private_key: &PrivateKey<G>) -> Result<Self, InternalPakeError> {
+        Ok(Self {
+            oprf_key: G::from_scalar_slice(private_key)?,
+            _hash: PhantomData,
+        })
+    }
+
+    /// The OPRF key, to be persisted by the server
+    pub fn private_key(&self) -> PrivateKey<G> {
+        PrivateKey::from_arr(&G::scalar_as_bytes(&self.oprf_key))
+            .expect("scalar bytes have the private key length")
+    }
+
+    /// The OPRF public key, which the server publishes so that clients can
+    /// check its verifiable mode evaluations
+    pub fn public_key(&self) -> PublicKey<G> {
+        PublicKey::from_arr(&public_key::<G>(&self.oprf_key).to_arr())
+            .expect("element bytes have the public key length")
+    }
+
+    /// Evaluates a base mode blinded input
+    pub fn evaluate(&self, blinded_element: &BlindedElement<G>) -> EvaluationElement<G> {
+        EvaluationElement {
+            value: evaluate(blinded_element.value, &self.oprf_key),
+        }
+    }
+
+    /// Evaluates a verifiable mode blinded input, returning a proof that the
+    /// evaluation used the key behind [OprfServer::public_key]
+    pub fn evaluate_verifiable<R: RngCore + CryptoRng>(
+        &self,
+        blinded_element: &BlindedElement<G>,
+        rng: &mut R,
+    ) -> Result<(EvaluationElement<G>, Proof<G>), InternalPakeError> {
+        let (value, proof) =
+            evaluate_verifiable::<R, G, H>(blinded_element.value, &self.oprf_key, rng)?;
+        Ok((EvaluationElement { value }, proof))
+    }
+
+    /// Evaluates a batch of verifiable mode blinded inputs, returning the
+    /// evaluations in order along with a single proof covering all of them
+    pub fn evaluate_verifiable_batch<R: RngCore + CryptoRng>(
+        &self,
+        blinded_elements: &[BlindedElement<G>],
+        rng: &mut R,
+    ) -> Result<(Vec<EvaluationElement<G>>, Proof<G>), InternalPakeError> {
+        let points: Vec<G> = blinded_elements
+            .iter()
+            .map(|element| element.value)
+            .collect();
+        let (values, proof) = evaluate_verifiable_batch::<R, G, H>(&points, &self.oprf_key, rng)?;
+        Ok((
+            values
+                .into_iter()
+                .map(|value| EvaluationElement { value })
+                .collect(),
+            proof,
+        ))
+    }
+}
+
+impl<G: PoprfSuite<H>, H: Hash> OprfServer<G, H> {
+    /// Evaluates a blinded input from a [PoprfClient] under the OPRF key
+    /// tweaked by the public info, returning a proof that the evaluation used
+    /// the key behind [OprfServer::public_key]
+    pub fn evaluate_poprf<R: RngCore + CryptoRng>(
+        &self,
+        blinded_element: &BlindedElement<G>,
+        info: &[u8],
+        rng: &mut R,
+    ) -> Result<(EvaluationElement<G>, Proof<G>), InternalPakeError> {
+        let (mut evaluation_elements, proof) =
+            self.evaluate_poprf_batch(std::slice::from_ref(blinded_element), info, rng)?;
+        Ok((evaluation_elements.remove(0), proof))
+    }
+
+    /// Evaluates a batch of blinded inputs from [PoprfClient]s which share the
+    /// same public info, returning the evaluations in order along with a
+    /// single proof covering all of them
+    pub fn evaluate_poprf_batch<R: RngCore + CryptoRng>(
+        &self,
+        blinded_elements: &[BlindedElement<G>],
+        info: &[u8],
+        rng: &mut R,
+    ) -> Result<(Vec<EvaluationElement<G>>, Proof<G>), InternalPakeError> {
+        let points: Vec<G> = blinded_elements
+            .iter()
+            .map(|element| element.value)
+            .collect();
+        let (values, proof) = evaluate_poprf_batch::<R, G, H>(&points, &self.oprf_key, info, rng)?;
+        Ok((
+            values
+                .into_iter()
+                .map(|value| EvaluationElement { value })
+                .collect(),
+            proof,
+        ))
+    }
 }
 
 ///////////
@@ -125,7 +953,7 @@ mod tests {
             RistrettoPoint::from_scalar_slice(GenericArray::from_slice(&oprf_key[..])).unwrap();
         let res = point * scalar;
 
-        finalize_after_unblind::<RistrettoPoint, sha2::Sha512>(&input, res)
+        finalize_after_unblind::<RistrettoPoint, sha2::Sha512>(input, res, MODE_BASE)
     }
 
     #[test]
@@ -140,7 +968,7 @@ mod tests {
         let oprf_key = RistrettoPoint::from_scalar_slice(&oprf_key_bytes)?;
         let beta = evaluate::<RistrettoPoint>(alpha, &oprf_key);
         let res = finalize::<RistrettoPoint, sha2::Sha512>(&token.data, &token.blind, beta);
-        let res2 = prf(&input[..], &oprf_key.as_bytes());
+        let res2 = prf(&input[..], oprf_key.as_bytes());
         assert_eq!(res, res2);
         Ok(())
     }
@@ -155,8 +983,393 @@ mod tests {
 
         let dst = [STR_VOPRF, &RistrettoPoint::get_context_string(MODE_BASE)].concat();
         let point = RistrettoPoint::map_to_curve::<Sha512>(&input, &dst).unwrap();
-        let res2 = finalize_after_unblind::<RistrettoPoint, sha2::Sha512>(&input, point);
+        let res2 = finalize_after_unblind::<RistrettoPoint, sha2::Sha512>(&input, point, MODE_BASE);
+
+        assert_eq!(res, res2);
+    }
 
+    // Runs the verifiable mode end to end for one (group, hash) pair, checking
+    // the output against a direct evaluation and the proof against a wrong key
+    fn verifiable_oprf_retrieval_for<G: GroupWithMapToCurve, H: Hash>(
+    ) -> Result<(), InternalPakeError> {
+        let input = b"hunter2";
+        let mut rng = OsRng;
+        let oprf_key = G::random_scalar(&mut rng);
+        let pk = public_key::<G>(&oprf_key);
+
+        let (token, alpha) = blind_verifiable::<_, G, H>(&input[..], &mut rng)?;
+        let (beta, proof) = evaluate_verifiable::<_, G, H>(alpha, &oprf_key, &mut rng)?;
+        let res = finalize_verifiable::<G, H>(&token.data, &token.blind, alpha, beta, &proof, pk)?;
+
+        let dst = [STR_VOPRF, &G::get_context_string(MODE_VERIFIABLE)].concat();
+        let point = G::map_to_curve::<H>(&input[..], &dst)?;
+        let res2 = finalize_after_unblind::<G, H>(&input[..], point * &oprf_key, MODE_VERIFIABLE);
         assert_eq!(res, res2);
+
+        // The verifiable mode is domain separated from the base mode
+        let res3 = finalize::<G, H>(&token.data, &token.blind, beta);
+        assert_ne!(res, res3);
+
+        let other_pk = public_key::<G>(&G::random_scalar(&mut rng));
+        assert!(finalize_verifiable::<G, H>(
+            &token.data,
+            &token.blind,
+            alpha,
+            beta,
+            &proof,
+            other_pk
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn verifiable_oprf_retrieval() -> Result<(), InternalPakeError> {
+        verifiable_oprf_retrieval_for::<RistrettoPoint, Sha512>()
+    }
+
+    #[cfg(feature = "p256")]
+    #[test]
+    fn verifiable_oprf_retrieval_p256() -> Result<(), InternalPakeError> {
+        verifiable_oprf_retrieval_for::<p256_::ProjectivePoint, sha2::Sha256>()
+    }
+
+    #[cfg(feature = "p384")]
+    #[test]
+    fn verifiable_oprf_retrieval_p384() -> Result<(), InternalPakeError> {
+        verifiable_oprf_retrieval_for::<p384_::ProjectivePoint, sha2::Sha384>()
+    }
+
+    #[cfg(feature = "p521")]
+    #[test]
+    fn verifiable_oprf_retrieval_p521() -> Result<(), InternalPakeError> {
+        verifiable_oprf_retrieval_for::<p521_::ProjectivePoint, Sha512>()
+    }
+
+    #[cfg(feature = "decaf448")]
+    #[test]
+    fn verifiable_oprf_retrieval_decaf448() -> Result<(), InternalPakeError> {
+        verifiable_oprf_retrieval_for::<ed448_goldilocks_plus::DecafPoint, Sha512>()
+    }
+
+    #[test]
+    fn verifiable_oprf_rejects_wrong_key() -> Result<(), InternalPakeError> {
+        let input = b"hunter2";
+        let mut rng = OsRng;
+        let oprf_key = RistrettoPoint::random_scalar(&mut rng);
+        let other_pk = public_key::<RistrettoPoint>(&RistrettoPoint::random_scalar(&mut rng));
+
+        let (token, alpha) = blind_verifiable::<_, RistrettoPoint, Sha512>(&input[..], &mut rng)?;
+        let (beta, proof) =
+            evaluate_verifiable::<_, RistrettoPoint, Sha512>(alpha, &oprf_key, &mut rng)?;
+        assert!(matches!(
+            finalize_verifiable::<RistrettoPoint, Sha512>(
+                &token.data,
+                &token.blind,
+                alpha,
+                beta,
+                &proof,
+                other_pk,
+            ),
+            Err(InternalPakeError::InvalidProofError)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn verifiable_oprf_rejects_tampered_evaluation() -> Result<(), InternalPakeError> {
+        let input = b"hunter2";
+        let mut rng = OsRng;
+        let oprf_key = RistrettoPoint::random_scalar(&mut rng);
+        let pk = public_key::<RistrettoPoint>(&oprf_key);
+
+        let (token, alpha) = blind_verifiable::<_, RistrettoPoint, Sha512>(&input[..], &mut rng)?;
+        let (beta, proof) =
+            evaluate_verifiable::<_, RistrettoPoint, Sha512>(alpha, &oprf_key, &mut rng)?;
+
+        // An evaluation under a different key, passed off with a valid proof
+        let other_key = RistrettoPoint::random_scalar(&mut rng);
+        let bad_beta = evaluate::<RistrettoPoint>(alpha, &other_key);
+        assert!(finalize_verifiable::<RistrettoPoint, Sha512>(
+            &token.data,
+            &token.blind,
+            alpha,
+            bad_beta,
+            &proof,
+            pk,
+        )
+        .is_err());
+
+        // A proof with a modified response
+        let mut proof_bytes = proof.serialize();
+        let last = proof_bytes.len() - 1;
+        proof_bytes[last] ^= 0x01;
+        let bad_proof = Proof::<RistrettoPoint>::deserialize(&proof_bytes)?;
+        assert!(finalize_verifiable::<RistrettoPoint, Sha512>(
+            &token.data,
+            &token.blind,
+            alpha,
+            beta,
+            &bad_proof,
+            pk,
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn proof_serialization_roundtrip() -> Result<(), InternalPakeError> {
+        let mut rng = OsRng;
+        let oprf_key = RistrettoPoint::random_scalar(&mut rng);
+        let (_, alpha) = blind_verifiable::<_, RistrettoPoint, Sha512>(b"hunter2", &mut rng)?;
+        let (_, proof) =
+            evaluate_verifiable::<_, RistrettoPoint, Sha512>(alpha, &oprf_key, &mut rng)?;
+
+        let bytes = proof.serialize();
+        assert_eq!(bytes.len(), 64);
+        assert_eq!(
+            Proof::<RistrettoPoint>::deserialize(&bytes)?.serialize(),
+            bytes
+        );
+        assert!(Proof::<RistrettoPoint>::deserialize(&bytes[..63]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn public_api_retrieval() -> Result<(), InternalPakeError> {
+        let input = b"hunter2";
+        let mut rng = OsRng;
+        let server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+
+        let (client, blinded_element) =
+            OprfClient::<RistrettoPoint, Sha512>::blind(&input[..], &mut rng)?;
+        let blinded_element = BlindedElement::deserialize(&blinded_element.serialize())?;
+        let evaluation_element = server.evaluate(&blinded_element);
+        let evaluation_element = EvaluationElement::deserialize(&evaluation_element.serialize())?;
+        let res = client.finalize(&evaluation_element)?;
+
+        // A server restored from the serialized key computes the same PRF
+        let server2 = OprfServer::<RistrettoPoint, Sha512>::new_with_key(&server.private_key())?;
+        let (client2, blinded_element2) =
+            OprfClient::<RistrettoPoint, Sha512>::blind(&input[..], &mut rng)?;
+        let res2 = client2.finalize(&server2.evaluate(&blinded_element2))?;
+        assert_eq!(res, res2);
+
+        // Base mode evaluations cannot be finalized as verifiable ones
+        let (evaluation_element, proof) = server.evaluate_verifiable(&blinded_element, &mut rng)?;
+        assert!(matches!(
+            client.finalize_verifiable(&evaluation_element, &proof, &server.public_key()),
+            Err(InternalPakeError::IncompatibleOprfModeError)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn public_api_verifiable_retrieval() -> Result<(), InternalPakeError> {
+        let input = b"hunter2";
+        let mut rng = OsRng;
+        let server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+        let public_key = server.public_key();
+
+        let (client, blinded_element) =
+            OprfClient::<RistrettoPoint, Sha512>::blind_verifiable(&input[..], &mut rng)?;
+        let (evaluation_element, proof) = server.evaluate_verifiable(&blinded_element, &mut rng)?;
+        let proof = Proof::deserialize(&proof.serialize())?;
+        client.finalize_verifiable(&evaluation_element, &proof, &public_key)?;
+
+        // Verifiable mode evaluations cannot be finalized without the proof
+        assert!(matches!(
+            client.finalize(&evaluation_element),
+            Err(InternalPakeError::IncompatibleOprfModeError)
+        ));
+
+        // Nor with the public key of another server
+        let other_server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+        assert!(matches!(
+            client.finalize_verifiable(&evaluation_element, &proof, &other_server.public_key()),
+            Err(InternalPakeError::InvalidProofError)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn public_api_rejects_invalid_elements() {
+        assert!(BlindedElement::<RistrettoPoint>::deserialize(&[0u8; 31]).is_err());
+        assert!(EvaluationElement::<RistrettoPoint>::deserialize(&[0xffu8; 32]).is_err());
+    }
+
+    #[test]
+    fn verifiable_oprf_batch_retrieval() -> Result<(), InternalPakeError> {
+        let inputs: [&[u8]; 3] = [b"hunter2", b"recovery code", b""];
+        let mut rng = OsRng;
+        let server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+        let public_key = server.public_key();
+
+        let mut clients = Vec::new();
+        let mut blinded_elements = Vec::new();
+        for input in inputs.iter() {
+            let (client, blinded_element) =
+                OprfClient::<RistrettoPoint, Sha512>::blind_verifiable(input, &mut rng)?;
+            clients.push(client);
+            blinded_elements.push(blinded_element);
+        }
+
+        let (evaluation_elements, proof) =
+            server.evaluate_verifiable_batch(&blinded_elements, &mut rng)?;
+        let outputs = OprfClient::finalize_verifiable_batch(
+            &clients,
+            &evaluation_elements,
+            &proof,
+            &public_key,
+        )?;
+
+        // Each output matches the one from an individual evaluation
+        for (input, output) in inputs.iter().zip(outputs.iter()) {
+            let (client, blinded_element) =
+                OprfClient::<RistrettoPoint, Sha512>::blind_verifiable(input, &mut rng)?;
+            let (evaluation_element, proof) =
+                server.evaluate_verifiable(&blinded_element, &mut rng)?;
+            assert_eq!(
+                &client.finalize_verifiable(&evaluation_element, &proof, &public_key)?,
+                output
+            );
+        }
+
+        // Reordering the evaluations invalidates the proof
+        let mut swapped = evaluation_elements.clone();
+        swapped.swap(0, 1);
+        assert!(matches!(
+            OprfClient::finalize_verifiable_batch(&clients, &swapped, &proof, &public_key),
+            Err(InternalPakeError::InvalidProofError)
+        ));
+
+        // So does dropping one of them
+        assert!(OprfClient::finalize_verifiable_batch(
+            &clients[..2],
+            &evaluation_elements[..2],
+            &proof,
+            &public_key
+        )
+        .is_err());
+
+        // And the lengths of the batch must match
+        assert!(OprfClient::finalize_verifiable_batch(
+            &clients,
+            &evaluation_elements[..2],
+            &proof,
+            &public_key
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn verifiable_oprf_rejects_empty_batch() {
+        let mut rng = OsRng;
+        let server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+        assert!(server.evaluate_verifiable_batch(&[], &mut rng).is_err());
+    }
+
+    #[test]
+    fn context_string() {
+        assert_eq!(
+            b"OPRFV1-\x00-ristretto255-SHA512".to_vec(),
+            super::context_string::<RistrettoPoint>(MODE_BASE)
+        );
+        assert_eq!(
+            b"OPRFV1-\x02-ristretto255-SHA512".to_vec(),
+            super::context_string::<RistrettoPoint>(MODE_POPRF)
+        );
+    }
+
+    // From the DeriveKeyPair test vector of the ristretto255-SHA512 OPRF
+    // mode in https://www.rfc-editor.org/rfc/rfc9497
+    #[test]
+    fn derive_key_test_vector() -> Result<(), InternalPakeError> {
+        let oprf_key = derive_key::<RistrettoPoint, Sha512>(&[0xa3; 32], b"test key")?;
+        assert_eq!(
+            "5ebcea5ee37023ccb9fc2d2019f9d7737be85591ae8652ffa9ef0f4d37063b0e",
+            hex::encode(oprf_key.as_bytes())
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn poprf_retrieval() -> Result<(), InternalPakeError> {
+        let input = b"hunter2";
+        let mut rng = OsRng;
+        let server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+        let public_key = server.public_key();
+
+        let (client, blinded_element) = PoprfClient::<RistrettoPoint, Sha512>::blind(
+            &input[..],
+            b"tenant 1",
+            &public_key,
+            &mut rng,
+        )?;
+        let (evaluation_element, proof) =
+            server.evaluate_poprf(&blinded_element, b"tenant 1", &mut rng)?;
+        let res = client.finalize(&evaluation_element, &proof)?;
+
+        let (client2, blinded_element2) = PoprfClient::<RistrettoPoint, Sha512>::blind(
+            &input[..],
+            b"tenant 1",
+            &public_key,
+            &mut rng,
+        )?;
+        let (evaluation_element2, proof2) =
+            server.evaluate_poprf(&blinded_element2, b"tenant 1", &mut rng)?;
+        assert_eq!(res, client2.finalize(&evaluation_element2, &proof2)?);
+
+        // Different public info yields an unrelated output
+        let (client3, blinded_element3) = PoprfClient::<RistrettoPoint, Sha512>::blind(
+            &input[..],
+            b"tenant 2",
+            &public_key,
+            &mut rng,
+        )?;
+        let (evaluation_element3, proof3) =
+            server.evaluate_poprf(&blinded_element3, b"tenant 2", &mut rng)?;
+        assert_ne!(res, client3.finalize(&evaluation_element3, &proof3)?);
+        Ok(())
+    }
+
+    #[test]
+    fn poprf_rejects_mismatched_info() -> Result<(), InternalPakeError> {
+        let mut rng = OsRng;
+        let server = OprfServer::<RistrettoPoint, Sha512>::new(&mut rng);
+        let public_key = server.public_key();
+
+        // The server evaluating under different info fails the proof
+        let (client, blinded_element) = PoprfClient::<RistrettoPoint, Sha512>::blind(
+            b"hunter2",
+            b"tenant 1",
+            &public_key,
+            &mut rng,
+        )?;
+        let (evaluation_element, proof) =
+            server.evaluate_poprf(&blinded_element, b"tenant 2", &mut rng)?;
+        assert!(matches!(
+            client.finalize(&evaluation_element, &proof),
+            Err(InternalPakeError::InvalidProofError)
+        ));
+
+        // A batch cannot mix clients with different info
+        let (client2, blinded_element2) = PoprfClient::<RistrettoPoint, Sha512>::blind(
+            b"hunter2",
+            b"tenant 2",
+            &public_key,
+            &mut rng,
+        )?;
+        let (evaluation_elements, proof) = server.evaluate_poprf_batch(
+            &[blinded_element, blinded_element2],
+            b"tenant 1",
+            &mut rng,
+        )?;
+        assert!(matches!(
+            PoprfClient::finalize_batch(&[client, client2], &evaluation_elements, &proof),
+            Err(InternalPakeError::InvalidPoprfInfoError)
+        ));
+        Ok(())
     }
 }
@@ -5,7 +5,7 @@
 
 use crate::{
     ciphersuite::CipherSuite,
-    envelope::{Envelope, InnerEnvelopeMode},
+    envelope::{Envelope, IdentifierMode, InnerEnvelopeMode},
     group::Group,
     key_exchange::{
         traits::{KeyExchange, ToBytes},
@@ -65,15 +65,11 @@ fn client_registration_roundtrip() {
 
 #[test]
 fn server_registration_roundtrip() {
-    // If we don't have envelope and client_pk, the server registration just
-    // contains the prf key
+    // If we don't have envelope and client_pk, the server registration is
+    // empty
     let mut rng = OsRng;
-    let oprf_key = <RistrettoPoint as Group>::random_scalar(&mut rng);
-    let mut oprf_bytes: Vec<u8> = vec![];
-    oprf_bytes.extend_from_slice(oprf_key.as_bytes());
-    let reg = ServerRegistration::<Default>::deserialize(&oprf_bytes[..]).unwrap();
-    let reg_bytes = reg.serialize();
-    assert_eq!(reg_bytes, oprf_bytes);
+    let reg = ServerRegistration::<Default>::deserialize(&[]).unwrap();
+    assert!(reg.serialize().is_empty());
 
     let mut ciphertext = [0u8; 32];
     rng.fill_bytes(&mut ciphertext);
@@ -86,9 +82,8 @@ fn server_registration_roundtrip() {
     mock_envelope_bytes.extend_from_slice(&[0; MAC_SIZE]); // length-MAC_SIZE hmac
 
     let mock_client_kp = Default::generate_random_keypair(&mut rng);
-    // serialization order: oprf_key, public key, envelope
+    // serialization order: public key, envelope
     let mut bytes = Vec::<u8>::new();
-    bytes.extend_from_slice(oprf_key.as_bytes());
     bytes.extend_from_slice(&mock_client_kp.public().to_arr());
     bytes.extend_from_slice(&mock_envelope_bytes);
     let reg = ServerRegistration::<Default>::deserialize(&bytes[..]).unwrap();
@@ -96,6 +91,17 @@ fn server_registration_roundtrip() {
     assert_eq!(reg_bytes, bytes);
 }
 
+#[test]
+fn oprf_seed_roundtrip() {
+    let mut rng = OsRng;
+    let seed = OprfSeed::<Default>::generate_random(&mut rng);
+    let seed_bytes = seed.serialize();
+    assert_eq!(seed_bytes.len(), 64);
+    let seed2 = OprfSeed::<Default>::deserialize(&seed_bytes[..]).unwrap();
+    assert_eq!(seed2.serialize(), seed_bytes);
+    assert!(OprfSeed::<Default>::deserialize(&seed_bytes[1..]).is_err());
+}
+
 #[test]
 fn registration_request_roundtrip() {
     let pt = random_ristretto_point();
@@ -119,7 +125,7 @@ fn registration_response_roundtrip() {
 
     let mut input = Vec::new();
     input.extend_from_slice(beta_bytes.as_slice());
-    input.extend_from_slice(&pubkey_bytes.as_slice());
+    input.extend_from_slice(pubkey_bytes.as_slice());
 
     let r2 = RegistrationResponse::<Default>::deserialize(input.as_slice()).unwrap();
     let r2_bytes = r2.serialize();
@@ -138,12 +144,15 @@ fn registration_upload_roundtrip() {
     let mut msg = [0u8; 32];
     rng.fill_bytes(&mut msg);
 
-    let (envelope, _) = Envelope::<sha2::Sha512>::seal_raw(
+    let (envelope, _) = Envelope::<Default>::seal_raw(
         &mut rng,
         &key,
         &msg,
+        None,
         &pubkey_bytes,
         InnerEnvelopeMode::Base,
+        IdentifierMode::Base,
+        None,
     )
     .unwrap();
     let envelope_bytes = envelope.serialize();
@@ -172,8 +181,8 @@ fn credential_request_roundtrip() {
 
     let ke1m: Vec<u8> = [
         &client_nonce[..],
-        &serialize(&info.to_vec(), 2),
-        &client_e_kp.public(),
+        &serialize(&info, 2),
+        client_e_kp.public(),
     ]
     .concat();
 
@@ -201,12 +210,15 @@ fn credential_response_roundtrip() {
     let mut msg = [0u8; 32];
     rng.fill_bytes(&mut msg);
 
-    let (envelope, _) = Envelope::<sha2::Sha512>::seal_raw(
+    let (envelope, _) = Envelope::<Default>::seal_raw(
         &mut rng,
         &key,
         &msg,
+        None,
         &pubkey_bytes,
         InnerEnvelopeMode::Base,
+        IdentifierMode::Base,
+        None,
     )
     .unwrap();
 
@@ -221,17 +233,19 @@ fn credential_response_roundtrip() {
 
     let ke2m: Vec<u8> = [
         &server_nonce[..],
-        &server_e_kp.public(),
-        &serialize(&e_info.to_vec(), 2),
+        server_e_kp.public(),
+        &serialize(&e_info, 2),
         &mac[..],
     ]
     .concat();
 
     let mut input = Vec::new();
     input.extend_from_slice(pt_bytes.as_slice());
-    input.extend_from_slice(&pubkey_bytes.as_slice());
+    input.extend_from_slice(pubkey_bytes.as_slice());
     input.extend_from_slice(&envelope.serialize());
     input.extend_from_slice(&ke2m[..]);
+    // The re-seal request
+    input.push(1);
 
     let l2 = CredentialResponse::<Default>::deserialize(&input).unwrap();
     let l2_bytes = l2.serialize();
@@ -262,12 +276,14 @@ fn client_login_roundtrip() {
     rng.fill_bytes(&mut client_nonce);
 
     let serialized_credential_request = b"serialized credential_request".to_vec();
+    let context = b"context".to_vec();
     let l1_data = [client_e_kp.private().to_arr().to_vec(), client_nonce].concat();
 
-    // serialization order: scalar, credential_request, ke1_state, password
+    // serialization order: scalar, credential_request, context, ke1_state, password
     let bytes: Vec<u8> = [
         &sc.as_bytes()[..],
         &serialize(&serialized_credential_request, 2),
+        &serialize(&context, 2),
         &serialize(&l1_data, 2),
         &pw[..],
     ]
@@ -290,8 +306,8 @@ fn ke1_message_roundtrip() {
 
     let ke1m: Vec<u8> = [
         &client_nonce[..],
-        &serialize(&info.to_vec(), 2),
-        &client_e_kp.public(),
+        &serialize(&info, 2),
+        client_e_kp.public(),
     ]
     .concat();
     let reg =
@@ -315,8 +331,8 @@ fn ke2_message_roundtrip() {
 
     let ke2m: Vec<u8> = [
         &server_nonce[..],
-        &server_e_kp.public(),
-        &serialize(&e_info.to_vec(), 2),
+        server_e_kp.public(),
+        &serialize(&e_info, 2),
         &mac[..],
     ]
     .concat();
@@ -352,52 +368,52 @@ fn test_i2osp_os2ip(bytes in vec(any::<u8>(), 0..std::mem::size_of::<usize>()))
 
 #[test]
 fn test_nocrash_registration_request(bytes in vec(any::<u8>(), 0..200)) {
-    RegistrationRequest::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = RegistrationRequest::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_registration_response(bytes in vec(any::<u8>(), 0..200)) {
-    RegistrationResponse::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = RegistrationResponse::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_registration_upload(bytes in vec(any::<u8>(), 0..200)) {
-    RegistrationUpload::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = RegistrationUpload::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_credential_request(bytes in vec(any::<u8>(), 0..500)) {
-    CredentialRequest::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = CredentialRequest::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_credential_response(bytes in vec(any::<u8>(), 0..500)) {
-    CredentialResponse::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = CredentialResponse::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_credential_finalization(bytes in vec(any::<u8>(), 0..500)) {
-    CredentialFinalization::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = CredentialFinalization::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_client_registration(bytes in vec(any::<u8>(), 0..700)) {
-    ClientRegistration::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = ClientRegistration::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_server_registration(bytes in vec(any::<u8>(), 0..700)) {
-    ServerRegistration::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = ServerRegistration::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_client_login(bytes in vec(any::<u8>(), 0..700)) {
-    ClientLogin::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = ClientLogin::<Default>::deserialize(&bytes[..]);
 }
 
 #[test]
 fn test_nocrash_server_login(bytes in vec(any::<u8>(), 0..700)) {
-    ServerLogin::<Default>::deserialize(&bytes[..]).map_or(true, |_| true);
+    let _ = ServerLogin::<Default>::deserialize(&bytes[..]);
 }
 
 }
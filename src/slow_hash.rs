@@ -7,47 +7,755 @@
 
 use crate::{errors::InternalPakeError, hash::Hash};
 use digest::Digest;
-#[cfg(feature = "slow-hash")]
+#[cfg(any(feature = "slow-hash", feature = "argon2", feature = "pbkdf2"))]
 use generic_array::typenum::Unsigned;
 use generic_array::GenericArray;
 
-/// Used for the slow hashing function in OPAQUE
-pub trait SlowHash<D: Hash> {
+/// Used for the slow hashing function in OPAQUE. An instance carries the
+/// cost parameters of the function, so that clients can choose them at
+/// runtime.
+pub trait SlowHash<D: Hash>: Sized {
+    /// Identifies the algorithm in a serialized parameter block
+    const ALGORITHM_ID: u8;
+
+    /// The instance used when the caller does not supply one
+    fn recommended() -> Self;
+
+    /// The most expensive instance which a client computes at login for
+    /// parameters stored with its envelope, when the caller does not supply
+    /// its own maximum
+    fn maximum() -> Self;
+
     /// Computes the slow hashing function
     fn hash(
+        &self,
         input: GenericArray<u8, <D as Digest>::OutputSize>,
     ) -> Result<Vec<u8>, InternalPakeError>;
+
+    /// Serializes the cost parameters and salt of this instance
+    fn serialize_parameters(&self) -> Vec<u8>;
+
+    /// Reconstructs an instance from its serialized cost parameters and salt
+    fn deserialize_parameters(input: &[u8]) -> Result<Self, InternalPakeError>;
+
+    /// Checks that the costs of this instance are no lower than those of
+    /// `minimum`
+    fn meets_minimum(&self, minimum: &Self) -> bool;
+
+    /// Checks that the costs of this instance are no higher than those of
+    /// `maximum`
+    fn within_maximum(&self, maximum: &Self) -> bool;
+}
+
+// The parameter block stored with an envelope consists of the algorithm
+// identifier, followed by the serialized parameters
+pub(crate) fn serialize_parameter_block<D: Hash, SH: SlowHash<D>>(slow_hash: &SH) -> Vec<u8> {
+    [&[SH::ALGORITHM_ID][..], &slow_hash.serialize_parameters()].concat()
+}
+
+pub(crate) fn deserialize_parameter_block<D: Hash, SH: SlowHash<D>>(
+    input: &[u8],
+) -> Result<SH, InternalPakeError> {
+    match input.split_first() {
+        Some((&algorithm_id, parameters)) if algorithm_id == SH::ALGORITHM_ID => {
+            SH::deserialize_parameters(parameters)
+        }
+        _ => Err(InternalPakeError::InvalidSlowHashParametersError),
+    }
 }
 
 /// A no-op hash which simply returns its input
+#[derive(Clone, Copy, Default)]
 pub struct NoOpHash;
 
 impl<D: Hash> SlowHash<D> for NoOpHash {
+    const ALGORITHM_ID: u8 = 0;
+
+    fn recommended() -> Self {
+        Self
+    }
+
+    fn maximum() -> Self {
+        Self
+    }
+
     fn hash(
+        &self,
         input: GenericArray<u8, <D as Digest>::OutputSize>,
     ) -> Result<Vec<u8>, InternalPakeError> {
         Ok(input.to_vec())
     }
+
+    fn serialize_parameters(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
+    fn deserialize_parameters(input: &[u8]) -> Result<Self, InternalPakeError> {
+        if !input.is_empty() {
+            return Err(InternalPakeError::InvalidSlowHashParametersError);
+        }
+        Ok(Self)
+    }
+
+    fn meets_minimum(&self, _minimum: &Self) -> bool {
+        true
+    }
+
+    fn within_maximum(&self, _maximum: &Self) -> bool {
+        true
+    }
 }
 
+// The recommended parameters of the scrypt crate: N = 2^15, r = 8, p = 1
+#[cfg(feature = "slow-hash")]
+const DEFAULT_SCRYPT_LOG_N: u8 = 15;
+#[cfg(feature = "slow-hash")]
+const DEFAULT_SCRYPT_R: u32 = 8;
+#[cfg(feature = "slow-hash")]
+const DEFAULT_SCRYPT_P: u32 = 1;
+// The maximum parameters, which allow for up to 4 GiB of memory: N = 2^20,
+// r = 32, p = 16
+#[cfg(feature = "slow-hash")]
+const MAX_SCRYPT_LOG_N: u8 = 20;
 #[cfg(feature = "slow-hash")]
-const DEFAULT_SCRYPT_LOG_N: u8 = 15u8;
+const MAX_SCRYPT_R: u32 = 32;
 #[cfg(feature = "slow-hash")]
-const DEFAULT_SCRYPT_R: u32 = 8u32;
+const MAX_SCRYPT_P: u32 = 16;
+
+/// The scrypt memory-hard function, with cost parameters which are chosen
+/// at runtime. The default instance uses `log_n = 15`, `r = 8` and `p = 1`,
+/// and the maximum one `log_n = 20`, `r = 32` and `p = 16`.
 #[cfg(feature = "slow-hash")]
-const DEFAULT_SCRYPT_P: u32 = 1u32;
+#[derive(Clone, Copy)]
+pub struct Scrypt {
+    log_n: u8,
+    r: u32,
+    p: u32,
+}
+
+#[cfg(feature = "slow-hash")]
+impl Scrypt {
+    /// Checks and sets the cost parameters, where `log_n` is the base-2
+    /// logarithm of the CPU/memory cost `N`
+    pub fn new(log_n: u8, r: u32, p: u32) -> Result<Self, InternalPakeError> {
+        let scrypt = Self { log_n, r, p };
+        scrypt.params()?;
+        Ok(scrypt)
+    }
+
+    fn params(&self) -> Result<scrypt::ScryptParams, InternalPakeError> {
+        scrypt::ScryptParams::new(self.log_n, self.r, self.p)
+            .map_err(|_| InternalPakeError::SlowHashError)
+    }
+}
 
 #[cfg(feature = "slow-hash")]
-impl<D: Hash> SlowHash<D> for scrypt::ScryptParams {
+impl Default for Scrypt {
+    fn default() -> Self {
+        Self {
+            log_n: DEFAULT_SCRYPT_LOG_N,
+            r: DEFAULT_SCRYPT_R,
+            p: DEFAULT_SCRYPT_P,
+        }
+    }
+}
+
+#[cfg(feature = "slow-hash")]
+impl<D: Hash> SlowHash<D> for Scrypt {
+    const ALGORITHM_ID: u8 = 1;
+
+    fn recommended() -> Self {
+        Self::default()
+    }
+
+    fn maximum() -> Self {
+        Self {
+            log_n: MAX_SCRYPT_LOG_N,
+            r: MAX_SCRYPT_R,
+            p: MAX_SCRYPT_P,
+        }
+    }
+
     fn hash(
+        &self,
         input: GenericArray<u8, <D as Digest>::OutputSize>,
     ) -> Result<Vec<u8>, InternalPakeError> {
-        let params =
-            scrypt::ScryptParams::new(DEFAULT_SCRYPT_LOG_N, DEFAULT_SCRYPT_R, DEFAULT_SCRYPT_P)
-                .map_err(|_| InternalPakeError::SlowHashError)?;
         let mut output = vec![0u8; <D as Digest>::OutputSize::to_usize()];
-        scrypt::scrypt(&input, &[], &params, &mut output)
+        scrypt::scrypt(&input, &[], &self.params()?, &mut output)
             .map_err(|_| InternalPakeError::SlowHashError)?;
         Ok(output)
     }
+
+    /// The format of the output is:
+    /// log_n | r      | p
+    /// u8    | u32 BE | u32 BE
+    fn serialize_parameters(&self) -> Vec<u8> {
+        [
+            &[self.log_n][..],
+            &self.r.to_be_bytes(),
+            &self.p.to_be_bytes(),
+        ]
+        .concat()
+    }
+
+    fn deserialize_parameters(input: &[u8]) -> Result<Self, InternalPakeError> {
+        if input.len() != 9 {
+            return Err(InternalPakeError::InvalidSlowHashParametersError);
+        }
+        Self::new(input[0], read_u32(&input[1..5]), read_u32(&input[5..9]))
+            .map_err(|_| InternalPakeError::InvalidSlowHashParametersError)
+    }
+
+    fn meets_minimum(&self, minimum: &Self) -> bool {
+        self.log_n >= minimum.log_n && self.r >= minimum.r && self.p >= minimum.p
+    }
+
+    fn within_maximum(&self, maximum: &Self) -> bool {
+        self.log_n <= maximum.log_n && self.r <= maximum.r && self.p <= maximum.p
+    }
+}
+
+// The recommended parameters of RFC 9106, Section 4, for environments in
+// which 2 GiB of memory per hash is too much: 64 MiB, 3 passes, 4 lanes
+#[cfg(feature = "argon2")]
+const DEFAULT_ARGON2_MEMORY_COST: u32 = 1 << 16;
+#[cfg(feature = "argon2")]
+const DEFAULT_ARGON2_ITERATIONS: u32 = 3;
+#[cfg(feature = "argon2")]
+const DEFAULT_ARGON2_PARALLELISM: u32 = 4;
+// The salt used when none is supplied, as in the OPAQUE specification
+#[cfg(feature = "argon2")]
+const DEFAULT_ARGON2_SALT: [u8; 16] = [0u8; 16];
+// The maximum parameters, following the first recommendation of RFC 9106,
+// Section 4, with room for more passes and lanes: 2 GiB, 16 passes, 16 lanes
+#[cfg(feature = "argon2")]
+const MAX_ARGON2_MEMORY_COST: u32 = 1 << 21;
+#[cfg(feature = "argon2")]
+const MAX_ARGON2_ITERATIONS: u32 = 16;
+#[cfg(feature = "argon2")]
+const MAX_ARGON2_PARALLELISM: u32 = 16;
+
+// The maximum length of a salt, which bounds the size of the parameter
+// blocks that a client accepts from the server
+#[cfg(any(feature = "argon2", feature = "pbkdf2"))]
+const MAX_SALT_LEN: usize = 64;
+
+/// The Argon2id memory-hard function, with cost parameters and an optional
+/// salt which are chosen at runtime. The default instance uses the
+/// recommended parameters of RFC 9106, and the maximum one allows for 2 GiB
+/// of memory, 16 passes and 16 lanes.
+#[cfg(feature = "argon2")]
+#[derive(Clone)]
+pub struct Argon2id {
+    memory_cost: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "argon2")]
+impl Argon2id {
+    /// Checks and sets the cost parameters, where `memory_cost` is in KiB.
+    /// If specified, the salt must be between 8 and 64 bytes long.
+    pub fn new(
+        memory_cost: u32,
+        iterations: u32,
+        parallelism: u32,
+        salt: Option<Vec<u8>>,
+    ) -> Result<Self, InternalPakeError> {
+        let argon2id = Self {
+            memory_cost,
+            iterations,
+            parallelism,
+            salt,
+        };
+        argon2id.params(None)?;
+        if argon2id.salt().len() < argon2::MIN_SALT_LEN || argon2id.salt().len() > MAX_SALT_LEN {
+            return Err(InternalPakeError::SlowHashError);
+        }
+        Ok(argon2id)
+    }
+
+    /// Computes Argon2id over the input, with an output of `output_len` bytes
+    pub fn stretch(&self, input: &[u8], output_len: usize) -> Result<Vec<u8>, InternalPakeError> {
+        let argon2 = argon2::Argon2::new(
+            argon2::Algorithm::Argon2id,
+            argon2::Version::V0x13,
+            self.params(Some(output_len))?,
+        );
+        let mut output = vec![0u8; output_len];
+        argon2
+            .hash_password_into(input, self.salt(), &mut output)
+            .map_err(|_| InternalPakeError::SlowHashError)?;
+        Ok(output)
+    }
+
+    fn params(&self, output_len: Option<usize>) -> Result<argon2::Params, InternalPakeError> {
+        argon2::Params::new(
+            self.memory_cost,
+            self.iterations,
+            self.parallelism,
+            output_len,
+        )
+        .map_err(|_| InternalPakeError::SlowHashError)
+    }
+
+    fn salt(&self) -> &[u8] {
+        self.salt.as_deref().unwrap_or(&DEFAULT_ARGON2_SALT)
+    }
+}
+
+#[cfg(feature = "argon2")]
+impl Default for Argon2id {
+    fn default() -> Self {
+        Self {
+            memory_cost: DEFAULT_ARGON2_MEMORY_COST,
+            iterations: DEFAULT_ARGON2_ITERATIONS,
+            parallelism: DEFAULT_ARGON2_PARALLELISM,
+            salt: None,
+        }
+    }
+}
+
+#[cfg(feature = "argon2")]
+impl<D: Hash> SlowHash<D> for Argon2id {
+    const ALGORITHM_ID: u8 = 2;
+
+    fn recommended() -> Self {
+        Self::default()
+    }
+
+    fn maximum() -> Self {
+        Self {
+            memory_cost: MAX_ARGON2_MEMORY_COST,
+            iterations: MAX_ARGON2_ITERATIONS,
+            parallelism: MAX_ARGON2_PARALLELISM,
+            salt: None,
+        }
+    }
+
+    fn hash(
+        &self,
+        input: GenericArray<u8, <D as Digest>::OutputSize>,
+    ) -> Result<Vec<u8>, InternalPakeError> {
+        self.stretch(&input, <D as Digest>::OutputSize::to_usize())
+    }
+
+    /// The format of the output is:
+    /// memory_cost | iterations | parallelism | salt
+    /// u32 BE      | u32 BE     | u32 BE      | variable length
+    ///
+    /// where an empty salt stands for the default one.
+    fn serialize_parameters(&self) -> Vec<u8> {
+        [
+            &self.memory_cost.to_be_bytes()[..],
+            &self.iterations.to_be_bytes(),
+            &self.parallelism.to_be_bytes(),
+            self.salt.as_deref().unwrap_or(&[]),
+        ]
+        .concat()
+    }
+
+    fn deserialize_parameters(input: &[u8]) -> Result<Self, InternalPakeError> {
+        if input.len() < 12 {
+            return Err(InternalPakeError::InvalidSlowHashParametersError);
+        }
+        let salt = match &input[12..] {
+            [] => None,
+            salt => Some(salt.to_vec()),
+        };
+        Self::new(
+            read_u32(&input[..4]),
+            read_u32(&input[4..8]),
+            read_u32(&input[8..12]),
+            salt,
+        )
+        .map_err(|_| InternalPakeError::InvalidSlowHashParametersError)
+    }
+
+    fn meets_minimum(&self, minimum: &Self) -> bool {
+        self.memory_cost >= minimum.memory_cost && self.iterations >= minimum.iterations
+    }
+
+    fn within_maximum(&self, maximum: &Self) -> bool {
+        self.memory_cost <= maximum.memory_cost
+            && self.iterations <= maximum.iterations
+            && self.parallelism <= maximum.parallelism
+    }
+}
+
+// The OWASP recommendation for PBKDF2-HMAC-SHA256, which is conservative
+// for suites using SHA-384 or SHA-512
+#[cfg(feature = "pbkdf2")]
+const DEFAULT_PBKDF2_ITERATIONS: u32 = 600_000;
+#[cfg(feature = "pbkdf2")]
+const MAX_PBKDF2_ITERATIONS: u32 = 10_000_000;
+
+/// PBKDF2, as specified in RFC 8018, with HMAC over the hash function of the
+/// ciphersuite as its pseudorandom function. The iteration count and an
+/// optional salt are chosen at runtime. The maximum instance allows for
+/// 10,000,000 iterations. This is not memory-hard, and is only meant for
+/// clients which cannot afford scrypt or Argon2id.
+#[cfg(feature = "pbkdf2")]
+#[derive(Clone)]
+pub struct Pbkdf2 {
+    iterations: u32,
+    salt: Option<Vec<u8>>,
+}
+
+#[cfg(feature = "pbkdf2")]
+impl Pbkdf2 {
+    /// Checks and sets the iteration count, which must be nonzero. If not
+    /// specified, the salt is empty, and otherwise it must be at most 64
+    /// bytes long.
+    pub fn new(iterations: u32, salt: Option<Vec<u8>>) -> Result<Self, InternalPakeError> {
+        if iterations == 0 || matches!(&salt, Some(salt) if salt.len() > MAX_SALT_LEN) {
+            return Err(InternalPakeError::SlowHashError);
+        }
+        Ok(Self { iterations, salt })
+    }
+
+    /// Computes PBKDF2-HMAC with the hash function `D` over the input, with
+    /// an output of `output_len` bytes
+    pub fn stretch<D: Hash + Sync>(&self, input: &[u8], output_len: usize) -> Vec<u8> {
+        let mut output = vec![0u8; output_len];
+        pbkdf2::pbkdf2::<hmac::Hmac<D>>(
+            input,
+            self.salt.as_deref().unwrap_or(&[]),
+            self.iterations,
+            &mut output,
+        );
+        output
+    }
+}
+
+#[cfg(feature = "pbkdf2")]
+impl Default for Pbkdf2 {
+    fn default() -> Self {
+        Self {
+            iterations: DEFAULT_PBKDF2_ITERATIONS,
+            salt: None,
+        }
+    }
+}
+
+#[cfg(feature = "pbkdf2")]
+impl<D: Hash + Sync> SlowHash<D> for Pbkdf2 {
+    const ALGORITHM_ID: u8 = 3;
+
+    fn recommended() -> Self {
+        Self::default()
+    }
+
+    fn maximum() -> Self {
+        Self {
+            iterations: MAX_PBKDF2_ITERATIONS,
+            salt: None,
+        }
+    }
+
+    fn hash(
+        &self,
+        input: GenericArray<u8, <D as Digest>::OutputSize>,
+    ) -> Result<Vec<u8>, InternalPakeError> {
+        Ok(self.stretch::<D>(&input, <D as Digest>::OutputSize::to_usize()))
+    }
+
+    /// The format of the output is:
+    /// iterations | salt
+    /// u32 BE     | variable length
+    fn serialize_parameters(&self) -> Vec<u8> {
+        [
+            &self.iterations.to_be_bytes()[..],
+            self.salt.as_deref().unwrap_or(&[]),
+        ]
+        .concat()
+    }
+
+    fn deserialize_parameters(input: &[u8]) -> Result<Self, InternalPakeError> {
+        if input.len() < 4 {
+            return Err(InternalPakeError::InvalidSlowHashParametersError);
+        }
+        let salt = match &input[4..] {
+            [] => None,
+            salt => Some(salt.to_vec()),
+        };
+        Self::new(read_u32(&input[..4]), salt)
+            .map_err(|_| InternalPakeError::InvalidSlowHashParametersError)
+    }
+
+    fn meets_minimum(&self, minimum: &Self) -> bool {
+        self.iterations >= minimum.iterations
+    }
+
+    fn within_maximum(&self, maximum: &Self) -> bool {
+        self.iterations <= maximum.iterations
+    }
+}
+
+#[cfg(any(feature = "slow-hash", feature = "argon2", feature = "pbkdf2"))]
+fn read_u32(input: &[u8]) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(input);
+    u32::from_be_bytes(bytes)
+}
+
+#[cfg(all(
+    test,
+    any(feature = "slow-hash", feature = "argon2", feature = "pbkdf2")
+))]
+mod tests {
+    use super::*;
+    use generic_array::typenum::U64;
+
+    fn kat_input() -> GenericArray<u8, U64> {
+        GenericArray::clone_from_slice(&(0u8..64).collect::<Vec<u8>>())
+    }
+
+    fn parameter_block_roundtrip<SH: SlowHash<sha2::Sha512>>(
+        slow_hash: &SH,
+    ) -> Result<SH, InternalPakeError> {
+        let block = serialize_parameter_block::<sha2::Sha512, SH>(slow_hash);
+        assert_eq!(SH::ALGORITHM_ID, block[0]);
+        deserialize_parameter_block::<sha2::Sha512, SH>(&block)
+    }
+
+    #[cfg(feature = "slow-hash")]
+    #[test]
+    fn scrypt_kat() -> Result<(), InternalPakeError> {
+        let scrypt = Scrypt::new(4, 8, 1)?;
+        assert_eq!(
+            "6852b0805ce5f325e8fedc882da5489482638271853d319cd37824c306bca27128c110d932ed4c87aaa1d3841feeb396941edacfb0b78c4851f5e365ba91a5a9",
+            hex::encode(<Scrypt as SlowHash<sha2::Sha512>>::hash(&scrypt, kat_input())?)
+        );
+        assert_eq!(
+            "24430027dcd441e03a1510f06fab51c693e3a4d46bad0ded447fce2de02e34872612be5b67b68ccc87eeaee5c88d86b32b6425af0be3b16a6a1a38b5c3741152",
+            hex::encode(<Scrypt as SlowHash<sha2::Sha512>>::hash(&Scrypt::default(), kat_input())?)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "slow-hash")]
+    #[test]
+    fn scrypt_parameters() -> Result<(), InternalPakeError> {
+        let scrypt = Scrypt::new(4, 8, 2)?;
+        let block = serialize_parameter_block::<sha2::Sha512, Scrypt>(&scrypt);
+        assert_eq!("01040000000800000002", hex::encode(&block));
+        let recovered = parameter_block_roundtrip(&scrypt)?;
+        assert_eq!(
+            block,
+            serialize_parameter_block::<sha2::Sha512, Scrypt>(&recovered)
+        );
+
+        let minimum = Scrypt::new(4, 8, 1)?;
+        assert!(<Scrypt as SlowHash<sha2::Sha512>>::meets_minimum(
+            &scrypt, &minimum
+        ));
+        assert!(!<Scrypt as SlowHash<sha2::Sha512>>::meets_minimum(
+            &minimum, &scrypt
+        ));
+        assert!(!<Scrypt as SlowHash<sha2::Sha512>>::meets_minimum(
+            &scrypt,
+            &Scrypt::default()
+        ));
+        assert!(<Scrypt as SlowHash<sha2::Sha512>>::within_maximum(
+            &scrypt, &scrypt
+        ));
+        assert!(!<Scrypt as SlowHash<sha2::Sha512>>::within_maximum(
+            &scrypt, &minimum
+        ));
+        assert!(!<Scrypt as SlowHash<sha2::Sha512>>::within_maximum(
+            &Scrypt::new(21, 8, 1)?,
+            &<Scrypt as SlowHash<sha2::Sha512>>::maximum()
+        ));
+
+        // Truncated, invalid and foreign parameter blocks are rejected
+        assert!(deserialize_parameter_block::<sha2::Sha512, Scrypt>(&block[..9]).is_err());
+        assert!(deserialize_parameter_block::<sha2::Sha512, Scrypt>(
+            &[&block[..5], &[0u8; 4][..], &block[9..]].concat()
+        )
+        .is_err());
+        assert!(deserialize_parameter_block::<sha2::Sha512, Scrypt>(&[0u8]).is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2id_with_salt() -> Result<(), InternalPakeError> {
+        let argon2id = Argon2id::new(256, 2, 1, Some(b"saltsaltsaltsalt".to_vec()))?;
+        assert_eq!(
+            "99a3f3882aa41d797fae4c298c8b015aaaac1adeb009af70559fce2a4bd09dfe0ad4e5fea2691ec7e7fb8af09e0d20abc08ff9b397a6677b8a67368d4bf6a95b",
+            hex::encode(argon2id.stretch(&kat_input(), 64)?)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2id_without_salt() -> Result<(), InternalPakeError> {
+        let argon2id = Argon2id::new(64, 3, 2, None)?;
+        assert_eq!(
+            "cd9c3aba9191f345bca587b543302f810d36f89a7496efb8a14fd7611e1524192e1009d212a744c57e3eabdbcc283ec809ecba405306a0f6eed36ba75bfef12b",
+            hex::encode(argon2id.stretch(&kat_input(), 64)?)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2id_default_parameters() -> Result<(), InternalPakeError> {
+        // The password-derived key for a suite using SHA-512
+        assert_eq!(
+            "763c05e205e6d06f9d49921578c5fc314590d8016bd8ccc98049f3da265fad5d4a27e85aaac6ac1de7cf2aeda7b8c767de0ff4e5db3ff8421d9bb3e8effb279b",
+            hex::encode(<Argon2id as SlowHash<sha2::Sha512>>::hash(&Argon2id::default(), kat_input())?)
+        );
+        // The password-derived key for a suite using SHA-256
+        let input = GenericArray::clone_from_slice(&kat_input()[..32]);
+        assert_eq!(
+            "a9355e05c909f5f212d23131e6ffe257af1fd548a3909cd20c0f3885ae03b8c9",
+            hex::encode(<Argon2id as SlowHash<sha2::Sha256>>::hash(
+                &Argon2id::default(),
+                input
+            )?)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2id_rejects_invalid_parameters() {
+        // Too little memory for the number of lanes
+        assert!(Argon2id::new(4, 1, 1, None).is_err());
+        assert!(Argon2id::new(64, 0, 1, None).is_err());
+        assert!(Argon2id::new(64, 1, 0, None).is_err());
+        // Salt shorter than 8 bytes or longer than 64 bytes
+        assert!(Argon2id::new(64, 1, 1, Some(b"salt".to_vec())).is_err());
+        assert!(Argon2id::new(64, 1, 1, Some(vec![0u8; 65])).is_err());
+    }
+
+    #[cfg(feature = "argon2")]
+    #[test]
+    fn argon2id_parameters() -> Result<(), InternalPakeError> {
+        let salted = Argon2id::new(256, 2, 1, Some(b"saltsaltsaltsalt".to_vec()))?;
+        assert_eq!(
+            "0200000100000000020000000173616c7473616c7473616c7473616c74",
+            hex::encode(serialize_parameter_block::<sha2::Sha512, Argon2id>(&salted))
+        );
+        let recovered = parameter_block_roundtrip(&salted)?;
+        assert_eq!(
+            salted.stretch(&kat_input(), 64)?,
+            recovered.stretch(&kat_input(), 64)?
+        );
+
+        let unsalted = Argon2id::new(64, 3, 2, None)?;
+        let recovered = parameter_block_roundtrip(&unsalted)?;
+        assert_eq!(
+            unsalted.stretch(&kat_input(), 64)?,
+            recovered.stretch(&kat_input(), 64)?
+        );
+
+        assert!(<Argon2id as SlowHash<sha2::Sha512>>::meets_minimum(
+            &salted, &salted
+        ));
+        assert!(!<Argon2id as SlowHash<sha2::Sha512>>::meets_minimum(
+            &salted, &unsalted
+        ));
+        assert!(!<Argon2id as SlowHash<sha2::Sha512>>::meets_minimum(
+            &unsalted,
+            &Argon2id::default()
+        ));
+        assert!(<Argon2id as SlowHash<sha2::Sha512>>::within_maximum(
+            &salted,
+            &<Argon2id as SlowHash<sha2::Sha512>>::maximum()
+        ));
+        assert!(!<Argon2id as SlowHash<sha2::Sha512>>::within_maximum(
+            &unsalted, &salted
+        ));
+
+        // Salts shorter than 8 bytes or longer than 64 bytes are rejected
+        let block = serialize_parameter_block::<sha2::Sha512, Argon2id>(&salted);
+        assert!(deserialize_parameter_block::<sha2::Sha512, Argon2id>(&block[..17]).is_err());
+        assert!(deserialize_parameter_block::<sha2::Sha512, Argon2id>(
+            &[&block[..13], &[0u8; 65][..]].concat()
+        )
+        .is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "pbkdf2")]
+    #[test]
+    fn pbkdf2_rfc7914_vectors() -> Result<(), InternalPakeError> {
+        // The PBKDF2-HMAC-SHA256 test vectors of RFC 7914, Section 11
+        assert_eq!(
+            "55ac046e56e3089fec1691c22544b605f94185216dde0465e68b9d57c20dacbc49ca9cccf179b645991664b39d77ef317c71b845b1e30bd509112041d3a19783",
+            hex::encode(Pbkdf2::new(1, Some(b"salt".to_vec()))?.stretch::<sha2::Sha256>(b"passwd", 64))
+        );
+        assert_eq!(
+            "4ddcd8f60b98be21830cee5ef22701f9641a4418d04c0414aeff08876b34ab56a1d425a1225833549adb841b51c9b3176a272bdebba1d078478f62b397f33c8d",
+            hex::encode(Pbkdf2::new(80000, Some(b"NaCl".to_vec()))?.stretch::<sha2::Sha256>(b"Password", 64))
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "pbkdf2")]
+    #[test]
+    fn pbkdf2_kat() -> Result<(), InternalPakeError> {
+        let salted = Pbkdf2::new(1000, Some(b"saltsaltsaltsalt".to_vec()))?;
+        assert_eq!(
+            "e1cdf406601ffb18d49e1da9ffcf48a2e19aa09813b700c0254118207d963352a806f88781cbe07362d41963fc8cc5d8c5177f2058b6ce7b0ba61b3ce93c8b58",
+            hex::encode(<Pbkdf2 as SlowHash<sha2::Sha512>>::hash(&salted, kat_input())?)
+        );
+        let unsalted = Pbkdf2::new(1000, None)?;
+        assert_eq!(
+            "43e1a31364d7c164513faea22d45bbfb52cbabb151e8e3d546720a85b67324350148413f89bb2650d1e51b52e1492a079cc735386cbca8593ca2a726653349b5",
+            hex::encode(<Pbkdf2 as SlowHash<sha2::Sha512>>::hash(&unsalted, kat_input())?)
+        );
+        Ok(())
+    }
+
+    #[cfg(feature = "pbkdf2")]
+    #[test]
+    fn pbkdf2_parameters() -> Result<(), InternalPakeError> {
+        let salted = Pbkdf2::new(1000, Some(b"salt".to_vec()))?;
+        let block = serialize_parameter_block::<sha2::Sha512, Pbkdf2>(&salted);
+        assert_eq!("03000003e873616c74", hex::encode(&block));
+        let recovered = parameter_block_roundtrip(&salted)?;
+        assert_eq!(
+            block,
+            serialize_parameter_block::<sha2::Sha512, Pbkdf2>(&recovered)
+        );
+
+        let unsalted = Pbkdf2::new(10, None)?;
+        assert!(parameter_block_roundtrip(&unsalted)?.salt.is_none());
+
+        assert!(<Pbkdf2 as SlowHash<sha2::Sha512>>::meets_minimum(
+            &salted, &unsalted
+        ));
+        assert!(!<Pbkdf2 as SlowHash<sha2::Sha512>>::meets_minimum(
+            &unsalted, &salted
+        ));
+        assert!(!<Pbkdf2 as SlowHash<sha2::Sha512>>::meets_minimum(
+            &salted,
+            &Pbkdf2::default()
+        ));
+        assert!(<Pbkdf2 as SlowHash<sha2::Sha512>>::within_maximum(
+            &unsalted, &salted
+        ));
+        assert!(!<Pbkdf2 as SlowHash<sha2::Sha512>>::within_maximum(
+            &Pbkdf2::new(MAX_PBKDF2_ITERATIONS + 1, None)?,
+            &<Pbkdf2 as SlowHash<sha2::Sha512>>::maximum()
+        ));
+
+        // Zero iterations, oversized salts and truncated parameter blocks are
+        // rejected
+        assert!(Pbkdf2::new(0, None).is_err());
+        assert!(Pbkdf2::new(1000, Some(vec![0u8; 65])).is_err());
+        assert!(deserialize_parameter_block::<sha2::Sha512, Pbkdf2>(
+            &[&block[..5], &[0u8; 65][..]].concat()
+        )
+        .is_err());
+        assert!(deserialize_parameter_block::<sha2::Sha512, Pbkdf2>(&[3, 0, 0, 0, 0]).is_err());
+        assert!(deserialize_parameter_block::<sha2::Sha512, Pbkdf2>(&block[..4]).is_err());
+        Ok(())
+    }
 }
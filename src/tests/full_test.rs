@@ -5,21 +5,31 @@
 
 use crate::{
     ciphersuite::CipherSuite,
+    envelope::{IdentifierMode, InnerEnvelopeMode},
     errors::*,
     group::Group,
-    key_exchange::tripledh::{NonceLen, TripleDH},
-    keypair::{Key, SizedBytesExt},
+    key_exchange::{
+        hmqv::HMQV,
+        sigma::{Schnorr, SigmaI},
+        traits::KeyExchange,
+        tripledh::{NonceLen, TripleDH},
+    },
+    keypair::{KeyPair, PrivateKey, PublicKey, SizedBytesExt},
     opaque::*,
-    slow_hash::NoOpHash,
+    slow_hash::{NoOpHash, SlowHash},
     tests::mock_rng::CycleRng,
     *,
 };
 use curve25519_dalek::ristretto::RistrettoPoint;
-use generic_array::typenum::Unsigned;
+use digest::Digest;
+use generic_array::{typenum::Unsigned, GenericArray};
 use generic_bytes::SizedBytes;
 use rand::{rngs::OsRng, RngCore};
 use serde_json::Value;
 
+#[cfg(any(feature = "p256", feature = "p384"))]
+use crate::key_exchange::sigma::Ecdsa;
+
 // Tests
 // =====
 
@@ -89,57 +99,55 @@ static TEST_VECTOR: &str = r#"
     "registration_response": "aee5c937a85acfbdbd71faf1c5519bfe9e44b0b7489dcc663df9f1ca5b520b6ac21a38653eb19437669bfa066a446b6eea7c1f253ac7adf4798d6c68e171b273",
     "registration_upload": "6e0a6082dd29936c44b47ecb8a5fe72e4b321a0ac314b0080ca4c48afdabd2150278b006042d011bdca5d0058a978d2103a6d546de311a2e8cd025fbc67303a4687618b32fe2ec2a5c2b6efecec1e6e535106de80af68733673daf0b644965966fa3f279e532d7ecef363f8d55ff6df4c473cfb1049a73f632972bfcc6744185d13a671dd6678d49fb1629a6fbfbe266937378fd9e772c2dd72692d1a35c020010",
     "credential_request": "14ba86e53018ce5507d2bfb2d98ad3f60e302d826bff3410a5ec669c8e1ef17d43497a6f86ba31a6a7f399271fb3b4b4f82c4af086bc431ebaeab7d768ff8a640005696e666f315260ad6eb47ac1db44babcce9327327d50e1a0133c0425acca3efcf41b119718",
-    "credential_response": "aee5c937a85acfbdbd71faf1c5519bfe9e44b0b7489dcc663df9f1ca5b520b6ac21a38653eb19437669bfa066a446b6eea7c1f253ac7adf4798d6c68e171b2730278b006042d011bdca5d0058a978d2103a6d546de311a2e8cd025fbc67303a4687618b32fe2ec2a5c2b6efecec1e6e535106de80af68733673daf0b644965966fa3f279e532d7ecef363f8d55ff6df4c473cfb1049a73f632972bfcc6744185d13a671dd6678d49fb1629a6fbfbe266937378fd9e772c2dd72692d1a35c020010680cd27da18bc56d4317e7db61de1726a70ebe4e49aee38a3bdb6787cf85466e125f4a2dd9353c8c37a08527a323908835d3fbc374260d32e829d5c3fa81f32500068a4c321f3c375613862ba83f7e5abb8f1d26dbd8035d39f192eb1324c2214457098054a2cbdf5d4ab2894eaf152af8c4be61d701c5a1ab1ec3e1cee5810898140b81771db0be",
-    "credential_finalization": "330aa8ac01bbc9d9642fe1c286187379efe12da14aceab86b22449d21f242d89adabc0295751b4e007beabc413ae9cbf9979e324749953705fd85b87c9c1b1a2",
+    "credential_response": "aee5c937a85acfbdbd71faf1c5519bfe9e44b0b7489dcc663df9f1ca5b520b6ac21a38653eb19437669bfa066a446b6eea7c1f253ac7adf4798d6c68e171b2730278b006042d011bdca5d0058a978d2103a6d546de311a2e8cd025fbc67303a4687618b32fe2ec2a5c2b6efecec1e6e535106de80af68733673daf0b644965966fa3f279e532d7ecef363f8d55ff6df4c473cfb1049a73f632972bfcc6744185d13a671dd6678d49fb1629a6fbfbe266937378fd9e772c2dd72692d1a35c020010680cd27da18bc56d4317e7db61de1726a70ebe4e49aee38a3bdb6787cf85466e125f4a2dd9353c8c37a08527a323908835d3fbc374260d32e829d5c3fa81f325000656d105dea312b919a07c4248281f0f485bb80783842d79f6d5e5584e2a6a1fb12723bfbd077e391af48aed50cc26f3ca2d99ac7c5cf36c74fbda417757133f845716d307884400",
+    "credential_finalization": "cabfc8eafaa211244440b815d2ee71c42bb3d72ba2cb804b2ad1d987a647d43f78bca24c72e5dd7996f13fd519310538d9f100c81a9a6cf20e8fb3510c8a587c",
     "client_registration_state": "a32862d66eb57246321fb6b229e83786745c3afdf8957ebe38b01c17571ba10570617373776f7264",
-    "client_login_state": "a32862d66eb57246321fb6b229e83786745c3afdf8957ebe38b01c17571ba105006714ba86e53018ce5507d2bfb2d98ad3f60e302d826bff3410a5ec669c8e1ef17d43497a6f86ba31a6a7f399271fb3b4b4f82c4af086bc431ebaeab7d768ff8a640005696e666f315260ad6eb47ac1db44babcce9327327d50e1a0133c0425acca3efcf41b11971800401526e0bed9af28830da956589d65768ed2a20d9689e82c90b89e4e33904e400943497a6f86ba31a6a7f399271fb3b4b4f82c4af086bc431ebaeab7d768ff8a6470617373776f7264",
-    "server_registration_state": "0851f5ec43e0b702bfcb9d8ec208085b51c0fc5200234901407c176327aa0b02",
-    "server_login_state": "89cd93dce8f59cf7b187736c50cbc3ca2e0bbbad0a0be1ddee180a2d95db60695b384be2dd673434ef94bcdbb1b457f63f41cd79ed2422c021c34ca1433b70576920ebb950ad1c40c8c015ec6832e12427e755ba21d005f0b6d5d66d2368ab5ec6f32151fced5a3aa25472c425912242de3638ef57f28b0dd02a956064e5bb9b8fcd73add52d233a454d6b20125e9506a95aae8772ebbfac4d70efe1fb10078fd40f93d84aa7db53853ca74436c917c427cd5c2e408860937e6f7ab80816ca47",
-    "password_file": "0851f5ec43e0b702bfcb9d8ec208085b51c0fc5200234901407c176327aa0b026e0a6082dd29936c44b47ecb8a5fe72e4b321a0ac314b0080ca4c48afdabd2150278b006042d011bdca5d0058a978d2103a6d546de311a2e8cd025fbc67303a4687618b32fe2ec2a5c2b6efecec1e6e535106de80af68733673daf0b644965966fa3f279e532d7ecef363f8d55ff6df4c473cfb1049a73f632972bfcc6744185d13a671dd6678d49fb1629a6fbfbe266937378fd9e772c2dd72692d1a35c020010",
+    "client_login_state": "a32862d66eb57246321fb6b229e83786745c3afdf8957ebe38b01c17571ba105006714ba86e53018ce5507d2bfb2d98ad3f60e302d826bff3410a5ec669c8e1ef17d43497a6f86ba31a6a7f399271fb3b4b4f82c4af086bc431ebaeab7d768ff8a640005696e666f315260ad6eb47ac1db44babcce9327327d50e1a0133c0425acca3efcf41b119718000000401526e0bed9af28830da956589d65768ed2a20d9689e82c90b89e4e33904e400943497a6f86ba31a6a7f399271fb3b4b4f82c4af086bc431ebaeab7d768ff8a6470617373776f7264",
+    "server_registration_state": "",
+    "server_login_state": "0c6699dd871695a629fb13c12d03286594d1d69024183055c205445eac5b0b26903100145be44de34815908f493098bb3adeb75a35843a6f581c44830f7e23993ec858e1ec191dc42bf46f53bc028838322eedd6b34cc08bda5306769259c75162e4f403d8913117e7a45860adee52cfac6447ada8a8493d76f19455bb930385c588e913d7fe34eae730c8e4c8571a616cddb27b3edfa8a01c8803fe4ce582cd3a979314ca17f4821f0998ed642630776b62be4c50f637194e21e41e73f8c961",
+    "password_file": "6e0a6082dd29936c44b47ecb8a5fe72e4b321a0ac314b0080ca4c48afdabd2150278b006042d011bdca5d0058a978d2103a6d546de311a2e8cd025fbc67303a4687618b32fe2ec2a5c2b6efecec1e6e535106de80af68733673daf0b644965966fa3f279e532d7ecef363f8d55ff6df4c473cfb1049a73f632972bfcc6744185d13a671dd6678d49fb1629a6fbfbe266937378fd9e772c2dd72692d1a35c020010",
     "export_key": "8197f91f0d4de1ab126d8dfd06abd0d5df420ce40a135ef376e4ffe515930f413632390e7dc3dcfd19afff62b9113e10eb6c359fc327df6e9ad4d0f06c242322",
-    "session_key": "8fcd73add52d233a454d6b20125e9506a95aae8772ebbfac4d70efe1fb10078fd40f93d84aa7db53853ca74436c917c427cd5c2e408860937e6f7ab80816ca47"
+    "session_key": "c588e913d7fe34eae730c8e4c8571a616cddb27b3edfa8a01c8803fe4ce582cd3a979314ca17f4821f0998ed642630776b62be4c50f637194e21e41e73f8c961"
 }
 "#;
 
 fn decode(values: &Value, key: &str) -> Option<Vec<u8>> {
-    values[key]
-        .as_str()
-        .and_then(|s| hex::decode(&s.to_string()).ok())
+    values[key].as_str().and_then(|s| hex::decode(s).ok())
 }
 
 fn populate_test_vectors(values: &Value) -> TestVectorParameters {
     TestVectorParameters {
-        client_s_pk: decode(&values, "client_s_pk").unwrap(),
-        client_s_sk: decode(&values, "client_s_sk").unwrap(),
-        client_e_pk: decode(&values, "client_e_pk").unwrap(),
-        client_e_sk: decode(&values, "client_e_sk").unwrap(),
-        server_s_pk: decode(&values, "server_s_pk").unwrap(),
-        server_s_sk: decode(&values, "server_s_sk").unwrap(),
-        server_e_pk: decode(&values, "server_e_pk").unwrap(),
-        server_e_sk: decode(&values, "server_e_sk").unwrap(),
-        id_u: decode(&values, "id_u").unwrap(),
-        id_s: decode(&values, "id_s").unwrap(),
-        password: decode(&values, "password").unwrap(),
-        blinding_factor: decode(&values, "blinding_factor").unwrap(),
-        oprf_key: decode(&values, "oprf_key").unwrap(),
-        envelope_nonce: decode(&values, "envelope_nonce").unwrap(),
-        client_nonce: decode(&values, "client_nonce").unwrap(),
-        server_nonce: decode(&values, "server_nonce").unwrap(),
-        info1: decode(&values, "info1").unwrap(),
-        einfo2: decode(&values, "einfo2").unwrap(),
-        registration_request: decode(&values, "registration_request").unwrap(),
-        registration_response: decode(&values, "registration_response").unwrap(),
-        registration_upload: decode(&values, "registration_upload").unwrap(),
-        credential_request: decode(&values, "credential_request").unwrap(),
-        credential_response: decode(&values, "credential_response").unwrap(),
-        credential_finalization: decode(&values, "credential_finalization").unwrap(),
-        client_registration_state: decode(&values, "client_registration_state").unwrap(),
-        client_login_state: decode(&values, "client_login_state").unwrap(),
-        server_registration_state: decode(&values, "server_registration_state").unwrap(),
-        server_login_state: decode(&values, "server_login_state").unwrap(),
-        password_file: decode(&values, "password_file").unwrap(),
-        export_key: decode(&values, "export_key").unwrap(),
-        session_key: decode(&values, "session_key").unwrap(),
+        client_s_pk: decode(values, "client_s_pk").unwrap(),
+        client_s_sk: decode(values, "client_s_sk").unwrap(),
+        client_e_pk: decode(values, "client_e_pk").unwrap(),
+        client_e_sk: decode(values, "client_e_sk").unwrap(),
+        server_s_pk: decode(values, "server_s_pk").unwrap(),
+        server_s_sk: decode(values, "server_s_sk").unwrap(),
+        server_e_pk: decode(values, "server_e_pk").unwrap(),
+        server_e_sk: decode(values, "server_e_sk").unwrap(),
+        id_u: decode(values, "id_u").unwrap(),
+        id_s: decode(values, "id_s").unwrap(),
+        password: decode(values, "password").unwrap(),
+        blinding_factor: decode(values, "blinding_factor").unwrap(),
+        oprf_key: decode(values, "oprf_key").unwrap(),
+        envelope_nonce: decode(values, "envelope_nonce").unwrap(),
+        client_nonce: decode(values, "client_nonce").unwrap(),
+        server_nonce: decode(values, "server_nonce").unwrap(),
+        info1: decode(values, "info1").unwrap(),
+        einfo2: decode(values, "einfo2").unwrap(),
+        registration_request: decode(values, "registration_request").unwrap(),
+        registration_response: decode(values, "registration_response").unwrap(),
+        registration_upload: decode(values, "registration_upload").unwrap(),
+        credential_request: decode(values, "credential_request").unwrap(),
+        credential_response: decode(values, "credential_response").unwrap(),
+        credential_finalization: decode(values, "credential_finalization").unwrap(),
+        client_registration_state: decode(values, "client_registration_state").unwrap(),
+        client_login_state: decode(values, "client_login_state").unwrap(),
+        server_registration_state: decode(values, "server_registration_state").unwrap(),
+        server_login_state: decode(values, "server_login_state").unwrap(),
+        password_file: decode(values, "password_file").unwrap(),
+        export_key: decode(values, "export_key").unwrap(),
+        session_key: decode(values, "session_key").unwrap(),
     }
 }
 
@@ -270,8 +278,7 @@ fn generate_parameters<CS: CipherSuite>() -> TestVectorParameters {
     let id_u = b"idU";
     let id_s = b"idS";
     let password = b"password";
-    let mut oprf_key_raw = [0u8; 32];
-    rng.fill_bytes(&mut oprf_key_raw);
+    let oprf_key = CS::Group::random_scalar(&mut rng);
     let mut envelope_nonce = [0u8; 32];
     rng.fill_bytes(&mut envelope_nonce);
     let mut client_nonce = vec![0u8; NonceLen::to_usize()];
@@ -301,19 +308,16 @@ fn generate_parameters<CS: CipherSuite>() -> TestVectorParameters {
         .to_vec();
     let client_registration_state = client_registration_start_result.state.serialize().to_vec();
 
-    let mut oprf_key_rng = CycleRng::new(oprf_key_raw.to_vec());
-    let server_registration_start_result = ServerRegistration::<CS>::start(
-        &mut oprf_key_rng,
+    let server_registration_start_result = ServerRegistration::<CS>::start_with_oprf_key(
+        &oprf_key,
         client_registration_start_result.message,
         server_s_kp.public(),
-    )
-    .unwrap();
+    );
     let registration_response_bytes = server_registration_start_result
         .message
         .serialize()
         .to_vec();
-    let oprf_key_bytes =
-        CS::Group::scalar_as_bytes(&server_registration_start_result.state.oprf_key).clone();
+    let oprf_key_bytes = CS::Group::scalar_as_bytes(&oprf_key).clone();
     let server_registration_state = server_registration_start_result.state.serialize().to_vec();
 
     let mut client_s_sk_and_nonce: Vec<u8> = Vec::new();
@@ -326,7 +330,8 @@ fn generate_parameters<CS: CipherSuite>() -> TestVectorParameters {
         .finish(
             &mut finish_registration_rng,
             server_registration_start_result.message,
-            ClientRegistrationFinishParameters::WithIdentifiers(id_u.to_vec(), id_s.to_vec()),
+            ClientRegistrationFinishParameters::default()
+                .with_identifiers(id_u.to_vec(), id_s.to_vec()),
         )
         .unwrap();
     let registration_upload_bytes = client_registration_finish_result
@@ -349,7 +354,7 @@ fn generate_parameters<CS: CipherSuite>() -> TestVectorParameters {
     let client_login_start_result = ClientLogin::<CS>::start(
         &mut client_login_start_rng,
         password,
-        ClientLoginStartParameters::WithInfo(info1.to_vec()),
+        ClientLoginStartParameters::default().with_info(info1.to_vec()),
     )
     .unwrap();
     let credential_request_bytes = client_login_start_result.message.serialize().to_vec();
@@ -362,16 +367,15 @@ fn generate_parameters<CS: CipherSuite>() -> TestVectorParameters {
         ]
         .concat(),
     );
-    let server_login_start_result = ServerLogin::<CS>::start(
+    let server_login_start_result = ServerLogin::<CS>::start_with_oprf_key(
         &mut server_e_sk_and_nonce_rng,
         password_file,
         server_s_kp.private(),
+        &oprf_key,
         client_login_start_result.message,
-        ServerLoginStartParameters::WithInfoAndIdentifiers(
-            einfo2.to_vec(),
-            id_u.to_vec(),
-            id_s.to_vec(),
-        ),
+        ServerLoginStartParameters::default()
+            .with_info(einfo2.to_vec())
+            .with_identifiers(id_u.to_vec(), id_s.to_vec()),
     )
     .unwrap();
     let credential_response_bytes = server_login_start_result.message.serialize().to_vec();
@@ -381,7 +385,7 @@ fn generate_parameters<CS: CipherSuite>() -> TestVectorParameters {
         .state
         .finish(
             server_login_start_result.message,
-            ClientLoginFinishParameters::WithIdentifiers(id_u.to_vec(), id_s.to_vec()),
+            ClientLoginFinishParameters::default().with_identifiers(id_u.to_vec(), id_s.to_vec()),
         )
         .unwrap();
     let credential_finalization_bytes = client_login_finish_result.message.serialize();
@@ -447,13 +451,12 @@ fn test_registration_request() -> Result<(), ProtocolError> {
 #[test]
 fn test_registration_response() -> Result<(), ProtocolError> {
     let parameters = populate_test_vectors(&serde_json::from_str(TEST_VECTOR).unwrap());
-    let mut oprf_key_rng = CycleRng::new(parameters.oprf_key);
     let server_registration_start_result =
-        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start(
-            &mut oprf_key_rng,
+        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start_with_oprf_key(
+            &RistrettoPoint::from_scalar_slice(GenericArray::from_slice(&parameters.oprf_key))?,
             RegistrationRequest::deserialize(&parameters.registration_request[..])?,
-            &Key::from_bytes(&parameters.server_s_pk[..])?,
-        )?;
+            &PublicKey::from_bytes(&parameters.server_s_pk[..])?,
+        );
     assert_eq!(
         hex::encode(parameters.registration_response),
         hex::encode(server_registration_start_result.message.serialize())
@@ -478,7 +481,8 @@ fn test_registration_upload() -> Result<(), ProtocolError> {
     .finish(
         &mut finish_registration_rng,
         RegistrationResponse::deserialize(&parameters.registration_response[..])?,
-        ClientRegistrationFinishParameters::WithIdentifiers(parameters.id_u, parameters.id_s),
+        ClientRegistrationFinishParameters::default()
+            .with_identifiers(parameters.id_u, parameters.id_s),
     )?;
 
     assert_eq!(
@@ -487,7 +491,7 @@ fn test_registration_upload() -> Result<(), ProtocolError> {
     );
     assert_eq!(
         hex::encode(parameters.export_key),
-        hex::encode(result.export_key.to_vec())
+        hex::encode(result.export_key)
     );
 
     Ok(())
@@ -525,7 +529,7 @@ fn test_credential_request() -> Result<(), ProtocolError> {
     let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
         &mut client_login_start_rng,
         &parameters.password,
-        ClientLoginStartParameters::WithInfo(parameters.info1),
+        ClientLoginStartParameters::default().with_info(parameters.info1),
     )?;
     assert_eq!(
         hex::encode(&parameters.credential_request),
@@ -544,19 +548,19 @@ fn test_credential_response() -> Result<(), ProtocolError> {
 
     let mut server_e_sk_and_nonce_rng =
         CycleRng::new([parameters.server_e_sk, parameters.server_nonce].concat());
-    let server_login_start_result = ServerLogin::<RistrettoSha5123dhNoSlowHash>::start(
-        &mut server_e_sk_and_nonce_rng,
-        ServerRegistration::deserialize(&parameters.password_file[..])?,
-        &Key::from_bytes(&parameters.server_s_sk[..])?,
-        CredentialRequest::<RistrettoSha5123dhNoSlowHash>::deserialize(
-            &parameters.credential_request[..],
-        )?,
-        ServerLoginStartParameters::WithInfoAndIdentifiers(
-            parameters.einfo2.to_vec(),
-            parameters.id_u,
-            parameters.id_s,
-        ),
-    )?;
+    let server_login_start_result =
+        ServerLogin::<RistrettoSha5123dhNoSlowHash>::start_with_oprf_key(
+            &mut server_e_sk_and_nonce_rng,
+            ServerRegistration::deserialize(&parameters.password_file[..])?,
+            &PrivateKey::from_bytes(&parameters.server_s_sk[..])?,
+            &RistrettoPoint::from_scalar_slice(GenericArray::from_slice(&parameters.oprf_key))?,
+            CredentialRequest::<RistrettoSha5123dhNoSlowHash>::deserialize(
+                &parameters.credential_request[..],
+            )?,
+            ServerLoginStartParameters::default()
+                .with_info(parameters.einfo2.to_vec())
+                .with_identifiers(parameters.id_u, parameters.id_s),
+        )?;
     assert_eq!(
         hex::encode(&parameters.info1),
         hex::encode(server_login_start_result.plain_info),
@@ -583,7 +587,7 @@ fn test_credential_finalization() -> Result<(), ProtocolError> {
         CredentialResponse::<RistrettoSha5123dhNoSlowHash>::deserialize(
             &parameters.credential_response[..],
         )?,
-        ClientLoginFinishParameters::WithIdentifiers(parameters.id_u, parameters.id_s),
+        ClientLoginFinishParameters::default().with_identifiers(parameters.id_u, parameters.id_s),
     )?;
 
     assert_eq!(
@@ -592,7 +596,7 @@ fn test_credential_finalization() -> Result<(), ProtocolError> {
     );
     assert_eq!(
         hex::encode(&parameters.server_s_pk),
-        hex::encode(&client_login_finish_result.server_s_pk.to_arr().to_vec())
+        hex::encode(client_login_finish_result.server_s_pk.to_arr())
     );
     assert_eq!(
         hex::encode(&parameters.session_key),
@@ -629,24 +633,22 @@ fn test_server_login_finish() -> Result<(), ProtocolError> {
     Ok(())
 }
 
-fn test_complete_flow(
+fn test_complete_flow<CS: CipherSuite>(
     registration_password: &[u8],
     login_password: &[u8],
 ) -> Result<(), ProtocolError> {
     let mut client_rng = OsRng;
     let mut server_rng = OsRng;
-    let server_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut server_rng);
+    let server_kp = CS::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<CS>::generate_random(&mut server_rng);
     let client_registration_start_result =
-        ClientRegistration::<RistrettoSha5123dhNoSlowHash>::start(
-            &mut client_rng,
-            registration_password,
-        )?;
-    let server_registration_start_result =
-        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start(
-            &mut server_rng,
-            client_registration_start_result.message,
-            server_kp.public(),
-        )?;
+        ClientRegistration::<CS>::start(&mut client_rng, registration_password)?;
+    let server_registration_start_result = ServerRegistration::<CS>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"username",
+    )?;
     let client_registration_finish_result = client_registration_start_result.state.finish(
         &mut client_rng,
         server_registration_start_result.message,
@@ -655,16 +657,18 @@ fn test_complete_flow(
     let p_file = server_registration_start_result
         .state
         .finish(client_registration_finish_result.message)?;
-    let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
+    let client_login_start_result = ClientLogin::<CS>::start(
         &mut client_rng,
         login_password,
         ClientLoginStartParameters::default(),
     )?;
-    let server_login_start_result = ServerLogin::<RistrettoSha5123dhNoSlowHash>::start(
+    let server_login_start_result = ServerLogin::<CS>::start(
         &mut server_rng,
-        p_file,
-        &server_kp.private(),
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
         client_login_start_result.message,
+        b"username",
         ServerLoginStartParameters::default(),
     )?;
 
@@ -688,10 +692,12 @@ fn test_complete_flow(
             hex::encode(client_login_finish_result.export_key)
         );
     } else {
-        assert!(match client_login_result {
-            Err(ProtocolError::VerificationError(PakeError::InvalidLoginError)) => true,
-            _ => false,
-        });
+        assert!(matches!(
+            client_login_result,
+            Err(ProtocolError::VerificationError(
+                PakeError::InvalidLoginError
+            ))
+        ));
     }
 
     Ok(())
@@ -699,10 +705,2182 @@ fn test_complete_flow(
 
 #[test]
 fn test_complete_flow_success() -> Result<(), ProtocolError> {
-    test_complete_flow(b"good password", b"good password")
+    test_complete_flow::<RistrettoSha5123dhNoSlowHash>(b"good password", b"good password")
 }
 
 #[test]
 fn test_complete_flow_fail() -> Result<(), ProtocolError> {
-    test_complete_flow(b"good password", b"bad password")
+    test_complete_flow::<RistrettoSha5123dhNoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn test_complete_flow_p256() -> Result<(), ProtocolError> {
+    struct P256Sha2563dhNoSlowHash;
+    impl CipherSuite for P256Sha2563dhNoSlowHash {
+        type Group = p256_::ProjectivePoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha256;
+        type SlowHash = NoOpHash;
+    }
+
+    test_complete_flow::<P256Sha2563dhNoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<P256Sha2563dhNoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "p384")]
+#[test]
+fn test_complete_flow_p384() -> Result<(), ProtocolError> {
+    struct P384Sha3843dhNoSlowHash;
+    impl CipherSuite for P384Sha3843dhNoSlowHash {
+        type Group = p384_::ProjectivePoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha384;
+        type SlowHash = NoOpHash;
+    }
+
+    test_complete_flow::<P384Sha3843dhNoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<P384Sha3843dhNoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "p521")]
+#[test]
+fn test_complete_flow_p521() -> Result<(), ProtocolError> {
+    struct P521Sha5123dhNoSlowHash;
+    impl CipherSuite for P521Sha5123dhNoSlowHash {
+        type Group = p521_::ProjectivePoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha512;
+        type SlowHash = NoOpHash;
+    }
+
+    test_complete_flow::<P521Sha5123dhNoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<P521Sha5123dhNoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "decaf448")]
+#[test]
+fn test_complete_flow_decaf448() -> Result<(), ProtocolError> {
+    struct Decaf448Sha5123dhNoSlowHash;
+    impl CipherSuite for Decaf448Sha5123dhNoSlowHash {
+        type Group = ed448_goldilocks_plus::DecafPoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha512;
+        type SlowHash = NoOpHash;
+    }
+
+    test_complete_flow::<Decaf448Sha5123dhNoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<Decaf448Sha5123dhNoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "decaf448")]
+#[test]
+fn test_complete_flow_decaf448_serialized() -> Result<(), ProtocolError> {
+    struct Decaf448Sha5123dhNoSlowHash;
+    impl CipherSuite for Decaf448Sha5123dhNoSlowHash {
+        type Group = ed448_goldilocks_plus::DecafPoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha512;
+        type SlowHash = NoOpHash;
+    }
+    type CS = Decaf448Sha5123dhNoSlowHash;
+
+    // Every message and state goes through its byte representation, so that
+    // the 56-byte elements and scalars are exercised on the wire
+    let password = b"good password";
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = CS::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<CS>::generate_random(&mut server_rng);
+
+    let client_registration_start_result =
+        ClientRegistration::<CS>::start(&mut client_rng, password)?;
+    let registration_request = RegistrationRequest::<CS>::deserialize(
+        &client_registration_start_result.message.serialize(),
+    )?;
+    assert_eq!(registration_request.serialize().len(), 56);
+    let client_registration =
+        ClientRegistration::<CS>::deserialize(&client_registration_start_result.state.serialize())?;
+    let server_registration_start_result = ServerRegistration::<CS>::start(
+        &oprf_seed,
+        registration_request,
+        server_kp.public(),
+        b"username",
+    )?;
+    let registration_response = RegistrationResponse::<CS>::deserialize(
+        &server_registration_start_result.message.serialize(),
+    )?;
+    let server_registration =
+        ServerRegistration::<CS>::deserialize(&server_registration_start_result.state.serialize())?;
+    let client_registration_finish_result = client_registration.finish(
+        &mut client_rng,
+        registration_response,
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    let registration_upload = RegistrationUpload::<CS>::deserialize(
+        &client_registration_finish_result.message.serialize(),
+    )?;
+    let p_file = ServerRegistration::<CS>::deserialize(
+        &server_registration.finish(registration_upload)?.serialize(),
+    )?;
+
+    let client_login_start_result = ClientLogin::<CS>::start(
+        &mut client_rng,
+        password,
+        ClientLoginStartParameters::default(),
+    )?;
+    let credential_request =
+        CredentialRequest::<CS>::deserialize(&client_login_start_result.message.serialize())?;
+    let client_login =
+        ClientLogin::<CS>::deserialize(&client_login_start_result.state.serialize())?;
+    let server_login_start_result = ServerLogin::<CS>::start(
+        &mut server_rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        credential_request,
+        b"username",
+        ServerLoginStartParameters::default(),
+    )?;
+    let credential_response =
+        CredentialResponse::<CS>::deserialize(&server_login_start_result.message.serialize())?;
+    let server_login =
+        ServerLogin::<CS>::deserialize(&server_login_start_result.state.serialize())?;
+    let client_login_finish_result =
+        client_login.finish(credential_response, ClientLoginFinishParameters::default())?;
+    let credential_finalization =
+        CredentialFinalization::<CS>::deserialize(&client_login_finish_result.message.serialize())?;
+    let server_login_finish_result = server_login.finish(credential_finalization)?;
+
+    assert_eq!(
+        hex::encode(server_login_finish_result.session_key),
+        hex::encode(client_login_finish_result.session_key)
+    );
+    assert_eq!(
+        hex::encode(client_registration_finish_result.export_key),
+        hex::encode(client_login_finish_result.export_key)
+    );
+    Ok(())
+}
+
+struct RistrettoSha512SigmaINoSlowHash;
+impl CipherSuite for RistrettoSha512SigmaINoSlowHash {
+    type Group = RistrettoPoint;
+    type KeyExchange = SigmaI<Schnorr>;
+    type Hash = sha2::Sha512;
+    type SlowHash = NoOpHash;
+}
+
+#[test]
+fn test_complete_flow_sigma_i_ristretto() -> Result<(), ProtocolError> {
+    test_complete_flow::<RistrettoSha512SigmaINoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<RistrettoSha512SigmaINoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "p256")]
+struct P256Sha256SigmaINoSlowHash;
+#[cfg(feature = "p256")]
+impl CipherSuite for P256Sha256SigmaINoSlowHash {
+    type Group = p256_::ProjectivePoint;
+    type KeyExchange = SigmaI<Ecdsa>;
+    type Hash = sha2::Sha256;
+    type SlowHash = NoOpHash;
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn test_complete_flow_sigma_i_p256() -> Result<(), ProtocolError> {
+    test_complete_flow::<P256Sha256SigmaINoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<P256Sha256SigmaINoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "p384")]
+#[test]
+fn test_complete_flow_sigma_i_p384() -> Result<(), ProtocolError> {
+    struct P384Sha384SigmaINoSlowHash;
+    impl CipherSuite for P384Sha384SigmaINoSlowHash {
+        type Group = p384_::ProjectivePoint;
+        type KeyExchange = SigmaI<Ecdsa>;
+        type Hash = sha2::Sha384;
+        type SlowHash = NoOpHash;
+    }
+
+    test_complete_flow::<P384Sha384SigmaINoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<P384Sha384SigmaINoSlowHash>(b"good password", b"bad password")
+}
+
+fn sigma_i_authentication<CS: CipherSuite>() -> Result<(), ProtocolError> {
+    let mut rng = OsRng;
+    let server_kp = CS::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<CS>::generate_random(&mut rng);
+    let password_file_bytes = {
+        let client_registration_start_result =
+            ClientRegistration::<CS>::start(&mut rng, b"password")?;
+        let server_registration_start_result = ServerRegistration::<CS>::start(
+            &oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"alice",
+        )?;
+        let client_registration_finish_result = client_registration_start_result.state.finish(
+            &mut rng,
+            server_registration_start_result.message,
+            ClientRegistrationFinishParameters::default(),
+        )?;
+        server_registration_start_result
+            .state
+            .finish(client_registration_finish_result.message)?
+            .serialize()
+    };
+
+    // The signature of each party is followed by its MAC at the end of its
+    // key exchange message, where the client's are encrypted so that a
+    // flipped bit in either still flips the same bit of the plaintext. The
+    // credential response then ends with the re-seal request.
+    let mac_len = <CS::Hash as Digest>::OutputSize::to_usize();
+    let signature_len = 2 * <CS::Group as Group>::ScalarLen::to_usize();
+
+    let login = |rng: &mut OsRng,
+                 tamper_ke2: Option<usize>,
+                 tamper_ke3: Option<usize>|
+     -> Result<(Vec<u8>, Vec<u8>), ProtocolError> {
+        let client_login_start_result =
+            ClientLogin::<CS>::start(rng, b"password", ClientLoginStartParameters::default())?;
+        let server_login_start_result = ServerLogin::<CS>::start(
+            rng,
+            Some(ServerRegistration::deserialize(&password_file_bytes[..])?),
+            server_kp.private(),
+            &oprf_seed,
+            client_login_start_result.message,
+            b"alice",
+            ServerLoginStartParameters::default().with_info(b"info".to_vec()),
+        )?;
+        let mut credential_response_bytes = server_login_start_result.message.serialize();
+        if let Some(offset) = tamper_ke2 {
+            let index = credential_response_bytes.len() - 1 - offset;
+            credential_response_bytes[index] ^= 1;
+        }
+        let client_login_finish_result = client_login_start_result.state.finish(
+            CredentialResponse::deserialize(&credential_response_bytes[..])?,
+            ClientLoginFinishParameters::default(),
+        )?;
+        assert_eq!(
+            b"info".to_vec(),
+            client_login_finish_result.confidential_info
+        );
+
+        let mut credential_finalization_bytes = client_login_finish_result.message.serialize();
+        if let Some(offset) = tamper_ke3 {
+            let index = credential_finalization_bytes.len() - offset;
+            credential_finalization_bytes[index] ^= 1;
+        }
+        let server_login_finish_result =
+            server_login_start_result
+                .state
+                .finish(CredentialFinalization::deserialize(
+                    &credential_finalization_bytes[..],
+                )?)?;
+        Ok((
+            client_login_finish_result.session_key,
+            server_login_finish_result.session_key,
+        ))
+    };
+
+    let (client_session_key, server_session_key) = login(&mut rng, None, None)?;
+    assert_eq!(client_session_key, server_session_key);
+
+    // A tampered server signature or MAC is rejected by the client, and a
+    // tampered client signature or MAC fails the login on the server
+    for &offset in &[mac_len + signature_len, 1] {
+        assert!(matches!(
+            login(&mut rng, Some(offset), None),
+            Err(ProtocolError::VerificationError(
+                PakeError::KeyExchangeMacValidationError
+            ))
+        ));
+        assert!(matches!(
+            login(&mut rng, None, Some(offset)),
+            Err(ProtocolError::VerificationError(
+                PakeError::InvalidLoginError
+            ))
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_sigma_i_authentication_ristretto() -> Result<(), ProtocolError> {
+    sigma_i_authentication::<RistrettoSha512SigmaINoSlowHash>()
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn test_sigma_i_authentication_p256() -> Result<(), ProtocolError> {
+    sigma_i_authentication::<P256Sha256SigmaINoSlowHash>()
+}
+
+struct RistrettoSha512Hmqv;
+impl CipherSuite for RistrettoSha512Hmqv {
+    type Group = RistrettoPoint;
+    type KeyExchange = HMQV;
+    type Hash = sha2::Sha512;
+    type SlowHash = NoOpHash;
+}
+
+#[test]
+fn test_complete_flow_hmqv() -> Result<(), ProtocolError> {
+    test_complete_flow::<RistrettoSha512Hmqv>(b"good password", b"good password")?;
+    test_complete_flow::<RistrettoSha512Hmqv>(b"good password", b"bad password")?;
+
+    // HMQV drops the server nonce from KE2
+    assert_eq!(
+        <HMQV as KeyExchange<sha2::Sha512, RistrettoPoint>>::ke2_message_size()
+            + NonceLen::to_usize(),
+        <TripleDH as KeyExchange<sha2::Sha512, RistrettoPoint>>::ke2_message_size()
+    );
+    Ok(())
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn test_complete_flow_hmqv_p256() -> Result<(), ProtocolError> {
+    struct P256Sha256HmqvNoSlowHash;
+    impl CipherSuite for P256Sha256HmqvNoSlowHash {
+        type Group = p256_::ProjectivePoint;
+        type KeyExchange = HMQV;
+        type Hash = sha2::Sha256;
+        type SlowHash = NoOpHash;
+    }
+
+    test_complete_flow::<P256Sha256HmqvNoSlowHash>(b"good password", b"good password")?;
+    test_complete_flow::<P256Sha256HmqvNoSlowHash>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "ml-kem")]
+struct RistrettoSha5123dhMlKem768;
+#[cfg(feature = "ml-kem")]
+impl CipherSuite for RistrettoSha5123dhMlKem768 {
+    type Group = RistrettoPoint;
+    type KeyExchange = key_exchange::tripledh_mlkem::TripleDHMlKem768;
+    type Hash = sha2::Sha512;
+    type SlowHash = NoOpHash;
+}
+
+#[cfg(feature = "ml-kem")]
+#[test]
+fn test_complete_flow_tripledh_ml_kem() -> Result<(), ProtocolError> {
+    test_complete_flow::<RistrettoSha5123dhMlKem768>(b"good password", b"good password")?;
+    test_complete_flow::<RistrettoSha5123dhMlKem768>(b"good password", b"bad password")
+}
+
+#[cfg(feature = "ml-kem")]
+#[test]
+fn test_tripledh_ml_kem_tampered_ciphertext() -> Result<(), ProtocolError> {
+    type CS = RistrettoSha5123dhMlKem768;
+    let mut rng = OsRng;
+    let server_kp = CS::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<CS>::generate_random(&mut rng);
+
+    let client_registration_start_result = ClientRegistration::<CS>::start(&mut rng, b"password")?;
+    let server_registration_start_result = ServerRegistration::<CS>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"alice",
+    )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    let password_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    let client_login_start_result =
+        ClientLogin::<CS>::start(&mut rng, b"password", ClientLoginStartParameters::default())?;
+    let server_login_start_result = ServerLogin::<CS>::start(
+        &mut rng,
+        Some(password_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"alice",
+        ServerLoginStartParameters::default(),
+    )?;
+
+    // With an empty e_info, the KEM ciphertext ends right before the e_info
+    // length prefix and the MAC at the end of KE2, which is followed by the
+    // re-seal request
+    let mac_len = <sha2::Sha512 as Digest>::OutputSize::to_usize();
+    let mut credential_response_bytes = server_login_start_result.message.serialize();
+    let index = credential_response_bytes.len() - 1 - mac_len - 3;
+    credential_response_bytes[index] ^= 1;
+
+    let client_login_finish_result = client_login_start_result.state.finish(
+        CredentialResponse::<CS>::deserialize(&credential_response_bytes[..])?,
+        ClientLoginFinishParameters::default(),
+    );
+    assert!(matches!(
+        client_login_finish_result,
+        Err(ProtocolError::VerificationError(
+            PakeError::KeyExchangeMacValidationError
+        ))
+    ));
+    Ok(())
+}
+
+struct RistrettoSha5123dhInternal;
+impl CipherSuite for RistrettoSha5123dhInternal {
+    type Group = RistrettoPoint;
+    type KeyExchange = TripleDH;
+    type Hash = sha2::Sha512;
+    type SlowHash = NoOpHash;
+    const INNER_ENVELOPE_MODE: InnerEnvelopeMode = InnerEnvelopeMode::Internal;
+}
+
+#[test]
+fn test_complete_flow_internal() -> Result<(), ProtocolError> {
+    test_complete_flow::<RistrettoSha5123dhInternal>(b"good password", b"good password")?;
+    test_complete_flow::<RistrettoSha5123dhInternal>(b"good password", b"bad password")
+}
+
+#[test]
+fn test_internal_envelope() -> Result<(), ProtocolError> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = RistrettoSha5123dhInternal::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhInternal>::generate_random(&mut server_rng);
+
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhInternal>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result = ServerRegistration::<RistrettoSha5123dhInternal>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"alice",
+    )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut client_rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default()
+            .with_identifiers(b"alice".to_vec(), b"server".to_vec()),
+    )?;
+    let envelope = &client_registration_finish_result.message.envelope;
+    assert_eq!(InnerEnvelopeMode::Internal, envelope.get_mode());
+
+    // The envelope encrypts no key, so it is shorter than in base mode
+    let envelope_len = envelope.serialize().len();
+    assert_eq!(1 + 32 + 64, envelope_len);
+    let p_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhInternal>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhInternal>::start(
+        &mut server_rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"alice",
+        ServerLoginStartParameters::default()
+            .with_identifiers(b"alice".to_vec(), b"server".to_vec()),
+    )?;
+    let client_login_finish_result = client_login_start_result.state.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default()
+            .with_identifiers(b"alice".to_vec(), b"server".to_vec()),
+    )?;
+    let server_login_finish_result = server_login_start_result
+        .state
+        .finish(client_login_finish_result.message)?;
+    assert_eq!(
+        hex::encode(server_login_finish_result.session_key),
+        hex::encode(client_login_finish_result.session_key)
+    );
+
+    // Records for unregistered users match the length and mode of real ones
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhInternal>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhInternal>::start(
+        &mut server_rng,
+        None,
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"mallory",
+        ServerLoginStartParameters::default()
+            .with_identifiers(b"mallory".to_vec(), b"server".to_vec()),
+    )?;
+    let fake_envelope = &server_login_start_result.message.envelope;
+    assert_eq!(envelope_len, fake_envelope.serialize().len());
+    assert_eq!(InnerEnvelopeMode::Internal, fake_envelope.get_mode());
+    assert!(matches!(
+        client_login_start_result.state.finish(
+            server_login_start_result.message,
+            ClientLoginFinishParameters::default()
+                .with_identifiers(b"mallory".to_vec(), b"server".to_vec()),
+        ),
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+    Ok(())
+}
+
+struct RistrettoSha5123dhExternal;
+impl CipherSuite for RistrettoSha5123dhExternal {
+    type Group = RistrettoPoint;
+    type KeyExchange = TripleDH;
+    type Hash = sha2::Sha512;
+    type SlowHash = NoOpHash;
+    const INNER_ENVELOPE_MODE: InnerEnvelopeMode = InnerEnvelopeMode::External;
+}
+
+#[test]
+fn test_external_keypair() -> Result<(), ProtocolError> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = RistrettoSha5123dhExternal::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhExternal>::generate_random(&mut server_rng);
+    let client_kp = RistrettoSha5123dhExternal::generate_random_keypair(&mut client_rng);
+
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhExternal>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result = ServerRegistration::<RistrettoSha5123dhExternal>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"alice",
+    )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut client_rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default().with_key_pair(client_kp.clone()),
+    )?;
+    assert_eq!(
+        client_kp.public(),
+        &client_registration_finish_result.message.client_s_pk
+    );
+    let envelope_len = client_registration_finish_result
+        .message
+        .envelope
+        .serialize()
+        .len();
+    let p_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    // The key pair is returned after opening the envelope
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhExternal>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhExternal>::start(
+        &mut server_rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"alice",
+        ServerLoginStartParameters::default(),
+    )?;
+    let client_login_finish_result = client_login_start_result.state.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default(),
+    )?;
+    assert_eq!(
+        Some(&client_kp),
+        client_login_finish_result.client_static_keypair.as_ref()
+    );
+    let server_login_finish_result = server_login_start_result
+        .state
+        .finish(client_login_finish_result.message)?;
+    assert_eq!(
+        hex::encode(server_login_finish_result.session_key),
+        hex::encode(client_login_finish_result.session_key)
+    );
+
+    // Records for unregistered users are as long as real ones
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhExternal>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhExternal>::start(
+        &mut server_rng,
+        None,
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"mallory",
+        ServerLoginStartParameters::default(),
+    )?;
+    assert_eq!(
+        envelope_len,
+        server_login_start_result.message.envelope.serialize().len()
+    );
+
+    // The key pair must be supplied in external mode, and only in that mode
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhExternal>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result = ServerRegistration::<RistrettoSha5123dhExternal>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"alice",
+    )?;
+    assert!(matches!(
+        client_registration_start_result.state.finish(
+            &mut client_rng,
+            server_registration_start_result.message,
+            ClientRegistrationFinishParameters::default(),
+        ),
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::IncompatibleEnvelopeModeError
+        )))
+    ));
+
+    // A key pair whose public key is not the one of its private key is
+    // rejected rather than registered
+    let other_kp = RistrettoSha5123dhExternal::generate_random_keypair(&mut client_rng);
+    let mismatched_kp = KeyPair::new(other_kp.public().clone(), client_kp.private().clone())?;
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhExternal>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result = ServerRegistration::<RistrettoSha5123dhExternal>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"alice",
+    )?;
+    assert!(matches!(
+        client_registration_start_result.state.finish(
+            &mut client_rng,
+            server_registration_start_result.message,
+            ClientRegistrationFinishParameters::default().with_key_pair(mismatched_kp),
+        ),
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::MismatchedKeyPairError
+        )))
+    ));
+
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhNoSlowHash>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start(
+            &OprfSeed::generate_random(&mut server_rng),
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"alice",
+        )?;
+    assert!(matches!(
+        client_registration_start_result.state.finish(
+            &mut client_rng,
+            server_registration_start_result.message,
+            ClientRegistrationFinishParameters::default().with_key_pair(client_kp),
+        ),
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::IncompatibleEnvelopeModeError
+        )))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_envelope_secret() -> Result<(), ProtocolError> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut server_rng);
+
+    let mut login =
+        |params: ClientRegistrationFinishParameters<RistrettoSha5123dhNoSlowHash>,
+         identifiers: Option<(Vec<u8>, Vec<u8>)>|
+         -> Result<ClientLoginFinishResult<RistrettoSha5123dhNoSlowHash>, ProtocolError> {
+            let client_registration_start_result = ClientRegistration::<
+                RistrettoSha5123dhNoSlowHash,
+            >::start(
+                &mut client_rng, b"password"
+            )?;
+            let server_registration_start_result =
+                ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start(
+                    &oprf_seed,
+                    client_registration_start_result.message,
+                    server_kp.public(),
+                    b"alice",
+                )?;
+            let client_registration_finish_result = client_registration_start_result.state.finish(
+                &mut client_rng,
+                server_registration_start_result.message,
+                params,
+            )?;
+            let p_file = server_registration_start_result
+                .state
+                .finish(client_registration_finish_result.message)?;
+            let (server_params, login_params) = match identifiers {
+                Some((id_u, id_s)) => (
+                    ServerLoginStartParameters::default()
+                        .with_identifiers(id_u.clone(), id_s.clone()),
+                    ClientLoginFinishParameters::default().with_identifiers(id_u, id_s),
+                ),
+                None => (
+                    ServerLoginStartParameters::default(),
+                    ClientLoginFinishParameters::default(),
+                ),
+            };
+            let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
+                &mut client_rng,
+                b"password",
+                ClientLoginStartParameters::default(),
+            )?;
+            let server_login_start_result = ServerLogin::<RistrettoSha5123dhNoSlowHash>::start(
+                &mut server_rng,
+                Some(p_file),
+                server_kp.private(),
+                &oprf_seed,
+                client_login_start_result.message,
+                b"alice",
+                server_params,
+            )?;
+            client_login_start_result
+                .state
+                .finish(server_login_start_result.message, login_params)
+        };
+
+    // The secret is returned at login, bound to the identifiers
+    let result = login(
+        ClientRegistrationFinishParameters::default()
+            .with_identifiers(b"alice".to_vec(), b"server".to_vec())
+            .with_secret(b"totp seed".to_vec()),
+        Some((b"alice".to_vec(), b"server".to_vec())),
+    )?;
+    assert_eq!(Some(b"totp seed".to_vec()), result.secret);
+
+    let result = login(
+        ClientRegistrationFinishParameters::default().with_secret(Vec::new()),
+        None,
+    )?;
+    assert_eq!(Some(Vec::new()), result.secret);
+
+    let result = login(ClientRegistrationFinishParameters::default(), None)?;
+    assert_eq!(None, result.secret);
+
+    assert!(matches!(
+        login(
+            ClientRegistrationFinishParameters::default()
+                .with_secret(vec![0u8; MAX_SECRET_LEN + 1]),
+            None,
+        ),
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::SecretTooLongError
+        )))
+    ));
+    Ok(())
+}
+
+fn register_for_password_change(
+    rng: &mut OsRng,
+    server_kp: &KeyPair<RistrettoPoint>,
+    oprf_seed: &OprfSeed<RistrettoSha5123dhNoSlowHash>,
+    password: &[u8],
+) -> Result<ServerRegistration<RistrettoSha5123dhNoSlowHash>, ProtocolError> {
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhNoSlowHash>::start(rng, password)?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start(
+            oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"alice",
+        )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)
+}
+
+fn login_after_password_change(
+    rng: &mut OsRng,
+    server_kp: &KeyPair<RistrettoPoint>,
+    oprf_seed: &OprfSeed<RistrettoSha5123dhNoSlowHash>,
+    password_file: ServerRegistration<RistrettoSha5123dhNoSlowHash>,
+    password: &[u8],
+) -> Result<ClientLoginFinishResult<RistrettoSha5123dhNoSlowHash>, ProtocolError> {
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
+        rng,
+        password,
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhNoSlowHash>::start(
+        rng,
+        Some(password_file),
+        server_kp.private(),
+        oprf_seed,
+        client_login_start_result.message,
+        b"alice",
+        ServerLoginStartParameters::default(),
+    )?;
+    client_login_start_result.state.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default(),
+    )
+}
+
+#[test]
+fn test_password_change() -> Result<(), ProtocolError> {
+    let mut rng = OsRng;
+    let server_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut rng);
+    let password_file_bytes =
+        register_for_password_change(&mut rng, &server_kp, &oprf_seed, b"old password")?
+            .serialize();
+    let password_file = || ServerRegistration::deserialize(&password_file_bytes[..]);
+
+    let change = |rng: &mut OsRng,
+                  password_file: Option<ServerRegistration<RistrettoSha5123dhNoSlowHash>>,
+                  old_password: &[u8]|
+     -> Result<
+        (
+            ServerPasswordChange<RistrettoSha5123dhNoSlowHash>,
+            ClientPasswordChangeFinishResult<RistrettoSha5123dhNoSlowHash>,
+        ),
+        ProtocolError,
+    > {
+        let client_start_result = ClientPasswordChange::<RistrettoSha5123dhNoSlowHash>::start(
+            rng,
+            old_password,
+            b"new password",
+            ClientLoginStartParameters::default().with_info(b"info".to_vec()),
+        )?;
+        let request_bytes = client_start_result.message.serialize();
+        let server_start_result = ServerPasswordChange::<RistrettoSha5123dhNoSlowHash>::start(
+            rng,
+            password_file,
+            server_kp.private(),
+            &oprf_seed,
+            PasswordChangeRequest::deserialize(&request_bytes[..])?,
+            b"alice",
+            ServerLoginStartParameters::default(),
+        )?;
+        assert_eq!(b"info".to_vec(), server_start_result.plain_info);
+        let response_bytes = server_start_result.message.serialize();
+        let client_finish_result = client_start_result.state.finish(
+            rng,
+            PasswordChangeResponse::deserialize(&response_bytes[..])?,
+            ClientLoginFinishParameters::default(),
+            ClientRegistrationFinishParameters::default(),
+        )?;
+        Ok((server_start_result.state, client_finish_result))
+    };
+
+    // A wrong old password is rejected by the client before anything is uploaded
+    assert!(matches!(
+        change(&mut rng, Some(password_file()?), b"wrong password"),
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+
+    // So is a password change for an unregistered user
+    assert!(matches!(
+        change(&mut rng, None, b"old password"),
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+
+    // A registration upload which is not bound to the session is rejected
+    let (server_state, client_finish_result) =
+        change(&mut rng, Some(password_file()?), b"old password")?;
+    let mut finalization_bytes = client_finish_result.message.serialize();
+    let last = finalization_bytes.len() - 1;
+    finalization_bytes[last] ^= 1;
+    assert!(matches!(
+        PasswordChangeFinalization::deserialize(&finalization_bytes[..])
+            .and_then(|message| server_state.finish(message)),
+        Err(ProtocolError::VerificationError(
+            PakeError::KeyExchangeMacValidationError
+        ))
+    ));
+
+    let (server_state, client_finish_result) =
+        change(&mut rng, Some(password_file()?), b"old password")?;
+    let finalization_bytes = client_finish_result.message.serialize();
+    let server_finish_result = server_state.finish(PasswordChangeFinalization::deserialize(
+        &finalization_bytes[..],
+    )?)?;
+    assert_eq!(
+        client_finish_result.session_key,
+        server_finish_result.session_key
+    );
+
+    // The new password file accepts the new password only
+    let new_password_file_bytes = server_finish_result.password_file.serialize();
+    let login_result = login_after_password_change(
+        &mut rng,
+        &server_kp,
+        &oprf_seed,
+        ServerRegistration::deserialize(&new_password_file_bytes[..])?,
+        b"new password",
+    )?;
+    assert_eq!(client_finish_result.export_key, login_result.export_key);
+    assert!(matches!(
+        login_after_password_change(
+            &mut rng,
+            &server_kp,
+            &oprf_seed,
+            ServerRegistration::deserialize(&new_password_file_bytes[..])?,
+            b"old password",
+        ),
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+
+    // The old password file is untouched until the server commits the new one
+    login_after_password_change(
+        &mut rng,
+        &server_kp,
+        &oprf_seed,
+        password_file()?,
+        b"old password",
+    )?;
+    Ok(())
+}
+
+fn login_with_key_set(
+    rng: &mut OsRng,
+    server_keys: &ServerKeySet<RistrettoSha5123dhNoSlowHash>,
+    oprf_seed: &OprfSeed<RistrettoSha5123dhNoSlowHash>,
+    password_file: Option<ServerRegistration<RistrettoSha5123dhNoSlowHash>>,
+    password: &[u8],
+) -> Result<ClientLoginFinishResult<RistrettoSha5123dhNoSlowHash>, ProtocolError> {
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
+        rng,
+        password,
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result =
+        ServerLogin::<RistrettoSha5123dhNoSlowHash>::start_with_key_set(
+            rng,
+            password_file,
+            server_keys,
+            oprf_seed,
+            client_login_start_result.message,
+            b"alice",
+            ServerLoginStartParameters::default(),
+        )?;
+    let credential_response_bytes = server_login_start_result.message.serialize();
+
+    // The re-seal request is the last byte of the credential response, and
+    // takes no other value than 0 or 1
+    assert_eq!(
+        Some(&(server_login_start_result.reseal as u8)),
+        credential_response_bytes.last()
+    );
+    let mut invalid_credential_response_bytes = credential_response_bytes.clone();
+    if let Some(reseal_byte) = invalid_credential_response_bytes.last_mut() {
+        *reseal_byte = 2;
+    }
+    assert!(matches!(
+        CredentialResponse::<RistrettoSha5123dhNoSlowHash>::deserialize(
+            &invalid_credential_response_bytes[..]
+        ),
+        Err(ProtocolError::VerificationError(
+            PakeError::SerializationError
+        ))
+    ));
+
+    let client_login_finish_result = client_login_start_result.state.finish(
+        CredentialResponse::deserialize(&credential_response_bytes[..])?,
+        ClientLoginFinishParameters::default(),
+    )?;
+    assert_eq!(
+        server_login_start_result.reseal,
+        client_login_finish_result.reseal
+    );
+    server_login_start_result
+        .state
+        .finish(CredentialFinalization::deserialize(
+            &client_login_finish_result.message.serialize()[..],
+        )?)?;
+    Ok(client_login_finish_result)
+}
+
+#[test]
+fn test_server_key_rotation() -> Result<(), ProtocolError> {
+    let mut rng = OsRng;
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut rng);
+    let legacy_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+    let first_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+    let second_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+
+    // A password file from before the key set records no key ID
+    let legacy_password_file_bytes =
+        register_for_password_change(&mut rng, &legacy_kp, &oprf_seed, b"password")?.serialize();
+    let legacy_password_file = || ServerRegistration::deserialize(&legacy_password_file_bytes[..]);
+    assert_eq!(None, legacy_password_file()?.key_id());
+
+    let mut server_keys = ServerKeySet::new(LEGACY_SERVER_KEY_ID, legacy_kp.clone());
+    server_keys.rotate(1, first_kp.clone());
+    assert_eq!(1, server_keys.current_key_id());
+
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhNoSlowHash>::start(&mut rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start_with_key_set(
+            &oprf_seed,
+            client_registration_start_result.message,
+            &server_keys,
+            b"alice",
+        )?;
+    assert_eq!(
+        first_kp.public(),
+        &server_registration_start_result.message.server_s_pk
+    );
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    let password_file_bytes = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?
+        .serialize();
+    let password_file = || ServerRegistration::deserialize(&password_file_bytes[..]);
+    assert_eq!(Some(1), password_file()?.key_id());
+
+    server_keys.rotate(2, second_kp.clone());
+
+    // Password files are opened under the key they were sealed under, without
+    // asking for a re-seal by default
+    let result = login_with_key_set(
+        &mut rng,
+        &server_keys,
+        &oprf_seed,
+        Some(password_file()?),
+        b"password",
+    )?;
+    assert_eq!(first_kp.public(), &result.server_s_pk);
+    assert!(!result.reseal);
+    let result = login_with_key_set(
+        &mut rng,
+        &server_keys,
+        &oprf_seed,
+        Some(legacy_password_file()?),
+        b"password",
+    )?;
+    assert_eq!(legacy_kp.public(), &result.server_s_pk);
+    assert!(!result.reseal);
+
+    server_keys.set_request_reseal(true);
+    let result = login_with_key_set(
+        &mut rng,
+        &server_keys,
+        &oprf_seed,
+        Some(password_file()?),
+        b"password",
+    )?;
+    assert!(result.reseal);
+
+    // Unregistered users get a response under the current key
+    assert!(matches!(
+        login_with_key_set(&mut rng, &server_keys, &oprf_seed, None, b"password"),
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+
+    // The key exchange authenticates the registration response, so that a
+    // server static public key substituted into it is rejected by the client
+    // before it seals a new envelope, even when asked to re-seal
+    let attacker_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+    let client_start_result = ClientPasswordChange::<RistrettoSha5123dhNoSlowHash>::start(
+        &mut rng,
+        b"password",
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_start_result =
+        ServerPasswordChange::<RistrettoSha5123dhNoSlowHash>::start_with_key_set(
+            &mut rng,
+            Some(password_file()?),
+            &server_keys,
+            &oprf_seed,
+            client_start_result.message,
+            b"alice",
+            ServerLoginStartParameters::default(),
+        )?;
+    let mut response_bytes = server_start_result.message.serialize();
+    response_bytes[32..64].copy_from_slice(&attacker_kp.public().to_arr());
+    assert!(matches!(
+        client_start_result.state.finish(
+            &mut rng,
+            PasswordChangeResponse::deserialize(&response_bytes[..])?,
+            ClientLoginFinishParameters::default(),
+            ClientRegistrationFinishParameters::default(),
+        ),
+        Err(ProtocolError::VerificationError(
+            PakeError::KeyExchangeMacValidationError
+        ))
+    ));
+
+    // The client re-seals with a password change to the same password
+    let client_start_result = ClientPasswordChange::<RistrettoSha5123dhNoSlowHash>::start(
+        &mut rng,
+        b"password",
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_start_result =
+        ServerPasswordChange::<RistrettoSha5123dhNoSlowHash>::start_with_key_set(
+            &mut rng,
+            Some(password_file()?),
+            &server_keys,
+            &oprf_seed,
+            client_start_result.message,
+            b"alice",
+            ServerLoginStartParameters::default(),
+        )?;
+    let client_finish_result = client_start_result.state.finish(
+        &mut rng,
+        server_start_result.message,
+        ClientLoginFinishParameters::default(),
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    let resealed_password_file = server_start_result
+        .state
+        .finish(client_finish_result.message)?
+        .password_file;
+    assert_eq!(Some(2), resealed_password_file.key_id());
+
+    let result = login_with_key_set(
+        &mut rng,
+        &server_keys,
+        &oprf_seed,
+        Some(resealed_password_file),
+        b"password",
+    )?;
+    assert_eq!(second_kp.public(), &result.server_s_pk);
+    assert!(!result.reseal);
+
+    // Password files sealed under a removed key can no longer log in
+    assert_eq!(Some(first_kp), server_keys.remove(1));
+    assert!(server_keys.remove(2).is_none());
+    assert!(matches!(
+        login_with_key_set(
+            &mut rng,
+            &server_keys,
+            &oprf_seed,
+            Some(password_file()?),
+            b"password",
+        ),
+        Err(ProtocolError::VerificationError(
+            PakeError::UnknownServerKeyError
+        ))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_tampered_key_exchange_messages() -> Result<(), ProtocolError> {
+    let mut rng = OsRng;
+    let server_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut rng);
+    let password_file_bytes =
+        register_for_password_change(&mut rng, &server_kp, &oprf_seed, b"password")?.serialize();
+
+    let ke2_len = <TripleDH as KeyExchange<sha2::Sha512, RistrettoPoint>>::ke2_message_size();
+    let e_info = b"confidential info";
+    let mac_len = <sha2::Sha512 as Digest>::OutputSize::to_usize();
+
+    let login = |rng: &mut OsRng,
+                 tamper_ke2: Option<usize>,
+                 tamper_ke3: Option<usize>|
+     -> Result<ServerLoginFinishResult, ProtocolError> {
+        let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
+            rng,
+            b"password",
+            ClientLoginStartParameters::default(),
+        )?;
+        let server_login_start_result = ServerLogin::<RistrettoSha5123dhNoSlowHash>::start(
+            rng,
+            Some(ServerRegistration::deserialize(&password_file_bytes[..])?),
+            server_kp.private(),
+            &oprf_seed,
+            client_login_start_result.message,
+            b"alice",
+            ServerLoginStartParameters::default().with_info(e_info.to_vec()),
+        )?;
+
+        // The Ke2Message is at the end of the credential response, right
+        // before the re-seal request
+        let mut credential_response_bytes = server_login_start_result.message.serialize();
+        let ke2_start = credential_response_bytes.len() - 1 - ke2_len - 2 - e_info.len();
+        if let Some(offset) = tamper_ke2 {
+            credential_response_bytes[ke2_start + offset] ^= 1;
+        }
+        let client_login_finish_result = client_login_start_result.state.finish(
+            CredentialResponse::deserialize(&credential_response_bytes[..])?,
+            ClientLoginFinishParameters::default(),
+        )?;
+        assert_eq!(
+            e_info.to_vec(),
+            client_login_finish_result.confidential_info
+        );
+
+        let mut credential_finalization_bytes = client_login_finish_result.message.serialize();
+        if let Some(offset) = tamper_ke3 {
+            credential_finalization_bytes[offset] ^= 1;
+        }
+        server_login_start_result
+            .state
+            .finish(CredentialFinalization::deserialize(
+                &credential_finalization_bytes[..],
+            )?)
+    };
+
+    login(&mut rng, None, None)?;
+
+    // Tampering with the server nonce, the encrypted info or the server MAC
+    // of the Ke2Message fails on the client
+    let ke2_mac_start = ke2_len - mac_len + 2 + e_info.len();
+    for &offset in &[
+        0,
+        ke2_mac_start - 1,
+        ke2_mac_start,
+        ke2_mac_start + mac_len - 1,
+    ] {
+        assert!(matches!(
+            login(&mut rng, Some(offset), None),
+            Err(ProtocolError::VerificationError(
+                PakeError::KeyExchangeMacValidationError
+            ))
+        ));
+    }
+
+    // Tampering with the client MAC of the Ke3Message fails the login on the
+    // server
+    for &offset in &[0, mac_len - 1] {
+        assert!(matches!(
+            login(&mut rng, None, Some(offset)),
+            Err(ProtocolError::VerificationError(
+                PakeError::InvalidLoginError
+            ))
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_oprf_key_derivation() -> Result<(), ProtocolError> {
+    let mut rng = OsRng;
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut rng);
+    let restored_seed =
+        OprfSeed::<RistrettoSha5123dhNoSlowHash>::deserialize(&oprf_seed.serialize()[..])?;
+
+    let alice_key = oprf_seed.derive_oprf_key(b"alice")?;
+    assert_eq!(alice_key, restored_seed.derive_oprf_key(b"alice")?);
+    assert_ne!(alice_key, oprf_seed.derive_oprf_key(b"bob")?);
+
+    let other_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut rng);
+    assert_ne!(alice_key, other_seed.derive_oprf_key(b"alice")?);
+
+    // Known answer, computed independently from the construction of
+    // https://www.rfc-editor.org/rfc/rfc9497 and
+    // https://www.rfc-editor.org/rfc/rfc9807
+    let fixed_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::deserialize(&[0xa3; 64])?;
+    assert_eq!(
+        "be648a8ec6acd9c579c0c79257118098a53043c74670c70ec5aa0b1cb549fe0c",
+        hex::encode(fixed_seed.derive_oprf_key(b"alice")?.as_bytes())
+    );
+    Ok(())
+}
+
+#[test]
+fn test_login_with_wrong_credential_identifier() -> Result<(), ProtocolError> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut server_rng);
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhNoSlowHash>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start(
+            &oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"alice",
+        )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut client_rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    let p_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    // The password file is looked up correctly, but the OPRF key is derived
+    // under a different identifier
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhNoSlowHash>::start(
+        &mut server_rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"bob",
+        ServerLoginStartParameters::default(),
+    )?;
+    let client_login_result = client_login_start_result.state.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default(),
+    );
+    assert!(matches!(
+        client_login_result,
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+    Ok(())
+}
+
+// Runs login start for a credential identifier which has no password file,
+// returning the client's view of the response along with the server state
+fn unregistered_login_start(
+    server_kp: &KeyPair<RistrettoPoint>,
+    oprf_seed: &OprfSeed<RistrettoSha5123dhNoSlowHash>,
+    params: ServerLoginStartParameters,
+) -> Result<
+    (
+        ClientLogin<RistrettoSha5123dhNoSlowHash>,
+        ServerLoginStartResult<RistrettoSha5123dhNoSlowHash>,
+    ),
+    ProtocolError,
+> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhNoSlowHash>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhNoSlowHash>::start(
+        &mut server_rng,
+        None,
+        server_kp.private(),
+        oprf_seed,
+        client_login_start_result.message,
+        b"mallory",
+        params,
+    )?;
+    Ok((client_login_start_result.state, server_login_start_result))
+}
+
+#[test]
+fn test_login_with_unregistered_user() -> Result<(), ProtocolError> {
+    let mut rng = OsRng;
+    let server_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut rng);
+
+    let (client_login, server_login_start_result) = unregistered_login_start(
+        &server_kp,
+        &oprf_seed,
+        ServerLoginStartParameters::default(),
+    )?;
+    let client_login_result = client_login.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default(),
+    );
+    assert!(matches!(
+        client_login_result,
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+
+    // Whatever the client sends back, the server rejects it in the same way
+    // as it would for a wrong password
+    let forged_message =
+        CredentialFinalization::<RistrettoSha5123dhNoSlowHash>::deserialize(&[0u8; 64][..])?;
+    assert!(matches!(
+        server_login_start_result.state.finish(forged_message),
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_unregistered_user_response_is_consistent() -> Result<(), ProtocolError> {
+    let mut rng = OsRng;
+    let server_kp = RistrettoSha5123dhNoSlowHash::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhNoSlowHash>::generate_random(&mut rng);
+
+    // The fake envelope does not change between login attempts
+    let (_, first) = unregistered_login_start(
+        &server_kp,
+        &oprf_seed,
+        ServerLoginStartParameters::default(),
+    )?;
+    let (_, second) = unregistered_login_start(
+        &server_kp,
+        &oprf_seed,
+        ServerLoginStartParameters::default(),
+    )?;
+    assert_eq!(
+        hex::encode(first.message.envelope.serialize()),
+        hex::encode(second.message.envelope.serialize())
+    );
+
+    // And it is as long as a real one, in the mode the server expects
+    let mut client_rng = OsRng;
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhNoSlowHash>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhNoSlowHash>::start(
+            &oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"alice",
+        )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut client_rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    assert_eq!(
+        client_registration_finish_result
+            .message
+            .envelope
+            .serialize()
+            .len(),
+        first.message.envelope.serialize().len()
+    );
+
+    let (_, with_identifiers) = unregistered_login_start(
+        &server_kp,
+        &oprf_seed,
+        ServerLoginStartParameters::default()
+            .with_identifiers(b"mallory".to_vec(), b"server".to_vec()),
+    )?;
+    assert!(matches!(
+        with_identifiers.message.envelope.get_identifier_mode(),
+        IdentifierMode::CustomIdentifier
+    ));
+    Ok(())
+}
+
+// A slow hash whose output depends on a runtime parameter, standing in for
+// the cost parameters of a real one. The length of the pepper stands in for
+// its cost.
+struct PepperedHash(Vec<u8>);
+
+impl SlowHash<sha2::Sha512> for PepperedHash {
+    const ALGORITHM_ID: u8 = 0xff;
+
+    fn recommended() -> Self {
+        Self(b"salt".to_vec())
+    }
+
+    fn maximum() -> Self {
+        Self(vec![0u8; 32])
+    }
+
+    fn hash(
+        &self,
+        input: GenericArray<u8, <sha2::Sha512 as Digest>::OutputSize>,
+    ) -> Result<Vec<u8>, InternalPakeError> {
+        Ok(sha2::Sha512::digest(&[&self.0[..], &input[..]].concat()).to_vec())
+    }
+
+    fn serialize_parameters(&self) -> Vec<u8> {
+        self.0.clone()
+    }
+
+    fn deserialize_parameters(input: &[u8]) -> Result<Self, InternalPakeError> {
+        Ok(Self(input.to_vec()))
+    }
+
+    fn meets_minimum(&self, minimum: &Self) -> bool {
+        self.0.len() >= minimum.0.len()
+    }
+
+    fn within_maximum(&self, maximum: &Self) -> bool {
+        self.0.len() <= maximum.0.len()
+    }
+}
+
+struct RistrettoSha5123dhPepperedHash;
+impl CipherSuite for RistrettoSha5123dhPepperedHash {
+    type Group = RistrettoPoint;
+    type KeyExchange = TripleDH;
+    type Hash = sha2::Sha512;
+    type SlowHash = PepperedHash;
+}
+
+// Runs a full registration and login, letting the caller modify the
+// serialized password file in between
+fn test_slow_hash_flow(
+    registration_params: ClientRegistrationFinishParameters<RistrettoSha5123dhPepperedHash>,
+    login_params: ClientLoginFinishParameters<PepperedHash>,
+    tamper: impl FnOnce(&mut Vec<u8>),
+) -> Result<ClientLoginFinishResult<RistrettoSha5123dhPepperedHash>, ProtocolError> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = RistrettoSha5123dhPepperedHash::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhPepperedHash>::generate_random(&mut server_rng);
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhPepperedHash>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhPepperedHash>::start(
+            &oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"username",
+        )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut client_rng,
+        server_registration_start_result.message,
+        registration_params,
+    )?;
+    let mut p_file_bytes = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?
+        .serialize();
+    tamper(&mut p_file_bytes);
+    let p_file = ServerRegistration::deserialize(&p_file_bytes)?;
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhPepperedHash>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhPepperedHash>::start(
+        &mut server_rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"username",
+        ServerLoginStartParameters::default(),
+    )?;
+    client_login_start_result
+        .state
+        .finish(server_login_start_result.message, login_params)
+}
+
+#[test]
+fn test_slow_hash_instance() -> Result<(), ProtocolError> {
+    let pepper = b"pepper".to_vec();
+    test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default().with_slow_hash(PepperedHash(pepper.clone())),
+        ClientLoginFinishParameters::default().with_slow_hash(PepperedHash(pepper.clone())),
+        |_| {},
+    )?;
+    test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default(),
+        ClientLoginFinishParameters::default(),
+        |_| {},
+    )?;
+
+    // Without stored parameters, logging in with different parameters than
+    // at registration fails
+    let result = test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default(),
+        ClientLoginFinishParameters::default().with_slow_hash(PepperedHash(pepper)),
+        |_| {},
+    );
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_stored_slow_hash_parameters() -> Result<(), ProtocolError> {
+    // The client recovers the parameters chosen at registration
+    test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default()
+            .with_slow_hash(PepperedHash(b"pepper".to_vec())),
+        ClientLoginFinishParameters::default(),
+        |_| {},
+    )?;
+
+    // Stored parameters below the minimum are rejected
+    let result = test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default()
+            .with_slow_hash(PepperedHash(b"salt".to_vec())),
+        ClientLoginFinishParameters::default().with_slow_hash(PepperedHash(b"pepper".to_vec())),
+        |_| {},
+    );
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::WeakSlowHashParametersError
+        )))
+    ));
+
+    // Without an explicit minimum, stored parameters weaker than the
+    // recommended ones are rejected, unless the caller relaxes the minimum
+    let result = test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default().with_slow_hash(PepperedHash(b"pep".to_vec())),
+        ClientLoginFinishParameters::default(),
+        |_| {},
+    );
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::WeakSlowHashParametersError
+        )))
+    ));
+    test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default().with_slow_hash(PepperedHash(b"pep".to_vec())),
+        ClientLoginFinishParameters::default().with_minimum_slow_hash(PepperedHash(Vec::new())),
+        |_| {},
+    )?;
+
+    // Stored parameters above the maximum are rejected before hashing,
+    // whether the maximum is the default or supplied by the caller
+    let result = test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default().with_slow_hash(PepperedHash(vec![0u8; 33])),
+        ClientLoginFinishParameters::default(),
+        |_| {},
+    );
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::ExpensiveSlowHashParametersError
+        )))
+    ));
+    let result = test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default()
+            .with_slow_hash(PepperedHash(b"pepper".to_vec())),
+        ClientLoginFinishParameters::default()
+            .with_maximum_slow_hash(PepperedHash(b"salt".to_vec())),
+        |_| {},
+    );
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::ExpensiveSlowHashParametersError
+        )))
+    ));
+
+    // The password file consists of the client public key, followed by the
+    // envelope mode, nonce, ciphertext and the length-prefixed parameter
+    // block, which starts with the algorithm identifier
+    let block_offset = 32 + 1 + 32 + 32 + 2;
+
+    // Parameters for a different algorithm are rejected
+    let result = test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default()
+            .with_slow_hash(PepperedHash(b"pepper".to_vec())),
+        ClientLoginFinishParameters::default(),
+        |bytes| bytes[block_offset] = 0,
+    );
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::InvalidSlowHashParametersError
+        )))
+    ));
+
+    // Parameters which were modified by the server are detected
+    let result = test_slow_hash_flow(
+        ClientRegistrationFinishParameters::default()
+            .with_slow_hash(PepperedHash(b"pepper".to_vec())),
+        ClientLoginFinishParameters::default(),
+        |bytes| bytes[block_offset + 1] ^= 1,
+    );
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+    Ok(())
+}
+
+// Returns the length of the credential response for a registered user and
+// for an unregistered one
+fn credential_response_lens(
+    oprf_seed: &OprfSeed<RistrettoSha5123dhPepperedHash>,
+    registration_params: ClientRegistrationFinishParameters<RistrettoSha5123dhPepperedHash>,
+) -> Result<(usize, usize), ProtocolError> {
+    let mut rng = OsRng;
+    let server_kp = RistrettoSha5123dhPepperedHash::generate_random_keypair(&mut rng);
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhPepperedHash>::start(&mut rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhPepperedHash>::start(
+            oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"alice",
+        )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut rng,
+        server_registration_start_result.message,
+        registration_params,
+    )?;
+    let p_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    let mut response_len = |p_file, credential_identifier: &[u8]| {
+        let client_login_start_result = ClientLogin::<RistrettoSha5123dhPepperedHash>::start(
+            &mut rng,
+            b"password",
+            ClientLoginStartParameters::default(),
+        )?;
+        let server_login_start_result = ServerLogin::<RistrettoSha5123dhPepperedHash>::start(
+            &mut rng,
+            p_file,
+            server_kp.private(),
+            oprf_seed,
+            client_login_start_result.message,
+            credential_identifier,
+            ServerLoginStartParameters::default(),
+        )?;
+        let len = server_login_start_result.message.serialize().len();
+        let result = client_login_start_result.state.finish(
+            server_login_start_result.message,
+            ClientLoginFinishParameters::default(),
+        );
+        Ok::<_, ProtocolError>((len, result))
+    };
+    let (registered_len, result) = response_len(Some(p_file), b"alice")?;
+    assert!(result.is_ok());
+    let (fake_len, result) = response_len(None, b"bob")?;
+    assert!(matches!(
+        result,
+        Err(ProtocolError::VerificationError(
+            PakeError::InvalidLoginError
+        ))
+    ));
+    Ok((registered_len, fake_len))
+}
+
+#[test]
+fn test_fake_slow_hash_parameters() -> Result<(), ProtocolError> {
+    let mut oprf_seed = OprfSeed::<RistrettoSha5123dhPepperedHash>::generate_random(&mut OsRng);
+
+    // Without configured parameters, the response for an unregistered user
+    // lacks the parameter block
+    let registration_params = || {
+        ClientRegistrationFinishParameters::default()
+            .with_slow_hash(PepperedHash(b"pepper".to_vec()))
+    };
+    let (registered_len, fake_len) = credential_response_lens(&oprf_seed, registration_params())?;
+    assert_ne!(registered_len, fake_len);
+
+    // With the parameters used at registration configured on the seed, the
+    // responses are indistinguishable by length
+    oprf_seed.set_fake_slow_hash(&PepperedHash(b"pepper".to_vec()));
+    let (registered_len, fake_len) = credential_response_lens(&oprf_seed, registration_params())?;
+    assert_eq!(registered_len, fake_len);
+    Ok(())
+}
+
+#[test]
+fn test_fake_secret_len() -> Result<(), ProtocolError> {
+    let mut oprf_seed = OprfSeed::<RistrettoSha5123dhPepperedHash>::generate_random(&mut OsRng);
+    let registration_params =
+        || ClientRegistrationFinishParameters::default().with_secret(vec![7u8; 16]);
+
+    // Without a configured length, the envelope for an unregistered user
+    // holds no secret
+    let (registered_len, fake_len) = credential_response_lens(&oprf_seed, registration_params())?;
+    assert_ne!(registered_len, fake_len);
+
+    // With the length of the stored secrets configured on the seed, the
+    // responses are indistinguishable by length
+    oprf_seed.set_fake_secret_len(16)?;
+    let (registered_len, fake_len) = credential_response_lens(&oprf_seed, registration_params())?;
+    assert_eq!(registered_len, fake_len);
+
+    assert!(matches!(
+        oprf_seed.set_fake_secret_len(MAX_SECRET_LEN + 1),
+        Err(ProtocolError::VerificationError(PakeError::CryptoError(
+            InternalPakeError::SecretTooLongError
+        )))
+    ));
+    Ok(())
+}
+
+#[test]
+fn test_split_finish() -> Result<(), ProtocolError> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = RistrettoSha5123dhPepperedHash::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhPepperedHash>::generate_random(&mut server_rng);
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhPepperedHash>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhPepperedHash>::start(
+            &oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"username",
+        )?;
+    let oprf_result = client_registration_start_result.state.finish_oprf(
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default()
+            .with_slow_hash(PepperedHash(b"pepper".to_vec())),
+    )?;
+    let key_stretch = oprf_result.key_stretch;
+    let stretched_key = std::thread::spawn(move || key_stretch.compute())
+        .join()
+        .unwrap()?;
+    let client_registration_finish_result =
+        oprf_result.state.finish(&mut client_rng, stretched_key)?;
+    let p_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    // The stored parameters carry over to the split login as well
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhPepperedHash>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhPepperedHash>::start(
+        &mut server_rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"username",
+        ServerLoginStartParameters::default(),
+    )?;
+    let oprf_result = client_login_start_result.state.finish_oprf(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default(),
+    )?;
+    let key_stretch = oprf_result.key_stretch;
+    let stretched_key = std::thread::spawn(move || key_stretch.compute())
+        .join()
+        .unwrap()?;
+    let client_login_finish_result = oprf_result.state.finish(stretched_key)?;
+    let server_login_finish_result = server_login_start_result
+        .state
+        .finish(client_login_finish_result.message)?;
+
+    assert_eq!(
+        hex::encode(client_registration_finish_result.export_key),
+        hex::encode(client_login_finish_result.export_key)
+    );
+    assert_eq!(
+        hex::encode(client_login_finish_result.session_key),
+        hex::encode(server_login_finish_result.session_key)
+    );
+    Ok(())
+}
+
+#[test]
+fn test_all_registration_parameters() -> Result<(), ProtocolError> {
+    struct RistrettoSha5123dhExternalPepperedHash;
+    impl CipherSuite for RistrettoSha5123dhExternalPepperedHash {
+        type Group = RistrettoPoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha512;
+        type SlowHash = PepperedHash;
+        const INNER_ENVELOPE_MODE: InnerEnvelopeMode = InnerEnvelopeMode::External;
+    }
+
+    let mut rng = OsRng;
+    let server_kp = RistrettoSha5123dhExternalPepperedHash::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<RistrettoSha5123dhExternalPepperedHash>::generate_random(&mut rng);
+    let client_kp = RistrettoSha5123dhExternalPepperedHash::generate_random_keypair(&mut rng);
+
+    // The identifiers, slow hashing parameters, key pair and secret are all
+    // taken together
+    let client_registration_start_result =
+        ClientRegistration::<RistrettoSha5123dhExternalPepperedHash>::start(&mut rng, b"password")?;
+    let server_registration_start_result =
+        ServerRegistration::<RistrettoSha5123dhExternalPepperedHash>::start(
+            &oprf_seed,
+            client_registration_start_result.message,
+            server_kp.public(),
+            b"alice",
+        )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default()
+            .with_identifiers(b"alice".to_vec(), b"server".to_vec())
+            .with_slow_hash(PepperedHash(b"pepper".to_vec()))
+            .with_key_pair(client_kp.clone())
+            .with_secret(b"totp seed".to_vec()),
+    )?;
+    let envelope = &client_registration_finish_result.message.envelope;
+    assert!(matches!(
+        envelope.get_identifier_mode(),
+        IdentifierMode::CustomIdentifier
+    ));
+    assert!(envelope.get_ksf_parameters().is_some());
+    let p_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    let client_login_start_result = ClientLogin::<RistrettoSha5123dhExternalPepperedHash>::start(
+        &mut rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<RistrettoSha5123dhExternalPepperedHash>::start(
+        &mut rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"alice",
+        ServerLoginStartParameters::default()
+            .with_identifiers(b"alice".to_vec(), b"server".to_vec()),
+    )?;
+    let client_login_finish_result = client_login_start_result.state.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default()
+            .with_identifiers(b"alice".to_vec(), b"server".to_vec())
+            .with_slow_hash(PepperedHash(b"pepper".to_vec())),
+    )?;
+    assert_eq!(
+        client_registration_finish_result.export_key,
+        client_login_finish_result.export_key
+    );
+    assert_eq!(
+        Some(&client_kp),
+        client_login_finish_result.client_static_keypair.as_ref()
+    );
+    assert_eq!(
+        Some(b"totp seed".to_vec()),
+        client_login_finish_result.secret
+    );
+    let server_login_finish_result = server_login_start_result
+        .state
+        .finish(client_login_finish_result.message)?;
+    assert_eq!(
+        client_login_finish_result.session_key,
+        server_login_finish_result.session_key
+    );
+    Ok(())
+}
+
+// Runs a full registration and login with the given slow hashing function,
+// checking that the export keys match
+#[cfg(any(feature = "slow-hash", feature = "pbkdf2"))]
+fn test_complete_flow_with_slow_hash<CS: CipherSuite>(
+    slow_hash: impl Fn() -> CS::SlowHash,
+) -> Result<(), ProtocolError> {
+    let mut client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_kp = CS::generate_random_keypair(&mut server_rng);
+    let oprf_seed = OprfSeed::<CS>::generate_random(&mut server_rng);
+    let client_registration_start_result =
+        ClientRegistration::<CS>::start(&mut client_rng, b"password")?;
+    let server_registration_start_result = ServerRegistration::<CS>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"username",
+    )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut client_rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default().with_slow_hash(slow_hash()),
+    )?;
+    let p_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+    let client_login_start_result = ClientLogin::<CS>::start(
+        &mut client_rng,
+        b"password",
+        ClientLoginStartParameters::default(),
+    )?;
+    let server_login_start_result = ServerLogin::<CS>::start(
+        &mut server_rng,
+        Some(p_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"username",
+        ServerLoginStartParameters::default(),
+    )?;
+    let client_login_finish_result = client_login_start_result.state.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default().with_slow_hash(slow_hash()),
+    )?;
+    assert_eq!(
+        hex::encode(client_registration_finish_result.export_key),
+        hex::encode(client_login_finish_result.export_key)
+    );
+    Ok(())
+}
+
+#[cfg(feature = "slow-hash")]
+#[test]
+fn test_complete_flow_scrypt() -> Result<(), ProtocolError> {
+    use crate::slow_hash::Scrypt;
+
+    struct RistrettoSha5123dhScrypt;
+    impl CipherSuite for RistrettoSha5123dhScrypt {
+        type Group = RistrettoPoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha512;
+        type SlowHash = Scrypt;
+    }
+
+    test_complete_flow_with_slow_hash::<RistrettoSha5123dhScrypt>(|| Scrypt::new(4, 8, 1).unwrap())
+}
+
+#[cfg(feature = "pbkdf2")]
+#[test]
+fn test_complete_flow_pbkdf2() -> Result<(), ProtocolError> {
+    use crate::slow_hash::Pbkdf2;
+
+    struct RistrettoSha5123dhPbkdf2;
+    impl CipherSuite for RistrettoSha5123dhPbkdf2 {
+        type Group = RistrettoPoint;
+        type KeyExchange = TripleDH;
+        type Hash = sha2::Sha512;
+        type SlowHash = Pbkdf2;
+    }
+
+    test_complete_flow_with_slow_hash::<RistrettoSha5123dhPbkdf2>(|| {
+        Pbkdf2::new(1000, Some(b"salt".to_vec())).unwrap()
+    })
+}
+
+fn login_with_context<CS: CipherSuite>(
+    client_context: &[u8],
+    server_context: &[u8],
+) -> Result<(Vec<u8>, Vec<u8>), ProtocolError> {
+    let mut rng = OsRng;
+    let server_kp = CS::generate_random_keypair(&mut rng);
+    let oprf_seed = OprfSeed::<CS>::generate_random(&mut rng);
+    let client_registration_start_result = ClientRegistration::<CS>::start(&mut rng, b"password")?;
+    let server_registration_start_result = ServerRegistration::<CS>::start(
+        &oprf_seed,
+        client_registration_start_result.message,
+        server_kp.public(),
+        b"alice",
+    )?;
+    let client_registration_finish_result = client_registration_start_result.state.finish(
+        &mut rng,
+        server_registration_start_result.message,
+        ClientRegistrationFinishParameters::default(),
+    )?;
+    let password_file = server_registration_start_result
+        .state
+        .finish(client_registration_finish_result.message)?;
+
+    let client_login_start_result = ClientLogin::<CS>::start(
+        &mut rng,
+        b"password",
+        ClientLoginStartParameters::default()
+            .with_info(b"info".to_vec())
+            .with_context(client_context.to_vec()),
+    )?;
+    // The context is kept in the serialized client state
+    let client_login =
+        ClientLogin::<CS>::deserialize(&client_login_start_result.state.serialize())?;
+    let server_login_start_result = ServerLogin::<CS>::start(
+        &mut rng,
+        Some(password_file),
+        server_kp.private(),
+        &oprf_seed,
+        client_login_start_result.message,
+        b"alice",
+        ServerLoginStartParameters::default().with_context(server_context.to_vec()),
+    )?;
+    assert_eq!(b"info".to_vec(), server_login_start_result.plain_info);
+
+    let client_login_finish_result = client_login.finish(
+        server_login_start_result.message,
+        ClientLoginFinishParameters::default(),
+    )?;
+    let server_login_finish_result = server_login_start_result
+        .state
+        .finish(client_login_finish_result.message)?;
+    Ok((
+        client_login_finish_result.session_key,
+        server_login_finish_result.session_key,
+    ))
+}
+
+fn test_login_context<CS: CipherSuite>() -> Result<(), ProtocolError> {
+    for context in &[&b""[..], &b"tls-exporter"[..]] {
+        let (client_session_key, server_session_key) = login_with_context::<CS>(context, context)?;
+        assert_eq!(client_session_key, server_session_key);
+    }
+
+    for (client_context, server_context) in &[
+        (&b"tls-exporter"[..], &b"other exporter"[..]),
+        (&b"tls-exporter"[..], &b""[..]),
+        (&b""[..], &b"tls-exporter"[..]),
+    ] {
+        assert!(matches!(
+            login_with_context::<CS>(client_context, server_context),
+            Err(ProtocolError::VerificationError(
+                PakeError::KeyExchangeMacValidationError
+            ))
+        ));
+    }
+
+    // The longest context fits its length prefix, and a longer one is
+    // rejected by whichever party supplied it
+    let longest_context = vec![0u8; MAX_CONTEXT_LEN];
+    let (client_session_key, server_session_key) =
+        login_with_context::<CS>(&longest_context, &longest_context)?;
+    assert_eq!(client_session_key, server_session_key);
+
+    let long_context = vec![0u8; MAX_CONTEXT_LEN + 1];
+    for (client_context, server_context) in
+        &[(&long_context[..], &b""[..]), (&b""[..], &long_context[..])]
+    {
+        assert!(matches!(
+            login_with_context::<CS>(client_context, server_context),
+            Err(ProtocolError::VerificationError(PakeError::CryptoError(
+                InternalPakeError::ContextTooLongError
+            )))
+        ));
+    }
+    Ok(())
+}
+
+#[test]
+fn test_login_context_tripledh() -> Result<(), ProtocolError> {
+    test_login_context::<RistrettoSha5123dhNoSlowHash>()
+}
+
+#[test]
+fn test_login_context_hmqv() -> Result<(), ProtocolError> {
+    test_login_context::<RistrettoSha512Hmqv>()
+}
+
+#[test]
+fn test_login_context_sigma_i() -> Result<(), ProtocolError> {
+    test_login_context::<RistrettoSha512SigmaINoSlowHash>()
 }
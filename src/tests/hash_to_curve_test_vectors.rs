@@ -0,0 +1,94 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+// Test vectors taken from Appendix J of
+// https://www.ietf.org/archive/id/draft-irtf-cfrg-hash-to-curve-10.txt,
+// with points given in their SEC1 compressed form
+
+use crate::{hash::Hash, map_to_curve::GroupWithMapToCurve};
+
+struct Params {
+    msg: &'static str,
+    point: &'static str,
+}
+
+fn test_hash_to_curve<G: GroupWithMapToCurve, H: Hash>(dst: &str, test_vectors: &[Params]) {
+    for tv in test_vectors {
+        let point = G::map_to_curve::<H>(tv.msg.as_bytes(), dst.as_bytes()).unwrap();
+        assert_eq!(tv.point, hex::encode(point.to_arr()));
+    }
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn test_p256_xmd_sha256_sswu_ro() {
+    // Section J.1.1
+    let test_vectors = [
+        Params {
+            msg: "",
+            point: "032c15230b26dbc6fc9a37051158c95b79656e17a1a920b11394ca91c44247d3e4",
+        },
+        Params {
+            msg: "abc",
+            point: "020bb8b87485551aa43ed54f009230450b492fead5f1cc91658775dac4a3388a0f",
+        },
+        Params {
+            msg: "abcdef0123456789",
+            point: "0365038ac8f2b1def042a5df0b33b1f4eca6bff7cb0f9c6c1526811864e544ed80",
+        },
+    ];
+    test_hash_to_curve::<p256_::ProjectivePoint, sha2::Sha256>(
+        "QUUX-V01-CS02-with-P256_XMD:SHA-256_SSWU_RO_",
+        &test_vectors,
+    );
+}
+
+#[cfg(feature = "p384")]
+#[test]
+fn test_p384_xmd_sha384_sswu_ro() {
+    // Section J.2.1
+    let test_vectors = [
+        Params {
+            msg: "",
+            point: "02eb9fe1b4f4e14e7140803c1d99d0a93cd823d2b024040f9c067a8eca1f5a2eeac9ad604973527a356f3fa3aeff0e4d83",
+        },
+        Params {
+            msg: "abc",
+            point: "02e02fc1a5f44a7519419dd314e29863f30df55a514da2d655775a81d413003c4d4e7fd59af0826dfaad4200ac6f60abe1",
+        },
+        Params {
+            msg: "abcdef0123456789",
+            point: "02bdecc1c1d870624965f19505be50459d363c71a699a496ab672f9a5d6b78676400926fbceee6fcd1780fe86e62b2aa89",
+        },
+    ];
+    test_hash_to_curve::<p384_::ProjectivePoint, sha2::Sha384>(
+        "QUUX-V01-CS02-with-P384_XMD:SHA-384_SSWU_RO_",
+        &test_vectors,
+    );
+}
+
+#[cfg(feature = "p521")]
+#[test]
+fn test_p521_xmd_sha512_sswu_ro() {
+    // Section J.3.1
+    let test_vectors = [
+        Params {
+            msg: "",
+            point: "0300fd767cebb2452030358d0e9cf907f525f50920c8f607889a6a35680727f64f4d66b161fafeb2654bea0d35086bec0a10b30b14adef3556ed9f7f1bc23cecc9c088",
+        },
+        Params {
+            msg: "abc",
+            point: "03002f89a1677b28054b50d15e1f81ed6669b5a2158211118ebdef8a6efc77f8ccaa528f698214e4340155abc1fa08f8f613ef14a043717503d57e267d57155cf784a4",
+        },
+        Params {
+            msg: "abcdef0123456789",
+            point: "02006e200e276a4a81760099677814d7f8794a4a5f3658442de63c18d2244dcc957c645e94cb0754f95fcf103b2aeaf94411847c24187b89fb7462ad3679066337cbc4",
+        },
+    ];
+    test_hash_to_curve::<p521_::ProjectivePoint, sha2::Sha512>(
+        "QUUX-V01-CS02-with-P521_XMD:SHA-512_SSWU_RO_",
+        &test_vectors,
+    );
+}
@@ -4,6 +4,9 @@
 // LICENSE file in the root directory of this source tree.
 
 mod full_test;
+#[cfg(any(feature = "p256", feature = "p384", feature = "p521"))]
+mod hash_to_curve_test_vectors;
 pub mod mock_rng;
 mod opaque_test_vectors;
+mod poprf_test_vectors;
 mod voprf_test_vectors;
@@ -0,0 +1,353 @@
+// Copyright (c) Facebook, Inc. and its affiliates.
+//
+// This source code is licensed under the MIT license found in the
+// LICENSE file in the root directory of this source tree.
+
+use crate::tests::mock_rng::CycleRng;
+use crate::{
+    errors::*,
+    hash::Hash,
+    keypair::{PrivateKey, PublicKey, SizedBytesExt},
+    map_to_curve::PoprfSuite,
+    oprf::{BlindedElement, EvaluationElement, OprfServer, PoprfClient, Proof},
+};
+use curve25519_dalek::ristretto::RistrettoPoint;
+use serde_json::Value;
+use sha2::Sha512;
+
+struct POPRFTestVectorParameters {
+    sksm: Vec<u8>,
+    pksm: Vec<u8>,
+    input: Vec<Vec<u8>>,
+    info: Vec<u8>,
+    blind: Vec<Vec<u8>>,
+    blinded_element: Vec<Vec<u8>>,
+    evaluation_element: Vec<Vec<u8>>,
+    proof: Vec<u8>,
+    proof_random_scalar: Vec<u8>,
+    output: Vec<Vec<u8>>,
+}
+
+// Taken from Appendix A of https://www.rfc-editor.org/rfc/rfc9497, in POPRF
+// mode. Batched values are separated by commas.
+static POPRF_RISTRETTO255_SHA512: &[&str] = &[
+    r#"
+    {
+        "sksm": "145c79c108538421ac164ecbe131942136d5570b16d8bf41a24d4337da981e07",
+        "pksm": "c647bef38497bc6ec077c22af65b696efa43bff3b4a1975a3e8e0a1c5a79d631",
+        "input": "00",
+        "info": "7465737420696e666f",
+        "blind": "64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706",
+        "blinded_element": "c8713aa89241d6989ac142f22dba30596db635c772cbf25021fdd8f3d461f715",
+        "evaluation_element": "1a4b860d808ff19624731e67b5eff20ceb2df3c3c03b906f5693e2078450d874",
+        "proof": "41ad1a291aa02c80b0915fbfbb0c0afa15a57e2970067a602ddb9e8fd6b7100de32e1ecff943a36f0b10e3dae6bd266cdeb8adf825d86ef27dbc6c0e30c52206",
+        "proof_random_scalar": "222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e",
+        "output": "ca688351e88afb1d841fde4401c79efebb2eb75e7998fa9737bd5a82a152406d38bd29f680504e54fd4587eddcf2f37a2617ac2fbd2993f7bdf45442ace7d221"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "145c79c108538421ac164ecbe131942136d5570b16d8bf41a24d4337da981e07",
+        "pksm": "c647bef38497bc6ec077c22af65b696efa43bff3b4a1975a3e8e0a1c5a79d631",
+        "input": "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706",
+        "blinded_element": "f0f0b209dd4d5f1844dac679acc7761b91a2e704879656cb7c201e82a99ab07d",
+        "evaluation_element": "8c3c9d064c334c6991e99f286ea2301d1bde170b54003fb9c44c6d7bd6fc1540",
+        "proof": "4c39992d55ffba38232cdac88fe583af8a85441fefd7d1d4a8d0394cd1de77018bf135c174f20281b3341ab1f453fe72b0293a7398703384bed822bfdeec8908",
+        "proof_random_scalar": "222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e",
+        "output": "7c6557b276a137922a0bcfc2aa2b35dd78322bd500235eb6d6b6f91bc5b56a52de2d65612d503236b321f5d0bebcbc52b64b92e426f29c9b8b69f52de98ae507"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "145c79c108538421ac164ecbe131942136d5570b16d8bf41a24d4337da981e07",
+        "pksm": "c647bef38497bc6ec077c22af65b696efa43bff3b4a1975a3e8e0a1c5a79d631",
+        "input": "00,5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "64d37aed22a27f5191de1c1d69fadb899d8862b58eb4220029e036ec4c1f6706,222a5e897cf59db8145db8d16e597e8facb80ae7d4e26d9881aa6f61d645fc0e",
+        "blinded_element": "c8713aa89241d6989ac142f22dba30596db635c772cbf25021fdd8f3d461f715,423a01c072e06eb1cce96d23acce06e1ea64a609d7ec9e9023f3049f2d64e50c",
+        "evaluation_element": "1a4b860d808ff19624731e67b5eff20ceb2df3c3c03b906f5693e2078450d874,aa1f16e903841036e38075da8a46655c94fc92341887eb5819f46312adfc0504",
+        "proof": "43fdb53be399cbd3561186ae480320caa2b9f36cca0e5b160c4a677b8bbf4301b28f12c36aa8e11e5a7ef551da0781e863a6dc8c0b2bf5a149c9e00621f02006",
+        "proof_random_scalar": "419c4f4f5052c53c45f3da494d2b67b220d02118e0857cdbcf037f9ea84bbe0c",
+        "output": "ca688351e88afb1d841fde4401c79efebb2eb75e7998fa9737bd5a82a152406d38bd29f680504e54fd4587eddcf2f37a2617ac2fbd2993f7bdf45442ace7d221,7c6557b276a137922a0bcfc2aa2b35dd78322bd500235eb6d6b6f91bc5b56a52de2d65612d503236b321f5d0bebcbc52b64b92e426f29c9b8b69f52de98ae507"
+    }
+    "#,
+];
+
+#[cfg(feature = "p256")]
+static POPRF_P256_SHA256: &[&str] = &[
+    r#"
+    {
+        "sksm": "6ad2173efa689ef2c27772566ad7ff6e2d59b3b196f00219451fb2c89ee4dae2",
+        "pksm": "030d7ff077fddeec965db14b794f0cc1ba9019b04a2f4fcc1fa525dedf72e2a3e3",
+        "input": "00",
+        "info": "7465737420696e666f",
+        "blind": "3338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364",
+        "blinded_element": "031563e127099a8f61ed51eeede05d747a8da2be329b40ba1f0db0b2bd9dd4e2c0",
+        "evaluation_element": "02c5e5300c2d9e6ba7f3f4ad60500ad93a0157e6288eb04b67e125db024a2c74d2",
+        "proof": "f8a33690b87736c854eadfcaab58a59b8d9c03b569110b6f31f8bf7577f3fbb85a8a0c38468ccde1ba942be501654adb106167c8eb178703ccb42bccffb9231a",
+        "proof_random_scalar": "f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "output": "193a92520bd8fd1f37accb918040a57108daa110dc4f659abe212636d245c592"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "6ad2173efa689ef2c27772566ad7ff6e2d59b3b196f00219451fb2c89ee4dae2",
+        "pksm": "030d7ff077fddeec965db14b794f0cc1ba9019b04a2f4fcc1fa525dedf72e2a3e3",
+        "input": "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "3338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364",
+        "blinded_element": "021a440ace8ca667f261c10ac7686adc66a12be31e3520fca317643a1eee9dcd4d",
+        "evaluation_element": "0208ca109cbae44f4774fc0bdd2783efdcb868cb4523d52196f700210e777c5de3",
+        "proof": "043a8fb7fc7fd31e35770cabda4753c5bf0ecc1e88c68d7d35a62bf2631e875af4613641be2d1875c31d1319d191c4bbc0d04875f4fd03c31d3d17dd8e069b69",
+        "proof_random_scalar": "f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "output": "1e6d164cfd835d88a31401623549bf6b9b306628ef03a7962921d62bc5ffce8c"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "6ad2173efa689ef2c27772566ad7ff6e2d59b3b196f00219451fb2c89ee4dae2",
+        "pksm": "030d7ff077fddeec965db14b794f0cc1ba9019b04a2f4fcc1fa525dedf72e2a3e3",
+        "input": "00,5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "3338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364,f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "blinded_element": "031563e127099a8f61ed51eeede05d747a8da2be329b40ba1f0db0b2bd9dd4e2c0,03ca4ff41c12fadd7a0bc92cf856732b21df652e01a3abdf0fa8847da053db213c",
+        "evaluation_element": "02c5e5300c2d9e6ba7f3f4ad60500ad93a0157e6288eb04b67e125db024a2c74d2,02f0b6bcd467343a8d8555a99dc2eed0215c71898c5edb77a3d97ddd0dbad478e8",
+        "proof": "8fbd85a32c13aba79db4b42e762c00687d6dbf9c8cb97b2a225645ccb00d9d7580b383c885cdfd07df448d55e06f50f6173405eee5506c0ed0851ff718d13e68",
+        "proof_random_scalar": "350e8040f828bf6ceca27405420cdf3d63cb3aef005f40ba51943c8026877963",
+        "output": "193a92520bd8fd1f37accb918040a57108daa110dc4f659abe212636d245c592,1e6d164cfd835d88a31401623549bf6b9b306628ef03a7962921d62bc5ffce8c"
+    }
+    "#,
+];
+
+#[cfg(feature = "p384")]
+static POPRF_P384_SHA384: &[&str] = &[
+    r#"
+    {
+        "sksm": "5b2690d6954b8fbb159f19935d64133f12770c00b68422559c65431942d721ff79d47d7a75906c30b7818ec0f38b7fb2",
+        "pksm": "02f00f0f1de81e5d6cf18140d4926ffdc9b1898c48dc49657ae36eb1e45deb8b951aaf1f10c82d2eaa6d02aafa3f10d2b6",
+        "input": "00",
+        "info": "7465737420696e666f",
+        "blind": "504650f53df8f16f6861633388936ea23338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364",
+        "blinded_element": "03859b36b95e6564faa85cd3801175eda2949707f6aa0640ad093cbf8ad2f58e762f08b56b2a1b42a64953aaf49cbf1ae3",
+        "evaluation_element": "0220710e2e00306453f5b4f574cb6a512453f35c45080d09373e190c19ce5b185914fbf36582d7e0754bb7c8b683205b91",
+        "proof": "82a17ef41c8b57f1e3122311b4d5cd39a63df0f67443ef18d961f9b659c1601ced8d3c64b294f604319ca80230380d437a49c7af0d620e22116669c008ebb767d90283d573b49cdb49e3725889620924c2c4b047a2a6225a3ba27e640ebddd33",
+        "proof_random_scalar": "803d955f0e073a04aa5d92b3fb739f56f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "output": "0188653cfec38119a6c7dd7948b0f0720460b4310e40824e048bf82a16527303ed449a08caf84272c3bbc972ede797df"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "5b2690d6954b8fbb159f19935d64133f12770c00b68422559c65431942d721ff79d47d7a75906c30b7818ec0f38b7fb2",
+        "pksm": "02f00f0f1de81e5d6cf18140d4926ffdc9b1898c48dc49657ae36eb1e45deb8b951aaf1f10c82d2eaa6d02aafa3f10d2b6",
+        "input": "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "504650f53df8f16f6861633388936ea23338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364",
+        "blinded_element": "03f7efcb4aaf000263369d8a0621cb96b81b3206e99876de2a00699ed4c45acf3969cd6e2319215395955d3f8d8cc1c712",
+        "evaluation_element": "034993c818369927e74b77c400376fd1ae29b6ac6c6ddb776cf10e4fbc487826531b3cf0b7c8ca4d92c7af90c9def85ce6",
+        "proof": "693471b5dff0cd6a5c00ea34d7bf127b2795164e3bdb5f39a1e5edfbd13e443bc516061cd5b8449a473c2ceeccada9f3e5b57302e3d7bc5e28d38d6e3a3056e1e73b6cc030f5180f8a1ffa45aa923ee66d2ad0a07b500f2acc7fb99b5506465c",
+        "proof_random_scalar": "803d955f0e073a04aa5d92b3fb739f56f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "output": "ff2a527a21cc43b251a567382677f078c6e356336aec069dea8ba36995343ca3b33bb5d6cf15be4d31a7e6d75b30d3f5"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "5b2690d6954b8fbb159f19935d64133f12770c00b68422559c65431942d721ff79d47d7a75906c30b7818ec0f38b7fb2",
+        "pksm": "02f00f0f1de81e5d6cf18140d4926ffdc9b1898c48dc49657ae36eb1e45deb8b951aaf1f10c82d2eaa6d02aafa3f10d2b6",
+        "input": "00,5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "504650f53df8f16f6861633388936ea23338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364,803d955f0e073a04aa5d92b3fb739f56f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "blinded_element": "03859b36b95e6564faa85cd3801175eda2949707f6aa0640ad093cbf8ad2f58e762f08b56b2a1b42a64953aaf49cbf1ae3,021a65d618d645f1a20bc33b06deaa7e73d6d634c8a56a3d02b53a732b69a5c53c5a207ea33d5afdcde9a22d59726bce51",
+        "evaluation_element": "0220710e2e00306453f5b4f574cb6a512453f35c45080d09373e190c19ce5b185914fbf36582d7e0754bb7c8b683205b91,02017657b315ec65ef861505e596c8645d94685dd7602cdd092a8f1c1c0194a5d0485fe47d071d972ab514370174cc23f5",
+        "proof": "4a0b2fe96d5b2a046a0447fe079b77859ef11a39a3520d6ff7c626aad9b473b724fb0cf188974ec961710a62162a83e97e0baa9eeada73397032d928b3e97b1ea92ad9458208302be3681b8ba78bcc17745bac00f84e0fdc98a6a8cba009c080",
+        "proof_random_scalar": "a097e722ed2427de86966910acba9f5c350e8040f828bf6ceca27405420cdf3d63cb3aef005f40ba51943c8026877963",
+        "output": "0188653cfec38119a6c7dd7948b0f0720460b4310e40824e048bf82a16527303ed449a08caf84272c3bbc972ede797df,ff2a527a21cc43b251a567382677f078c6e356336aec069dea8ba36995343ca3b33bb5d6cf15be4d31a7e6d75b30d3f5"
+    }
+    "#,
+];
+
+#[cfg(feature = "p521")]
+static POPRF_P521_SHA512: &[&str] = &[
+    r#"
+    {
+        "sksm": "014893130030ce69cf714f536498a02ff6b396888f9bb507985c32928c4427d6d39de10ef509aca4240e8569e3a88debc0d392e3361bcd934cb9bdd59e339dff7b27",
+        "pksm": "0301de8ceb9ffe9237b1bba87c320ea0bebcfc3447fe6f278065c6c69886d692d1126b79b6844f829940ace9b52a5e26882cf7cbc9e57503d4cca3cd834584729f812a",
+        "input": "00",
+        "info": "7465737420696e666f",
+        "blind": "00d1dccf7a51bafaf75d4a866d53d8cafe4d504650f53df8f16f6861633388936ea23338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364",
+        "blinded_element": "020095cff9d7ecf65bdfee4ea92d6e748d60b02de34ad98094f82e25d33a8bf50138ccc2cc633556f1a97d7ea9438cbb394df612f041c485a515849d5ebb2238f2f0e2",
+        "evaluation_element": "0301408e9c5be3ffcc1c16e5ae8f8aa68446223b0804b11962e856af5a6d1c65ebbb5db7278c21db4e8cc06d89a35b6804fb1738a295b691638af77aa1327253f26d01",
+        "proof": "0106a89a61eee9dd2417d2849a8e2167bc5f56e3aed5a3ff23e22511fa1b37a29ed44d1bbfd6907d99cfbc558a56aec709282415a864a281e49dc53792a4a638a0660034306d64be12a94dcea5a6d664cf76681911c8b9a84d49bf12d4893307ec14436bd05f791f82446c0de4be6c582d373627b51886f76c4788256e3da7ec8fa18a86",
+        "proof_random_scalar": "015e80ae32363b32cb76ad4b95a5a34e46bb803d955f0e073a04aa5d92b3fb739f56f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "output": "808ae5b87662eaaf0b39151dd85991b94c96ef214cb14a68bf5c143954882d330da8953a80eea20788e552bc8bbbfff3100e89f9d6e341197b122c46a208733b"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "014893130030ce69cf714f536498a02ff6b396888f9bb507985c32928c4427d6d39de10ef509aca4240e8569e3a88debc0d392e3361bcd934cb9bdd59e339dff7b27",
+        "pksm": "0301de8ceb9ffe9237b1bba87c320ea0bebcfc3447fe6f278065c6c69886d692d1126b79b6844f829940ace9b52a5e26882cf7cbc9e57503d4cca3cd834584729f812a",
+        "input": "5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "00d1dccf7a51bafaf75d4a866d53d8cafe4d504650f53df8f16f6861633388936ea23338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364",
+        "blinded_element": "030112ea89cf9cf589496189eafc5f9eb13c9f9e170d6ecde7c5b940541cb1a9c5cfeec908b67efe16b81ca00d0ce216e34b3d5f46a658d3fd8573d671bdb6515ed508",
+        "evaluation_element": "0200ebc49df1e6fa61f412e6c391e6f074400ecdd2f56c4a8c03fe0f91d9b551f40d4b5258fd891952e8c9b28003bcfa365122e54a5714c8949d5d202767b31b4bf1f6",
+        "proof": "0082162c71a7765005cae202d4bd14b84dae63c29067e886b82506992bd994a1c3aac0c1c5309222fe1af8287b6443ed6df5c2e0b0991faddd3564c73c7597aecd9a003b1f1e3c65f28e58ab4e767cfb4adbcaf512441645f4c2aed8bf67d132d966006d35fa71a34145414bf3572c1de1a46c266a344dd9e22e7fb1e90ffba1caf556d9",
+        "proof_random_scalar": "015e80ae32363b32cb76ad4b95a5a34e46bb803d955f0e073a04aa5d92b3fb739f56f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "output": "27032e24b1a52a82ab7f4646f3c5df0f070f499db98b9c5df33972bd5af5762c3638afae7912a6c1acdb1ae2ab2fa670bd5486c645a0e55412e08d33a4a0d6e3"
+    }
+    "#,
+    r#"
+    {
+        "sksm": "014893130030ce69cf714f536498a02ff6b396888f9bb507985c32928c4427d6d39de10ef509aca4240e8569e3a88debc0d392e3361bcd934cb9bdd59e339dff7b27",
+        "pksm": "0301de8ceb9ffe9237b1bba87c320ea0bebcfc3447fe6f278065c6c69886d692d1126b79b6844f829940ace9b52a5e26882cf7cbc9e57503d4cca3cd834584729f812a",
+        "input": "00,5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a5a",
+        "info": "7465737420696e666f",
+        "blind": "00d1dccf7a51bafaf75d4a866d53d8cafe4d504650f53df8f16f6861633388936ea23338fa65ec36e0290022b48eb562889d89dbfa691d1cde91517fa222ed7ad364,015e80ae32363b32cb76ad4b95a5a34e46bb803d955f0e073a04aa5d92b3fb739f56f9db001266677f62c095021db018cd8cbb55941d4073698ce45c405d1348b7b1",
+        "blinded_element": "020095cff9d7ecf65bdfee4ea92d6e748d60b02de34ad98094f82e25d33a8bf50138ccc2cc633556f1a97d7ea9438cbb394df612f041c485a515849d5ebb2238f2f0e2,0201a328cf9f3fdeb86b6db242dd4cbb436b3a488b70b72d2fbbd1e5f50d7b0878b157d6f278c6a95c488f3ad52d6898a421658a82fe7ceb000b01aedea7967522d525",
+        "evaluation_element": "0301408e9c5be3ffcc1c16e5ae8f8aa68446223b0804b11962e856af5a6d1c65ebbb5db7278c21db4e8cc06d89a35b6804fb1738a295b691638af77aa1327253f26d01,020062ab51ac3aa829e0f5b7ae50688bcf5f63a18a83a6e0da538666b8d50c7ea2b4ef31f4ac669302318dbebe46660acdda695da30c22cee7ca21f6984a720504502e",
+        "proof": "00731738844f739bca0cca9d1c8bea204bed4fd00285785738b985763741de5cdfa275152d52b6a2fdf7792ef3779f39ba34581e56d62f78ecad5b7f8083f384961501cd4b43713253c022692669cf076b1d382ecd8293c1de69ea569737f37a24772ab73517983c1e3db5818754ba1f008076267b8058b6481949ae346cdc17a8455fe2",
+        "proof_random_scalar": "01ec21c7bb69b0734cb48dfd68433dd93b0fa097e722ed2427de86966910acba9f5c350e8040f828bf6ceca27405420cdf3d63cb3aef005f40ba51943c8026877963",
+        "output": "808ae5b87662eaaf0b39151dd85991b94c96ef214cb14a68bf5c143954882d330da8953a80eea20788e552bc8bbbfff3100e89f9d6e341197b122c46a208733b,27032e24b1a52a82ab7f4646f3c5df0f070f499db98b9c5df33972bd5af5762c3638afae7912a6c1acdb1ae2ab2fa670bd5486c645a0e55412e08d33a4a0d6e3"
+    }
+    "#,
+];
+
+fn decode(values: &Value, key: &str) -> Option<Vec<u8>> {
+    values[key].as_str().and_then(|s| hex::decode(s).ok())
+}
+
+fn decode_batch(values: &Value, key: &str) -> Option<Vec<Vec<u8>>> {
+    values[key]
+        .as_str()
+        .and_then(|s| s.split(',').map(|x| hex::decode(x).ok()).collect())
+}
+
+fn populate_test_vectors(values: &Value) -> POPRFTestVectorParameters {
+    POPRFTestVectorParameters {
+        sksm: decode(values, "sksm").unwrap(),
+        pksm: decode(values, "pksm").unwrap(),
+        input: decode_batch(values, "input").unwrap(),
+        info: decode(values, "info").unwrap(),
+        blind: decode_batch(values, "blind").unwrap(),
+        blinded_element: decode_batch(values, "blinded_element").unwrap(),
+        evaluation_element: decode_batch(values, "evaluation_element").unwrap(),
+        proof: decode(values, "proof").unwrap(),
+        proof_random_scalar: decode(values, "proof_random_scalar").unwrap(),
+        output: decode_batch(values, "output").unwrap(),
+    }
+}
+
+type BlindResult<G, H> = (PoprfClient<G, H>, BlindedElement<G>);
+
+fn blind_clients<G: PoprfSuite<H>, H: Hash>(
+    parameters: &POPRFTestVectorParameters,
+) -> Result<Vec<BlindResult<G, H>>, InternalPakeError> {
+    let public_key = PublicKey::from_bytes(&parameters.pksm)?;
+    parameters
+        .input
+        .iter()
+        .zip(parameters.blind.iter())
+        .map(|(input, blind)| {
+            let mut rng = CycleRng::new(blind.to_vec());
+            PoprfClient::<G, H>::blind(input, &parameters.info, &public_key, &mut rng)
+        })
+        .collect()
+}
+
+// Tests input, info, pksm, blind -> blinded_element
+fn test_blind<G: PoprfSuite<H>, H: Hash>(tvs: &[&str]) -> Result<(), InternalPakeError> {
+    for tv in tvs {
+        let parameters = populate_test_vectors(&serde_json::from_str(tv).unwrap());
+        let clients = blind_clients::<G, H>(&parameters)?;
+        for (expected, (_, blinded_element)) in
+            parameters.blinded_element.iter().zip(clients.iter())
+        {
+            assert_eq!(expected, &blinded_element.serialize());
+        }
+    }
+    Ok(())
+}
+
+// Tests sksm, info, blinded_element, proof_random_scalar -> pksm,
+// evaluation_element, proof
+fn test_evaluate<G: PoprfSuite<H>, H: Hash>(tvs: &[&str]) -> Result<(), InternalPakeError> {
+    for tv in tvs {
+        let parameters = populate_test_vectors(&serde_json::from_str(tv).unwrap());
+        let server = OprfServer::<G, H>::new_with_key(&PrivateKey::from_bytes(&parameters.sksm)?)?;
+        assert_eq!(&parameters.pksm, &server.public_key().to_vec());
+
+        let blinded_elements = parameters
+            .blinded_element
+            .iter()
+            .map(|bytes| BlindedElement::deserialize(bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut rng = CycleRng::new(parameters.proof_random_scalar.to_vec());
+        let (evaluation_elements, proof) =
+            server.evaluate_poprf_batch(&blinded_elements, &parameters.info, &mut rng)?;
+
+        for (expected, evaluation_element) in parameters
+            .evaluation_element
+            .iter()
+            .zip(evaluation_elements.iter())
+        {
+            assert_eq!(expected, &evaluation_element.serialize());
+        }
+        assert_eq!(&parameters.proof, &proof.serialize());
+    }
+    Ok(())
+}
+
+// Tests input, info, pksm, blind, evaluation_element, proof -> output
+fn test_finalize<G: PoprfSuite<H>, H: Hash>(tvs: &[&str]) -> Result<(), InternalPakeError> {
+    for tv in tvs {
+        let parameters = populate_test_vectors(&serde_json::from_str(tv).unwrap());
+        let clients: Vec<PoprfClient<G, H>> = blind_clients::<G, H>(&parameters)?
+            .into_iter()
+            .map(|(client, _)| client)
+            .collect();
+        let evaluation_elements = parameters
+            .evaluation_element
+            .iter()
+            .map(|bytes| EvaluationElement::deserialize(bytes))
+            .collect::<Result<Vec<_>, _>>()?;
+        let proof = Proof::deserialize(&parameters.proof)?;
+
+        let outputs = PoprfClient::finalize_batch(&clients, &evaluation_elements, &proof)?;
+        for (expected, output) in parameters.output.iter().zip(outputs.iter()) {
+            assert_eq!(expected, &output.to_vec());
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_poprf_ristretto255_sha512() -> Result<(), InternalPakeError> {
+    test_blind::<RistrettoPoint, Sha512>(POPRF_RISTRETTO255_SHA512)?;
+    test_evaluate::<RistrettoPoint, Sha512>(POPRF_RISTRETTO255_SHA512)?;
+    test_finalize::<RistrettoPoint, Sha512>(POPRF_RISTRETTO255_SHA512)
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn test_poprf_p256_sha256() -> Result<(), InternalPakeError> {
+    test_blind::<p256_::ProjectivePoint, sha2::Sha256>(POPRF_P256_SHA256)?;
+    test_evaluate::<p256_::ProjectivePoint, sha2::Sha256>(POPRF_P256_SHA256)?;
+    test_finalize::<p256_::ProjectivePoint, sha2::Sha256>(POPRF_P256_SHA256)
+}
+
+#[cfg(feature = "p384")]
+#[test]
+fn test_poprf_p384_sha384() -> Result<(), InternalPakeError> {
+    test_blind::<p384_::ProjectivePoint, sha2::Sha384>(POPRF_P384_SHA384)?;
+    test_evaluate::<p384_::ProjectivePoint, sha2::Sha384>(POPRF_P384_SHA384)?;
+    test_finalize::<p384_::ProjectivePoint, sha2::Sha384>(POPRF_P384_SHA384)
+}
+
+#[cfg(feature = "p521")]
+#[test]
+fn test_poprf_p521_sha512() -> Result<(), InternalPakeError> {
+    test_blind::<p521_::ProjectivePoint, Sha512>(POPRF_P521_SHA512)?;
+    test_evaluate::<p521_::ProjectivePoint, Sha512>(POPRF_P521_SHA512)?;
+    test_finalize::<p521_::ProjectivePoint, Sha512>(POPRF_P521_SHA512)
+}